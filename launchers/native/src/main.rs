@@ -1,5 +1,37 @@
+// no console window in release builds, debug builds keep it for the logs
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
 fn main() {
     tracing_subscriber::fmt::init();
 
-    pollster::block_on(asteroids_core::game());
+    if let Ok(path) = std::env::var("ASTEROIDS_REPLAY") {
+        let replay = asteroids_core::Replay::load(path.as_ref()).expect("Failed to load replay");
+        pollster::block_on(asteroids_core::play_replay(replay));
+        return;
+    }
+
+    if std::env::var_os("ASTEROIDS_DAILY").is_some() {
+        pollster::block_on(asteroids_core::game_with_mode(
+            asteroids_core::GameMode::Daily,
+        ));
+        return;
+    }
+    if std::env::var_os("ASTEROIDS_TIME_ATTACK").is_some() {
+        pollster::block_on(asteroids_core::game_with_mode(
+            asteroids_core::GameMode::TimeAttack,
+        ));
+        return;
+    }
+
+    let seed = std::env::var("ASTEROIDS_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok());
+    if let Ok(path) = std::env::var("ASTEROIDS_RECORD") {
+        pollster::block_on(asteroids_core::record_game(seed, path.into()));
+        return;
+    }
+    match seed {
+        Some(seed) => pollster::block_on(asteroids_core::game_with_seed(seed)),
+        None => pollster::block_on(asteroids_core::game()),
+    }
 }
@@ -0,0 +1,66 @@
+use js_sys::{Array, Reflect, JSON};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Document, Request, RequestInit, Response};
+
+/// Number of leaderboard entries shown
+pub const TOP_N: usize = 10;
+
+pub struct Entry {
+    pub name: String,
+    pub score: u64,
+}
+
+/// Client of the leaderboard server
+///
+/// The endpoint takes `{ "name": string, "score": number }` as a JSON POST and answers with the
+/// top scores, a JSON array of the same objects.
+pub struct LeaderboardClient {
+    url: String,
+}
+
+impl LeaderboardClient {
+    /// Endpoint from the `leaderboard-url` meta tag, `None` if the page doesn't set one
+    pub fn from_page(document: &Document) -> Option<Self> {
+        let meta = document
+            .query_selector("meta[name=leaderboard-url]")
+            .ok()??;
+        let url = meta.get_attribute("content")?;
+        (!url.is_empty()).then_some(Self { url })
+    }
+
+    pub async fn submit(&self, name: &str, score: u64) -> Result<Vec<Entry>, JsValue> {
+        let name = String::from(JSON::stringify(&JsValue::from_str(name))?);
+        let body = format!(r#"{{"name":{name},"score":{score}}}"#);
+        let mut init = RequestInit::new();
+        init.method("POST").body(Some(&JsValue::from_str(&body)));
+        let request = Request::new_with_str_and_init(&self.url, &init)?;
+        request.headers().set("Content-Type", "application/json")?;
+
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+        let response: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await?
+            .dyn_into()?;
+        if !response.ok() {
+            return Err(JsValue::from_str(&format!(
+                "leaderboard answered {}",
+                response.status()
+            )));
+        }
+        let json = JsFuture::from(response.json()?).await?;
+        // malformed entries are skipped rather than failing the whole list
+        let entries = Array::from(&json)
+            .iter()
+            .filter_map(|item| {
+                let name = Reflect::get(&item, &"name".into()).ok()?.as_string()?;
+                let score = Reflect::get(&item, &"score".into()).ok()?.as_f64()?;
+                Some(Entry {
+                    name,
+                    score: score as u64,
+                })
+            })
+            .take(TOP_N)
+            .collect();
+        Ok(entries)
+    }
+}
@@ -1,5 +1,271 @@
+mod leaderboard;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use asteroids_core::{GameMode, PageLink, ScoreFeed, Tilt, TouchAction, VirtualKeys};
+use leaderboard::{Entry, LeaderboardClient};
+use wasm_bindgen::{closure::Closure, JsCast};
+
 fn main() {
     tracing_wasm::set_as_global_default();
 
-    wasm_bindgen_futures::spawn_local(asteroids_core::game());
+    let link = PageLink {
+        mode: page_mode(),
+        ..Default::default()
+    };
+    bind_touch_controls(&link.keys);
+    bind_tilt_controls(&link.tilt);
+    show_score(&link.score);
+    submit_personal_bests(&link.score);
+    wasm_bindgen_futures::spawn_local(asteroids_core::game_in_page(link));
+}
+
+/// `?daily` in the address plays the daily challenge, `?time-attack` a time attack
+fn page_mode() -> GameMode {
+    let search = web_sys::window().and_then(|w| w.location().search().ok());
+    match search.as_deref() {
+        Some("?daily") => GameMode::Daily,
+        Some("?time-attack") => GameMode::TimeAttack,
+        _ => GameMode::Endless,
+    }
+}
+
+/// How often the page picks up the score
+const SCORE_POLL_MS: i32 = 100;
+
+/// Copies the score into the `#score` element of the page
+fn show_score(score: &ScoreFeed) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let Some(element) = window.document().and_then(|d| d.get_element_by_id("score")) else {
+        return;
+    };
+    let score = score.clone();
+    let mut shown = None;
+    let callback = Closure::<dyn FnMut()>::new(move || {
+        let value = score.get();
+        if shown != Some(value) {
+            shown = Some(value);
+            element.set_text_content(Some(&value.to_string()));
+        }
+    });
+    if let Err(err) = window.set_interval_with_callback_and_timeout_and_arguments_0(
+        callback.as_ref().unchecked_ref(),
+        SCORE_POLL_MS,
+    ) {
+        tracing::warn!(?err, "Failed to start the score display");
+    }
+    callback.forget();
+}
+
+/// localStorage key of the best score of this browser
+const BEST_SCORE_KEY: &str = "asteroids-best";
+
+/// Posts the final score to the leaderboard whenever a game ends with a new personal best
+///
+/// Does nothing unless the page names a leaderboard endpoint. Network errors are logged, the game
+/// never waits on the request.
+fn submit_personal_bests(score: &ScoreFeed) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let Some(document) = window.document() else {
+        return;
+    };
+    let Some(client) = LeaderboardClient::from_page(&document) else {
+        return;
+    };
+    if let Some(panel) = document.get_element_by_id("leaderboard-panel") {
+        let _ = panel.remove_attribute("hidden");
+    }
+    let client = Rc::new(client);
+    let storage = window.local_storage().ok().flatten();
+    let score = score.clone();
+    let mut was_over = false;
+    let callback = Closure::<dyn FnMut()>::new(move || {
+        let is_over = score.is_game_over();
+        let just_ended = is_over && !was_over;
+        was_over = is_over;
+        if !just_ended {
+            return;
+        }
+        let final_score = score.get();
+        let best = storage
+            .as_ref()
+            .and_then(|s| s.get_item(BEST_SCORE_KEY).ok().flatten())
+            .and_then(|best| best.parse::<u64>().ok())
+            .unwrap_or(0);
+        if final_score <= best {
+            return;
+        }
+        if let Some(storage) = storage.as_ref() {
+            let _ = storage.set_item(BEST_SCORE_KEY, &final_score.to_string());
+        }
+        let name = document
+            .get_element_by_id("player-name")
+            .and_then(|e| e.dyn_into::<web_sys::HtmlInputElement>().ok())
+            .map(|input| input.value())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "anonymous".to_owned());
+        let client = Rc::clone(&client);
+        let document = document.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match client.submit(name.trim(), final_score).await {
+                Ok(entries) => show_leaderboard(&document, &entries),
+                Err(err) => tracing::warn!(?err, "Failed to submit score"),
+            }
+        });
+    });
+    if let Err(err) = window.set_interval_with_callback_and_timeout_and_arguments_0(
+        callback.as_ref().unchecked_ref(),
+        SCORE_POLL_MS,
+    ) {
+        tracing::warn!(?err, "Failed to watch for game over");
+    }
+    callback.forget();
+}
+
+/// Fills the `#leaderboard` list of the page
+fn show_leaderboard(document: &web_sys::Document, entries: &[Entry]) {
+    let Some(list) = document.get_element_by_id("leaderboard") else {
+        return;
+    };
+    list.set_inner_html("");
+    for entry in entries {
+        let Ok(item) = document.create_element("li") else {
+            return;
+        };
+        item.set_text_content(Some(&format!("{} {}", entry.name, entry.score)));
+        let _ = list.append_child(&item);
+    }
+}
+
+/// Hooks the buttons of `index.html` up to `keys`, pages without the buttons only get the keyboard
+fn bind_touch_controls(keys: &VirtualKeys) {
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+        return;
+    };
+    for action in TouchAction::ALL {
+        let id = match action {
+            TouchAction::RotateLeft => "touch-left",
+            TouchAction::RotateRight => "touch-right",
+            TouchAction::Thrust => "touch-thrust",
+            TouchAction::Fire => "touch-fire",
+        };
+        let Some(button) = document.get_element_by_id(id) else {
+            continue;
+        };
+        for (event, held) in [
+            ("touchstart", true),
+            ("touchend", false),
+            ("touchcancel", false),
+        ] {
+            let keys = keys.clone();
+            let callback = Closure::<dyn FnMut(web_sys::Event)>::new(move |e: web_sys::Event| {
+                // keep the browser from scrolling or zooming under the thumb
+                e.prevent_default();
+                keys.set(action, held);
+            });
+            if let Err(err) =
+                button.add_event_listener_with_callback(event, callback.as_ref().unchecked_ref())
+            {
+                tracing::warn!(?err, id, event, "Failed to bind touch control");
+            }
+            // the buttons live as long as the page
+            callback.forget();
+        }
+    }
+}
+
+/// Turns the ship by tilting the device while the `#tilt-toggle` button is on
+///
+/// Reads the left to right tilt of a phone held upright. When the browser has no orientation
+/// sensor or permission is denied the button stays off and the other controls keep working.
+fn bind_tilt_controls(tilt: &Tilt) {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let Some(button) = window
+        .document()
+        .and_then(|d| d.get_element_by_id("tilt-toggle"))
+    else {
+        return;
+    };
+    let enabled = Rc::new(Cell::new(false));
+
+    let on_orientation = {
+        let tilt = tilt.clone();
+        let enabled = Rc::clone(&enabled);
+        Closure::<dyn FnMut(web_sys::DeviceOrientationEvent)>::new(
+            move |e: web_sys::DeviceOrientationEvent| {
+                if enabled.get() {
+                    tilt.set(e.gamma().map(|degrees| degrees as f32));
+                }
+            },
+        )
+    };
+    if let Err(err) = window.add_event_listener_with_callback(
+        "deviceorientation",
+        on_orientation.as_ref().unchecked_ref(),
+    ) {
+        tracing::warn!(?err, "Failed to listen for device orientation");
+        return;
+    }
+    on_orientation.forget();
+
+    let tilt = tilt.clone();
+    let toggle = button.clone();
+    let on_click = Closure::<dyn FnMut()>::new(move || {
+        if enabled.get() {
+            enabled.set(false);
+            tilt.set(None);
+            let _ = toggle.set_attribute("aria-pressed", "false");
+            return;
+        }
+        let enabled = Rc::clone(&enabled);
+        let toggle = toggle.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            if request_orientation_permission().await {
+                enabled.set(true);
+                let _ = toggle.set_attribute("aria-pressed", "true");
+            } else {
+                tracing::warn!("Device orientation is unavailable, tilt controls stay off");
+            }
+        });
+    });
+    if let Err(err) =
+        button.add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref())
+    {
+        tracing::warn!(?err, "Failed to bind the tilt toggle");
+    }
+    on_click.forget();
+}
+
+/// Safari only sends orientation events once `DeviceOrientationEvent.requestPermission()` is
+/// granted, and only asks from a tap. Other browsers lack the function and send them right away.
+async fn request_orientation_permission() -> bool {
+    let Ok(class) = js_sys::Reflect::get(&js_sys::global(), &"DeviceOrientationEvent".into())
+    else {
+        return false;
+    };
+    if class.is_undefined() {
+        return false;
+    }
+    let Ok(request) = js_sys::Reflect::get(&class, &"requestPermission".into())
+        .and_then(|request| request.dyn_into::<js_sys::Function>())
+    else {
+        return true;
+    };
+    let Ok(promise) = request
+        .call0(&class)
+        .and_then(|promise| promise.dyn_into::<js_sys::Promise>())
+    else {
+        return false;
+    };
+    matches!(
+        wasm_bindgen_futures::JsFuture::from(promise).await,
+        Ok(state) if state.as_string().as_deref() == Some("granted")
+    )
 }
@@ -0,0 +1,414 @@
+use brengin::cecs::prelude::*;
+use brengin::glam::{Vec2, Vec3};
+use brengin::renderer::sprite_renderer::{sprite_sheet_bundle, SpriteInstance};
+use brengin::transform::{self, transform_bundle, GlobalTransform, Transform};
+use brengin::DeltaTime;
+use smallvec::SmallVec;
+
+use crate::collision::{aabb_bundle, ColliderKind, CollisionEvent, CollisionTag, Collisions};
+use crate::{
+    deactivate_bullet, hit_player, layers, nearest_ship, spawn_asteroid, spawn_explosion,
+    AngularVelocity, AsteroidKind, GameEntity, Health, Player, Plugin, Pooled, Rng, Score, Shield,
+    Sprites, Threat, TimeScale, Velocity, WrapBehavior, AABB, BOSS_TAG, MISSILE_DAMAGE,
+    MISSILE_TAG, PLAYER_TAG,
+};
+
+/// How the boss shows up, moves and breaks apart
+pub struct BossConfig {
+    /// Every this many waves the wave brings a boss
+    pub wave_interval: u32,
+    pub scale: f32,
+    /// Distance from the player the boss spawns at
+    pub spawn_distance: f32,
+    pub speed: f32,
+    /// How quickly the boss turns towards the player, per second
+    pub steering: f32,
+    /// Radians per second
+    pub spin: f32,
+    pub weak_points: u32,
+    /// Bullet hits each weak point takes
+    pub weak_point_health: u32,
+    pub weak_point_score: u64,
+    /// Awarded when the last weak point goes and the boss cracks
+    pub score: u64,
+    /// Asteroids the boss cracks into
+    pub fragments: u32,
+    pub fragment_speed: f32,
+    /// Speed a shielded ship bounces off the boss with
+    pub knockback: f32,
+}
+
+impl Default for BossConfig {
+    fn default() -> Self {
+        Self {
+            wave_interval: 5,
+            scale: 4.0,
+            spawn_distance: 15.0,
+            speed: 1.0,
+            steering: 0.5,
+            spin: 0.3,
+            weak_points: 4,
+            weak_point_health: 5,
+            weak_point_score: 10,
+            score: 200,
+            fragments: 8,
+            fragment_speed: 2.0,
+            knockback: 8.0,
+        }
+    }
+}
+
+impl BossConfig {
+    pub fn is_boss_wave(&self, wave: u32) -> bool {
+        wave > 0 && wave.is_multiple_of(self.wave_interval)
+    }
+
+    /// Collider size of the core, at scale 1
+    fn core_size(&self) -> f32 {
+        AsteroidKind::Metallic.stats().size
+    }
+}
+
+/// The core of a boss, shots bounce off it until every [WeakPoint] is destroyed
+pub struct Boss;
+
+/// Destructible child of a [Boss]
+pub struct WeakPoint {
+    pub boss: EntityId,
+}
+
+struct HasWeakPoints;
+
+/// World size of a weak point
+const WEAK_POINT_SIZE: f32 = 0.8;
+
+/// Where a boss spawns, `config.spawn_distance` away from `player_pos` in a random direction,
+/// inside the map
+pub fn boss_spawn_point(
+    rng: &mut fastrand::Rng,
+    config: &BossConfig,
+    player_pos: Vec3,
+    map_radius: f32,
+) -> Vec2 {
+    let angle = rng.f32() * std::f32::consts::TAU;
+    let edge = map_radius - config.scale * config.core_size();
+    (player_pos.truncate() + Vec2::new(angle.cos(), angle.sin()) * config.spawn_distance)
+        .clamp(Vec2::splat(-edge), Vec2::splat(edge))
+}
+
+pub fn spawn_boss(
+    cmd: &mut Commands,
+    rng: &mut fastrand::Rng,
+    sprites: &Sprites,
+    config: &BossConfig,
+    pos: Vec2,
+) {
+    cmd.spawn()
+        .insert_bundle(transform_bundle(Transform {
+            pos: pos.extend(layers::ASTEROID),
+            scale: Vec3::splat(config.scale),
+            ..Default::default()
+        }))
+        .insert_bundle(sprite_sheet_bundle(
+            AsteroidKind::Metallic.sheet(sprites),
+            SpriteInstance {
+                index: rng.u32(..AsteroidKind::Metallic.stats().variants),
+                flip: rng.bool(),
+            },
+        ))
+        .insert_bundle(aabb_bundle(
+            AABB::around_origin(Vec2::splat(config.core_size())),
+            BOSS_TAG,
+            ColliderKind::Trigger,
+        ))
+        .insert_bundle((
+            Boss,
+            GameEntity,
+            Threat,
+            Velocity::default(),
+            AngularVelocity(config.spin),
+            WrapBehavior::Wrap,
+        ));
+}
+
+/// Weak points are spawned on bosses that don't have them yet
+fn weak_point_spawn_system(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    config: Res<BossConfig>,
+    q_boss: Query<EntityId, (With<Boss>, WithOut<HasWeakPoints>)>,
+) {
+    // evenly around the rim, half sticking out so shots reach them before the core
+    let radius = config.core_size() * 0.5;
+    for id in q_boss.iter() {
+        for i in 0..config.weak_points {
+            let angle = i as f32 / config.weak_points as f32 * std::f32::consts::TAU;
+            let pos = Vec2::new(angle.cos(), angle.sin()) * radius;
+            transform::spawn_child(id, &mut cmd, |cmd| {
+                cmd.insert_bundle(transform_bundle(Transform {
+                    pos: pos.extend(layers::WEAK_POINT / config.scale),
+                    scale: Vec3::splat(WEAK_POINT_SIZE / config.scale),
+                    ..Default::default()
+                }))
+                .insert_bundle(sprite_sheet_bundle(sprites.weak_point_sheet.clone(), None))
+                .insert_bundle(aabb_bundle(
+                    AABB::around_origin(Vec2::ONE),
+                    BOSS_TAG,
+                    ColliderKind::Trigger,
+                ))
+                .insert_bundle((
+                    WeakPoint { boss: id },
+                    Health(config.weak_point_health),
+                    GameEntity,
+                ));
+            });
+        }
+        cmd.entity(id).insert(HasWeakPoints);
+    }
+}
+
+/// Drifts the bosses towards the nearest ship
+fn boss_movement_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    config: Res<BossConfig>,
+    mut q_boss: Query<(&Transform, &mut Velocity), With<Boss>>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+) {
+    let t = (config.steering * time_scale.scale(&dt).as_secs_f32()).min(1.0);
+    for (tr, vel) in q_boss.iter_mut() {
+        let pos = tr.pos.truncate();
+        // with the ships gone the boss coasts to a stop
+        let target = nearest_ship(&q_player, pos)
+            .map(|player| (player - pos).normalize_or_zero() * config.speed)
+            .unwrap_or_default();
+        vel.0 = vel.0.lerp(target, t);
+    }
+}
+
+/// One overlap of a boss part, `part` is always the boss side
+struct PartHit {
+    part: EntityId,
+    other: EntityId,
+    tag: CollisionTag,
+    /// Points from `other` towards `part`
+    normal: Vec2,
+    penetration: f32,
+}
+
+/// Shots on weak points damage them, shots on the core are wasted, and the boss hurts the ship
+/// like an asteroid without breaking on a shield
+fn boss_collision_system(
+    collisions: Res<Collisions>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    config: Res<BossConfig>,
+    mut score: ResMut<Score>,
+    mut rng: ResMut<Rng>,
+    q_boss: Query<&GlobalTransform, With<Boss>>,
+    mut q_weak: Query<(&WeakPoint, &mut Health, &GlobalTransform)>,
+    q_parts: Query<(EntityId, &WeakPoint)>,
+    q_pooled: Query<&(), With<Pooled>>,
+    mut q_ship: Query<(&mut Transform, &mut Velocity), With<Player>>,
+    mut q_shield: Query<&mut Shield>,
+) {
+    let mut hits: SmallVec<[PartHit; 8]> = collisions
+        .iter()
+        .filter_map(|event| {
+            let CollisionEvent {
+                entity_1,
+                tag1,
+                entity_2,
+                tag2,
+                normal,
+                penetration,
+                ..
+            } = *event;
+            if tag1 == BOSS_TAG {
+                Some(PartHit {
+                    part: entity_1,
+                    other: entity_2,
+                    tag: tag2,
+                    normal,
+                    penetration,
+                })
+            } else if tag2 == BOSS_TAG {
+                Some(PartHit {
+                    part: entity_2,
+                    other: entity_1,
+                    tag: tag1,
+                    normal: -normal,
+                    penetration,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+    // a shot overlapping a weak point and the core goes to the weak point
+    hits.sort_by_key(|hit| q_weak.fetch(hit.part).is_none());
+
+    let mut spent: SmallVec<[EntityId; 8]> = SmallVec::new();
+    let mut destroyed: SmallVec<[EntityId; 4]> = SmallVec::new();
+    let mut downed: SmallVec<[EntityId; 2]> = SmallVec::new();
+    for hit in hits {
+        if hit.tag == PLAYER_TAG {
+            if downed.contains(&hit.other) {
+                continue;
+            }
+            if !hit_player(&mut cmd, hit.other, &mut q_shield) {
+                downed.push(hit.other);
+            } else {
+                // the shield holds, push the ship clear so it doesn't lose a charge every frame
+                if let Some((tr, vel)) = q_ship.fetch_mut(hit.other) {
+                    tr.pos -= (hit.normal * hit.penetration).extend(0.0);
+                    vel.0 = -hit.normal * config.knockback;
+                }
+            }
+            continue;
+        }
+        if spent.contains(&hit.other) {
+            continue;
+        }
+        spent.push(hit.other);
+        if q_pooled.fetch(hit.other).is_some() {
+            deactivate_bullet(cmd.entity(hit.other));
+        } else {
+            cmd.delete(hit.other);
+        }
+        if destroyed.contains(&hit.part) {
+            continue;
+        }
+        let Some((weak, health, tr)) = q_weak.fetch_mut(hit.part) else {
+            continue;
+        };
+        if !damage_weak_point(health, hit.tag) {
+            continue;
+        }
+        let boss = weak.boss;
+        score.score += config.weak_point_score;
+        spawn_explosion(cmd.spawn(), &sprites, tr.0.pos, tr.0.scale.x);
+        cmd.delete(hit.part);
+        destroyed.push(hit.part);
+
+        if !has_weak_points(q_parts.iter(), boss, &destroyed) {
+            if let Some(tr) = q_boss.fetch(boss) {
+                crack_boss(
+                    &mut cmd, &mut rng.0, &sprites, &config, &mut score, boss, tr,
+                );
+            }
+        }
+    }
+}
+
+/// Takes a hit by a collider tagged `tag` off the weak point, returns whether it's destroyed
+fn damage_weak_point(health: &mut Health, tag: CollisionTag) -> bool {
+    let damage = if tag == MISSILE_TAG {
+        MISSILE_DAMAGE
+    } else {
+        1
+    };
+    health.0 = health.0.saturating_sub(damage);
+    health.0 == 0
+}
+
+/// Whether any of the weak points in `parts` belongs to `boss`, besides the `destroyed` ones
+fn has_weak_points<'a>(
+    mut parts: impl Iterator<Item = (EntityId, &'a WeakPoint)>,
+    boss: EntityId,
+    destroyed: &[EntityId],
+) -> bool {
+    parts.any(|(id, weak)| weak.boss == boss && !destroyed.contains(&id))
+}
+
+/// Breaks the boss into a ring of asteroids flying outwards
+fn crack_boss(
+    cmd: &mut Commands,
+    rng: &mut fastrand::Rng,
+    sprites: &Sprites,
+    config: &BossConfig,
+    score: &mut Score,
+    boss: EntityId,
+    tr: &GlobalTransform,
+) {
+    score.score += config.score;
+    cmd.delete(boss);
+    spawn_explosion(cmd.spawn(), sprites, tr.0.pos, tr.0.scale.x);
+    let center = tr.0.pos.truncate();
+    let radius = config.core_size() * tr.0.scale.x * 0.25;
+    let phase = rng.f32() * std::f32::consts::TAU;
+    for i in 0..config.fragments {
+        let angle = phase + i as f32 / config.fragments as f32 * std::f32::consts::TAU;
+        let dir = Vec2::new(angle.cos(), angle.sin());
+        let kind = AsteroidKind::random(rng);
+        spawn_asteroid(
+            cmd.spawn(),
+            rng,
+            Transform {
+                pos: (center + dir * radius).extend(tr.0.pos.z),
+                ..Default::default()
+            },
+            sprites,
+            kind,
+            Velocity(dir * config.fragment_speed),
+        );
+    }
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::Update)
+            .add_system(weak_point_spawn_system)
+            .add_system(boss_movement_system);
+        app.stage(crate::Stage::PostUpdate)
+            .add_system(boss_collision_system);
+        app.insert_resource(BossConfig::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BULLET_TAG;
+
+    #[test]
+    fn boss_breaks_with_its_last_weak_point() {
+        let config = BossConfig::default();
+        let mut world = brengin::cecs::World::new(2 + config.weak_points * 2);
+        let boss = world.insert_entity();
+        let other_boss = world.insert_entity();
+        let mut parts = Vec::new();
+        for owner in [boss, other_boss] {
+            for _ in 0..config.weak_points {
+                parts.push((world.insert_entity(), WeakPoint { boss: owner }));
+            }
+        }
+        let parts = || parts.iter().map(|(id, weak)| (*id, weak));
+
+        let mut destroyed = Vec::new();
+        for (id, weak) in parts().filter(|(_, weak)| weak.boss == boss) {
+            assert!(has_weak_points(parts(), weak.boss, &destroyed));
+            let mut health = Health(config.weak_point_health);
+            let mut hits = 1;
+            while !damage_weak_point(&mut health, BULLET_TAG) {
+                hits += 1;
+            }
+            assert_eq!(hits, config.weak_point_health);
+            destroyed.push(id);
+        }
+        assert!(!has_weak_points(parts(), boss, &destroyed));
+        // the other boss keeps its own
+        assert!(has_weak_points(parts(), other_boss, &destroyed));
+    }
+
+    #[test]
+    fn missiles_hit_weak_points_harder() {
+        let mut health = Health(MISSILE_DAMAGE + 1);
+        assert!(!damage_weak_point(&mut health, MISSILE_TAG));
+        assert_eq!(health.0, 1);
+        assert!(damage_weak_point(&mut health, MISSILE_TAG));
+        assert_eq!(health.0, 0);
+    }
+}
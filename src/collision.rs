@@ -1,11 +1,18 @@
 use brengin::cecs::{
-    prelude::{Bundle, EntityId, ResMut},
+    prelude::{Bundle, EntityId, Res, ResMut, WithOut},
     query::Query,
     systems::IntoSystem,
 };
 use brengin::glam::Vec2;
+use serde::Deserialize;
+#[cfg(not(target_family = "wasm"))]
+use std::sync::{mpsc, Arc, RwLock};
 
-use crate::{transform::GlobalTransform, Plugin};
+use crate::{
+    events::Events,
+    transform::{GlobalTransform, Transform},
+    Plugin,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CollisionTag {
@@ -23,12 +30,49 @@ pub struct AABB {
 
 impl AABB {
     pub fn around_origin(size: Vec2) -> Self {
+        Self::around_point(Vec2::ZERO, size)
+    }
+
+    pub fn around_point(center: Vec2, size: Vec2) -> Self {
         let extents = size / 2.0;
         Self {
-            min: -extents,
-            max: extents,
+            min: center - extents,
+            max: center + extents,
         }
     }
+
+    pub fn from_min_max(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    /// Smallest box containing both boxes
+    pub fn merge(&self, other: &AABB) -> Self {
+        Self::from_min_max(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Inclusive of the edges, like [test_aabb_aabb]
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.min.cmple(point).all() && point.cmple(self.max).all()
+    }
+
+    /// Distance from the center to the edge of the box along the unit vector `dir`
+    pub fn extent_along(&self, dir: Vec2) -> f32 {
+        // a zero component divides into infinity and drops out of the min
+        (self.size() * 0.5 / dir.abs()).min_element()
+    }
+
+    pub fn area(&self) -> f32 {
+        let size = self.size().max(Vec2::ZERO);
+        size.x * size.y
+    }
 }
 
 pub fn test_aabb_aabb(a: &AABB, b: &AABB) -> bool {
@@ -40,106 +84,1256 @@ pub fn test_aabb_aabb(a: &AABB, b: &AABB) -> bool {
     true
 }
 
-struct AABBBuffer(pub Vec<(EntityId, AABB, CollisionTag)>);
+/// Contact point, normal and penetration depth of two overlapping boxes
+///
+/// The point is the center of the overlap, the normal is the axis of least penetration,
+/// pointing from `b` towards `a`
+pub fn contact_aabb_aabb(a: &AABB, b: &AABB) -> (Vec2, Vec2, f32) {
+    let min = a.min.max(b.min);
+    let max = a.max.min(b.max);
+    let overlap = max - min;
+    let d = a.center() - b.center();
+    let (normal, depth) = if overlap.x < overlap.y {
+        (
+            Vec2::new(if d.x < 0.0 { -1.0 } else { 1.0 }, 0.0),
+            overlap.x,
+        )
+    } else {
+        (
+            Vec2::new(0.0, if d.y < 0.0 { -1.0 } else { 1.0 }),
+            overlap.y,
+        )
+    };
+    ((min + max) * 0.5, normal, depth)
+}
+
+/// Distance along `dir` at which the ray from `origin` enters `aabb`, using the slab method
+///
+/// The distance is in lengths of `dir`, rays starting inside the box hit at 0.
+pub fn ray_aabb(origin: Vec2, dir: Vec2, aabb: &AABB) -> Option<f32> {
+    let mut t_min = 0.0f32;
+    let mut t_max = f32::INFINITY;
+    for i in 0..2 {
+        if dir[i].abs() <= f32::EPSILON {
+            // parallel to the slab, either always inside it or never
+            if origin[i] < aabb.min[i] || aabb.max[i] < origin[i] {
+                return None;
+            }
+            continue;
+        }
+        let inv = 1.0 / dir[i];
+        let mut t0 = (aabb.min[i] - origin[i]) * inv;
+        let mut t1 = (aabb.max[i] - origin[i]) * inv;
+        if t0 > t1 {
+            std::mem::swap(&mut t0, &mut t1);
+        }
+        t_min = t_min.max(t0);
+        t_max = t_max.min(t1);
+        if t_min > t_max {
+            return None;
+        }
+    }
+    Some(t_min)
+}
+
+/// A collider in the broadphase, the last field is set for the wrapped copies of edge colliders
+type Collider = (EntityId, AABB, CollisionTag, bool);
+
+/// Colliders of this frame
+pub struct AABBBuffer(pub Vec<Collider>);
+
+/// Closest collider in the layers of `mask` hit by the ray, and the distance to it, see [ray_aabb]
+///
+/// Sees this frame's colliders, so call it from systems running after [CollisionPlugin]'s. The
+/// wrapped copies of edge colliders are in the buffer too, rays hit those as well.
+pub fn raycast(buffer: &AABBBuffer, origin: Vec2, dir: Vec2, mask: u8) -> Option<(EntityId, f32)> {
+    buffer
+        .0
+        .iter()
+        .filter(|(_, _, tag, _)| tag.src & mask != 0)
+        .filter_map(|(id, aabb, _, _)| ray_aabb(origin, dir, aabb).map(|t| (*id, t)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Half size of a wrapping world, colliders crossing its edge also collide on the opposite side
+pub struct WrapRadius(pub Option<f32>);
+
+/// Layers, as `src` bits, whose colliders also collide with each other on top of what their tags
+/// accept
+///
+/// Switches the pairs within a layer on and off while the game runs, without changing the tag the
+/// colliders are told apart by. Pairs that aren't reported aren't tested either.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelfColliding(pub u8);
+
+/// World space box of a collider, kept up to date from its [AABB] and transform
+pub struct GlobalAABB(pub AABB);
+
+/// How a collider reacts to overlaps, every overlap is reported as a [CollisionEvent] either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColliderKind {
+    /// Only reports overlaps
+    Trigger,
+    /// Pushed out of other solid colliders by [resolve_penetration_system]
+    Solid,
+}
 
-struct GlobalAABB(AABB);
+/// Keeps a collider out of the broadphase without removing its components
+pub struct CollisionDisabled;
 
 fn update_aabbs_system(mut q: Query<(&mut GlobalAABB, &GlobalTransform, &AABB)>) {
     q.par_for_each_mut(|(out, tr, aabb)| {
-        let p = (aabb.max + aabb.min) * 0.5;
-        let size = aabb.max - aabb.min;
-        let size = tr.0.scale.truncate() * size * 0.5;
-        let offset = tr.0.pos.truncate();
-        out.0 = AABB {
-            min: p - size + offset,
-            max: p + size + offset,
-        };
+        let scale = tr.0.scale.truncate();
+        let center = aabb.center() * scale + tr.0.pos.truncate();
+        out.0 = AABB::around_point(center, aabb.size() * scale);
     });
 }
 
 fn collect_aabbs_system(
     mut buff: ResMut<AABBBuffer>,
-    q: Query<(EntityId, &GlobalAABB, &CollisionTag)>,
+    wrap: Res<WrapRadius>,
+    q: Query<(EntityId, &GlobalAABB, &CollisionTag), WithOut<CollisionDisabled>>,
 ) {
     buff.0.clear();
     for (id, aabb, tag) in q.iter() {
-        buff.0.push((id, aabb.0, *tag));
+        push_collider(&mut buff.0, id, aabb.0, *tag, wrap.0);
+    }
+}
+
+/// Adds the collider to the broadphase, along with its wrapped copies if the world wraps at
+/// `wrap_radius`
+fn push_collider(
+    out: &mut Vec<Collider>,
+    id: EntityId,
+    aabb: AABB,
+    tag: CollisionTag,
+    wrap_radius: Option<f32>,
+) {
+    // degenerate boxes, e.g. of entities scaled to zero, would still touch their neighbours
+    if aabb.area() <= 0.0 {
+        return;
+    }
+    out.push((id, aabb, tag, false));
+    if let Some(radius) = wrap_radius {
+        push_wrapped(out, id, aabb, tag, radius);
+    }
+}
+
+fn push_wrapped(out: &mut Vec<Collider>, id: EntityId, aabb: AABB, tag: CollisionTag, radius: f32) {
+    let size = 2.0 * radius;
+    let shift = |min: f32, max: f32| {
+        if min < -radius {
+            size
+        } else if max > radius {
+            -size
+        } else {
+            0.0
+        }
+    };
+    let dx = shift(aabb.min.x, aabb.max.x);
+    let dy = shift(aabb.min.y, aabb.max.y);
+    let mut push = |offset: Vec2| {
+        out.push((
+            id,
+            AABB::from_min_max(aabb.min + offset, aabb.max + offset),
+            tag,
+            true,
+        ));
+    };
+    if dx != 0.0 {
+        push(Vec2::new(dx, 0.0));
+    }
+    if dy != 0.0 {
+        push(Vec2::new(0.0, dy));
+    }
+    if dx != 0.0 && dy != 0.0 {
+        push(Vec2::new(dx, dy));
     }
 }
 
 struct SortAxis(usize);
 
-#[derive(Default)]
-pub struct Collisions(pub Vec<CollisionEvent>);
+/// How the broadphase finds the overlapping pairs among this frame's colliders
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BroadphaseMode {
+    /// Sorts the colliders along the axis they're most spread out on and sweeps them
+    ///
+    /// Needs no memory beyond the collider buffer, on large scenes the sweep is split across
+    /// worker threads sharing a copy of it. Colliders bunched up along the sweep axis but far apart on the other one are
+    /// still tested against each other, so it slows down when a big map fills up unevenly.
+    #[default]
+    Sweep,
+    /// Splits the area covered by the colliders into quadrants, only where there are colliders
+    ///
+    /// Only boxes sharing a branch of the tree are tested, which keeps large sparse maps and
+    /// clusters cheap. The tree is rebuilt every frame on a single thread, on small or evenly
+    /// filled maps that costs more than sorting.
+    Quadtree,
+}
 
-#[derive(Debug)]
+/// Collisions of this frame, ordered by entity pair, read them from systems running after
+/// [CollisionPlugin]'s
+pub type Collisions = Events<CollisionEvent>;
+
+#[derive(Debug, PartialEq)]
 pub struct CollisionEvent {
+    /// The lower id of the pair
     pub entity_1: EntityId,
     pub tag1: CollisionTag,
     pub entity_2: EntityId,
     pub tag2: CollisionTag,
+    /// Center of the overlap
+    pub contact_point: Vec2,
+    /// Unit axis of least penetration, pointing from `entity_2` towards `entity_1`
+    pub normal: Vec2,
+    /// Overlap along [CollisionEvent::normal]
+    pub penetration: f32,
 }
 
-fn sort_sweep_system(
+/// Finds this frame's overlapping pairs with the [BroadphaseMode] picked for the map
+fn broadphase_system(
     mut buff: ResMut<AABBBuffer>,
+    mode: Res<BroadphaseMode>,
     mut axis: ResMut<SortAxis>,
     mut collisions: ResMut<Collisions>,
+    mut scratch: ResMut<NarrowPhaseScratch>,
+    mut quadtree: ResMut<Quadtree>,
+    wrap: Res<WrapRadius>,
+    self_colliding: Res<SelfColliding>,
+) {
+    collisions.clear();
+    find_collisions(
+        &mut buff.0,
+        *mode,
+        wrap.0.is_some(),
+        self_colliding.0,
+        &mut axis,
+        &mut scratch,
+        &mut quadtree,
+        collisions.buffer_mut(),
+    );
+}
+
+/// Appends the overlapping pairs among `colliders` to `out`, ordered by entity pair
+fn find_collisions(
+    colliders: &mut [Collider],
+    mode: BroadphaseMode,
+    wraps: bool,
+    self_colliding: u8,
+    axis: &mut SortAxis,
+    scratch: &mut NarrowPhaseScratch,
+    quadtree: &mut Quadtree,
+    out: &mut Vec<CollisionEvent>,
+) {
+    // nothing to pair up, and the variance of an empty buffer would be NaN
+    if colliders.len() < 2 {
+        return;
+    }
+    match mode {
+        BroadphaseMode::Sweep => sort_sweep(colliders, self_colliding, axis, scratch, out),
+        BroadphaseMode::Quadtree => {
+            quadtree.build(colliders);
+            quadtree.find_pairs(colliders, self_colliding, out);
+        }
+    }
+
+    // the broadphases push pairs in whatever order they're found in, which changes with the sort
+    // axis and the shape of the tree, sort them so the same scene is handled the same way
+    out.sort_by(|a, b| {
+        pair_key(a)
+            .cmp(&pair_key(b))
+            .then(a.contact_point.x.total_cmp(&b.contact_point.x))
+            .then(a.contact_point.y.total_cmp(&b.contact_point.y))
+    });
+    if wraps {
+        // a pair touching across the seam is found from both sides, keep the same one every time
+        out.dedup_by_key(|e| pair_key(e));
+    }
+}
+
+/// Sorts `colliders` along the sweep axis and sweeps them, then picks the axis for the next frame
+fn sort_sweep(
+    colliders: &mut [Collider],
+    self_colliding: u8,
+    axis: &mut SortAxis,
+    scratch: &mut NarrowPhaseScratch,
+    out: &mut Vec<CollisionEvent>,
 ) {
-    collisions.0.clear();
     let sort_axis = axis.0;
-    buff.0.sort_unstable_by(|a, b| {
+    colliders.sort_unstable_by(|a, b| {
         a.1.min[sort_axis]
             .partial_cmp(&b.1.min[sort_axis])
             .unwrap_or(std::cmp::Ordering::Equal)
     });
 
+    narrow_phase(colliders, sort_axis, self_colliding, scratch, out);
+
     let mut sum = Vec2::ZERO;
     let mut sum2 = Vec2::ZERO;
+    for a in colliders.iter() {
+        let p = a.1.center();
+        sum += p;
+        sum2 += p * p;
+    }
+
+    let variance = sum2 - ((sum * sum) / colliders.len() as f32);
+    if variance.y > variance.x {
+        axis.0 = 1;
+    } else {
+        axis.0 = 0;
+    }
+}
+
+/// Below this many colliders handing the sweep to the workers costs more than the sweep itself
+#[cfg(not(target_family = "wasm"))]
+const PARALLEL_NARROW_PHASE_THRESHOLD: usize = 512;
+
+/// Narrow phase state kept between frames
+#[derive(Default)]
+struct NarrowPhaseScratch {
+    /// Started on the first frame with enough colliders to use it
+    #[cfg(not(target_family = "wasm"))]
+    pool: Option<NarrowPhasePool>,
+}
+
+/// A range of the sorted colliders for a worker to sweep
+#[cfg(not(target_family = "wasm"))]
+struct SweepJob {
+    worker: usize,
+    range: std::ops::Range<usize>,
+    sort_axis: usize,
+    self_colliding: u8,
+    /// Sent back and forth with the job, so its allocation is reused every frame
+    events: Vec<CollisionEvent>,
+}
+
+/// Threads sweeping the narrow phase, kept alive until the pool is dropped instead of spawning
+/// threads every frame
+#[cfg(not(target_family = "wasm"))]
+struct NarrowPhasePool {
+    /// Copy of this frame's sorted colliders
+    colliders: Arc<RwLock<Vec<Collider>>>,
+    jobs: Vec<mpsc::Sender<SweepJob>>,
+    done: mpsc::Receiver<SweepJob>,
+    /// Event buffers of the workers while they're idle
+    events: Vec<Vec<CollisionEvent>>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl NarrowPhasePool {
+    fn new(workers: usize) -> Self {
+        let colliders = Arc::new(RwLock::new(Vec::<Collider>::new()));
+        let (done_tx, done) = mpsc::channel();
+        let jobs = (0..workers)
+            .map(|i| {
+                let (tx, rx) = mpsc::channel::<SweepJob>();
+                let colliders = colliders.clone();
+                let done = done_tx.clone();
+                std::thread::Builder::new()
+                    .name(format!("narrow-phase-{i}"))
+                    .spawn(move || {
+                        // runs until the pool drops its sender
+                        for mut job in rx {
+                            let colliders = colliders.read().unwrap();
+                            sweep_range(
+                                &colliders,
+                                job.range.clone(),
+                                job.sort_axis,
+                                job.self_colliding,
+                                &mut job.events,
+                            );
+                            drop(colliders);
+                            if done.send(job).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("Failed to spawn a narrow phase worker");
+                tx
+            })
+            .collect();
+        Self {
+            colliders,
+            jobs,
+            done,
+            events: (0..workers).map(|_| Vec::new()).collect(),
+        }
+    }
 
-    for (i, a) in buff.0.iter().enumerate() {
-        for b in &buff.0[i + 1..] {
+    /// Splits the sweep of the sorted `colliders` into contiguous ranges, one per worker, and
+    /// appends their events to `out` in range order
+    fn sweep(
+        &mut self,
+        colliders: &[Collider],
+        sort_axis: usize,
+        self_colliding: u8,
+        out: &mut Vec<CollisionEvent>,
+    ) {
+        {
+            let mut shared = self.colliders.write().unwrap();
+            shared.clear();
+            shared.extend_from_slice(colliders);
+        }
+        let chunk = colliders.len().div_ceil(self.jobs.len());
+        let mut sent = 0;
+        for (worker, start) in (0..colliders.len()).step_by(chunk).enumerate() {
+            let job = SweepJob {
+                worker,
+                range: start..(start + chunk).min(colliders.len()),
+                sort_axis,
+                self_colliding,
+                events: std::mem::take(&mut self.events[worker]),
+            };
+            self.jobs[worker]
+                .send(job)
+                .expect("Narrow phase worker stopped");
+            sent += 1;
+        }
+        for _ in 0..sent {
+            let job = self.done.recv().expect("Narrow phase worker stopped");
+            self.events[job.worker] = job.events;
+        }
+        for events in self.events.iter_mut() {
+            out.append(events);
+        }
+    }
+}
+
+/// Tests the candidate pairs of the sorted `colliders`, appending the hits to `out`
+///
+/// The sweep of every collider only reads the buffer, so large buffers are split into
+/// contiguous ranges for the [NarrowPhasePool] and merged in order, giving the same events in
+/// the same order as a serial sweep
+#[cfg_attr(target_family = "wasm", allow(unused_variables))]
+fn narrow_phase(
+    colliders: &[Collider],
+    sort_axis: usize,
+    self_colliding: u8,
+    scratch: &mut NarrowPhaseScratch,
+    out: &mut Vec<CollisionEvent>,
+) {
+    #[cfg(not(target_family = "wasm"))]
+    if colliders.len() >= PARALLEL_NARROW_PHASE_THRESHOLD {
+        let pool = scratch.pool.get_or_insert_with(|| {
+            let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+            // a single worker would only add the hand-off to the serial sweep
+            NarrowPhasePool::new(if workers > 1 { workers } else { 0 })
+        });
+        if !pool.jobs.is_empty() {
+            pool.sweep(colliders, sort_axis, self_colliding, out);
+            return;
+        }
+    }
+    sweep_range(
+        colliders,
+        0..colliders.len(),
+        sort_axis,
+        self_colliding,
+        out,
+    );
+}
+
+/// Sweeps the colliders in `range` against every collider after them
+fn sweep_range(
+    colliders: &[Collider],
+    range: std::ops::Range<usize>,
+    sort_axis: usize,
+    self_colliding: u8,
+    out: &mut Vec<CollisionEvent>,
+) {
+    for i in range {
+        let a = &colliders[i];
+        for b in &colliders[i + 1..] {
             if b.1.min[sort_axis] > a.1.max[sort_axis] {
                 break;
             }
-            // tags are not commutative
-            if ((a.2.src & b.2.dst != 0) || (b.2.src & a.2.dst != 0)) && test_aabb_aabb(&a.1, &b.1)
-            {
-                collisions.0.push(CollisionEvent {
-                    entity_1: a.0,
-                    tag1: a.2,
-                    entity_2: b.0,
-                    tag2: b.2,
-                });
+            test_pair(a, b, self_colliding, out);
+        }
+    }
+}
+
+/// Reports the pair if the tags of either side accept the other, or both are in one of the
+/// `self_colliding` layers, and the boxes overlap
+fn test_pair(a: &Collider, b: &Collider, self_colliding: u8, out: &mut Vec<CollisionEvent>) {
+    // wrapped copies never collide with their own entity, nor with each other: the originals
+    // already overlap in that case
+    if a.0 == b.0 || (a.3 && b.3) {
+        return;
+    }
+    // the lower id goes first, so the event doesn't depend on the order the pair was found in
+    let (a, b) = if a.0 < b.0 { (a, b) } else { (b, a) };
+    // tags are not commutative
+    let accepted = (a.2.src & b.2.dst != 0)
+        || (b.2.src & a.2.dst != 0)
+        || (a.2.src == b.2.src && a.2.src & self_colliding != 0);
+    if accepted && test_aabb_aabb(&a.1, &b.1) {
+        let (contact_point, normal, penetration) = contact_aabb_aabb(&a.1, &b.1);
+        out.push(CollisionEvent {
+            entity_1: a.0,
+            tag1: a.2,
+            entity_2: b.0,
+            tag2: b.2,
+            contact_point,
+            normal,
+            penetration,
+        });
+    }
+}
+
+/// Colliders a quadtree node holds before it splits
+const QUADTREE_NODE_CAPACITY: usize = 8;
+/// Nodes stop splitting at this depth, so a pile of boxes on one spot can't split forever
+const QUADTREE_MAX_DEPTH: u32 = 8;
+
+struct QuadNode {
+    bounds: AABB,
+    /// Indices of the colliders that don't fit entirely inside one of the children
+    items: Vec<usize>,
+    /// Index of the first of the four children in [Quadtree::nodes]
+    children: Option<usize>,
+    depth: u32,
+}
+
+/// Quadtree broadphase, see [BroadphaseMode::Quadtree]
+///
+/// Kept between frames to reuse the node buffer.
+#[derive(Default)]
+struct Quadtree {
+    nodes: Vec<QuadNode>,
+}
+
+impl Quadtree {
+    /// Rebuilds the tree over the bounds of `colliders`
+    fn build(&mut self, colliders: &[Collider]) {
+        self.nodes.clear();
+        let Some(bounds) = colliders.iter().map(|c| c.1).reduce(|a, b| a.merge(&b)) else {
+            return;
+        };
+        self.nodes.push(QuadNode {
+            bounds,
+            items: Vec::new(),
+            children: None,
+            depth: 0,
+        });
+        for i in 0..colliders.len() {
+            self.insert(colliders, i);
+        }
+    }
+
+    fn insert(&mut self, colliders: &[Collider], item: usize) {
+        let mut node = 0;
+        while let Some(child) = self.child_containing(node, &colliders[item].1) {
+            node = child;
+        }
+        let n = &mut self.nodes[node];
+        n.items.push(item);
+        if n.children.is_none()
+            && n.items.len() > QUADTREE_NODE_CAPACITY
+            && n.depth < QUADTREE_MAX_DEPTH
+        {
+            self.split(colliders, node);
+        }
+    }
+
+    /// The child of `node` that `aabb` fits in without touching its edges
+    ///
+    /// Boxes touching the split lines stay in the parent, touching counts as overlapping and
+    /// neighbouring quadrants are never tested against each other.
+    fn child_containing(&self, node: usize, aabb: &AABB) -> Option<usize> {
+        let first = self.nodes[node].children?;
+        (first..first + 4).find(|&child| {
+            let bounds = &self.nodes[child].bounds;
+            bounds.min.cmplt(aabb.min).all() && aabb.max.cmplt(bounds.max).all()
+        })
+    }
+
+    fn split(&mut self, colliders: &[Collider], node: usize) {
+        let bounds = self.nodes[node].bounds;
+        let depth = self.nodes[node].depth + 1;
+        let center = bounds.center();
+        let first = self.nodes.len();
+        for (min, max) in [
+            (bounds.min, center),
+            (
+                Vec2::new(center.x, bounds.min.y),
+                Vec2::new(bounds.max.x, center.y),
+            ),
+            (
+                Vec2::new(bounds.min.x, center.y),
+                Vec2::new(center.x, bounds.max.y),
+            ),
+            (center, bounds.max),
+        ] {
+            self.nodes.push(QuadNode {
+                bounds: AABB::from_min_max(min, max),
+                items: Vec::new(),
+                children: None,
+                depth,
+            });
+        }
+        self.nodes[node].children = Some(first);
+        for item in std::mem::take(&mut self.nodes[node].items) {
+            let owner = self
+                .child_containing(node, &colliders[item].1)
+                .unwrap_or(node);
+            self.nodes[owner].items.push(item);
+        }
+    }
+
+    /// Tests every collider against the rest of its node and the nodes above it, boxes in
+    /// different branches can't overlap
+    fn find_pairs(
+        &self,
+        colliders: &[Collider],
+        self_colliding: u8,
+        out: &mut Vec<CollisionEvent>,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut ancestors = Vec::new();
+        self.visit(0, colliders, self_colliding, &mut ancestors, out);
+    }
+
+    fn visit(
+        &self,
+        node: usize,
+        colliders: &[Collider],
+        self_colliding: u8,
+        ancestors: &mut Vec<usize>,
+        out: &mut Vec<CollisionEvent>,
+    ) {
+        let n = &self.nodes[node];
+        for (i, &a) in n.items.iter().enumerate() {
+            for &b in ancestors.iter().chain(&n.items[i + 1..]) {
+                test_pair(&colliders[b], &colliders[a], self_colliding, out);
             }
         }
-        let p = (a.1.max + a.1.min) / 2.0;
-        sum += p;
-        sum2 += p * p;
+        let len = ancestors.len();
+        ancestors.extend_from_slice(&n.items);
+        if let Some(first) = n.children {
+            for child in first..first + 4 {
+                self.visit(child, colliders, self_colliding, ancestors, out);
+            }
+        }
+        ancestors.truncate(len);
     }
+}
 
-    let variance = sum2 - ((sum * sum) / buff.0.len() as f32);
-    if variance.y > variance.x {
-        axis.0 = 1;
+/// Moves overlapping solid colliders apart along the contact normal, half the penetration each
+fn resolve_penetration_system(
+    collisions: Res<Collisions>,
+    mut q: Query<(&mut Transform, &ColliderKind)>,
+) {
+    for event in collisions.iter() {
+        let is_solid = |id| {
+            q.fetch(id)
+                .is_some_and(|(_, kind)| *kind == ColliderKind::Solid)
+        };
+        if !is_solid(event.entity_1) || !is_solid(event.entity_2) {
+            continue;
+        }
+        let push = separation(event).extend(0.0);
+        if let Some((tr, _)) = q.fetch_mut(event.entity_1) {
+            tr.pos += push;
+        }
+        if let Some((tr, _)) = q.fetch_mut(event.entity_2) {
+            tr.pos -= push;
+        }
+    }
+}
+
+/// Offset moving `entity_1` out of `entity_2`, `entity_2` moves by the opposite
+fn separation(event: &CollisionEvent) -> Vec2 {
+    event.normal * event.penetration * 0.5
+}
+
+fn pair_key(e: &CollisionEvent) -> (EntityId, EntityId) {
+    if e.entity_1 < e.entity_2 {
+        (e.entity_1, e.entity_2)
     } else {
-        axis.0 = 0;
+        (e.entity_2, e.entity_1)
     }
 }
 
-pub struct CollisionPlugin;
+pub struct CollisionPlugin {
+    /// Half size of the world if it wraps around, see [WrapRadius]
+    pub wrap_radius: Option<f32>,
+    pub broadphase: BroadphaseMode,
+}
 
 impl Plugin for CollisionPlugin {
     fn build(self, app: &mut crate::App) {
         app.stage(crate::Stage::Update)
             .add_system(update_aabbs_system)
             .add_system(collect_aabbs_system.after(update_aabbs_system))
-            .add_system(sort_sweep_system.after(collect_aabbs_system));
+            .add_system(broadphase_system.after(collect_aabbs_system))
+            .add_system(resolve_penetration_system.after(broadphase_system));
 
         app.insert_resource(AABBBuffer(Vec::default()));
         app.insert_resource(SortAxis(0));
+        app.insert_resource(self.broadphase);
+        app.insert_resource(Quadtree::default());
+        app.insert_resource(WrapRadius(self.wrap_radius));
+        app.insert_resource(SelfColliding::default());
         app.insert_resource(Collisions::default());
+        app.insert_resource(NarrowPhaseScratch::default());
     }
 }
 
-pub fn aabb_bundle(aabb: AABB, tag: CollisionTag) -> impl Bundle {
-    (aabb, GlobalAABB(aabb), tag)
+pub fn aabb_bundle(aabb: AABB, tag: CollisionTag, kind: ColliderKind) -> impl Bundle {
+    (aabb, GlobalAABB(aabb), tag, kind)
+}
+
+/// Trigger collider without a [CollisionTag], ignored by the broadphase until a tag is inserted
+pub fn untagged_aabb_bundle(aabb: AABB) -> impl Bundle {
+    (aabb, GlobalAABB(aabb), ColliderKind::Trigger)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ASTEROID_TAG, BULLET_TAG};
+
+    fn entities(n: usize) -> Vec<EntityId> {
+        let mut world = brengin::cecs::World::new(n as u32);
+        (0..n).map(|_| world.insert_entity()).collect()
+    }
+
+    /// Broadphase buffer of `boxes`, the ids come from [entities] in the same order
+    fn colliders(
+        ids: &[EntityId],
+        boxes: &[(AABB, CollisionTag)],
+        wrap_radius: Option<f32>,
+    ) -> Vec<Collider> {
+        let mut out = Vec::new();
+        for (id, (aabb, tag)) in ids.iter().zip(boxes) {
+            push_collider(&mut out, *id, *aabb, *tag, wrap_radius);
+        }
+        out
+    }
+
+    /// Events of the `colliders`, asteroids colliding with each other so the scenes have plenty
+    /// of pairs
+    fn collisions(
+        colliders: &mut [Collider],
+        mode: BroadphaseMode,
+        wraps: bool,
+        axis: usize,
+    ) -> Vec<CollisionEvent> {
+        let mut out = Vec::new();
+        find_collisions(
+            colliders,
+            mode,
+            wraps,
+            ASTEROID_TAG.src,
+            &mut SortAxis(axis),
+            &mut NarrowPhaseScratch::default(),
+            &mut Quadtree::default(),
+            &mut out,
+        );
+        out
+    }
+
+    const MODES: [BroadphaseMode; 2] = [BroadphaseMode::Sweep, BroadphaseMode::Quadtree];
+
+    /// Whether [test_pair] reports two overlapping colliders, in either order
+    fn reports(a: CollisionTag, b: CollisionTag) -> bool {
+        let ids = entities(2);
+        let a = (ids[0], AABB::around_origin(Vec2::ONE), a, false);
+        let b = (
+            ids[1],
+            AABB::around_point(Vec2::splat(0.5), Vec2::ONE),
+            b,
+            false,
+        );
+        let mut out = Vec::new();
+        test_pair(&a, &b, 0, &mut out);
+        let forward = !out.is_empty();
+        out.clear();
+        test_pair(&b, &a, 0, &mut out);
+        assert_eq!(forward, !out.is_empty(), "{a:?} {b:?}");
+        forward
+    }
+
+    #[test]
+    fn layer_tags_report_the_interaction_table() {
+        use crate::CollisionLayers as L;
+        let tags = [
+            L::asteroid(),
+            L::bullet(),
+            L::player(),
+            L::powerup(),
+            L::missile(),
+            L::ufo(),
+            L::enemy_bullet(),
+            L::boss(),
+        ];
+        let expected = [
+            [false, true, true, false, true, true, false, false],
+            [true, false, false, false, false, true, false, true],
+            [true, false, false, true, false, true, true, true],
+            [false, false, true, false, false, false, false, false],
+            [true, false, false, false, false, true, false, true],
+            [true, true, true, false, true, false, false, false],
+            [false, false, true, false, false, false, false, false],
+            [false, true, true, false, true, false, false, false],
+        ];
+        for (i, a) in tags.iter().enumerate() {
+            for (j, b) in tags.iter().enumerate() {
+                assert_eq!(reports(*a, *b), expected[i][j], "{i} {j}");
+            }
+        }
+        let mut seen = 0u8;
+        for tag in tags {
+            assert_eq!(tag.src.count_ones(), 1, "{tag:?}");
+            assert_eq!(seen & tag.src, 0, "{tag:?}");
+            seen |= tag.src;
+        }
+    }
+
+    #[test]
+    fn shots_skip_each_other_and_the_shooter() {
+        use crate::{ENEMY_BULLET_TAG, MISSILE_TAG, PLAYER_TAG, UFO_TAG};
+        // a spread shot leaving the ship, every shot still inside its hull
+        let ids = entities(4);
+        let ship = AABB::around_origin(Vec2::splat(2.0));
+        let shot = |x: f32| AABB::around_point(Vec2::new(x, 0.5), Vec2::new(0.25, 0.5));
+        for (shooter, shot_tag) in [
+            (PLAYER_TAG, BULLET_TAG),
+            (PLAYER_TAG, MISSILE_TAG),
+            (UFO_TAG, ENEMY_BULLET_TAG),
+        ] {
+            let boxes = [
+                (ship, shooter),
+                (shot(-0.1), shot_tag),
+                (shot(0.0), shot_tag),
+                (shot(0.1), shot_tag),
+            ];
+            for mode in MODES {
+                let mut buffer = colliders(&ids, &boxes, None);
+                assert!(collisions(&mut buffer, mode, false, 0).is_empty());
+            }
+        }
+        assert!(!reports(BULLET_TAG, BULLET_TAG));
+        assert!(!reports(BULLET_TAG, MISSILE_TAG));
+        assert!(!reports(ENEMY_BULLET_TAG, BULLET_TAG));
+    }
+
+    #[test]
+    fn normal_points_from_entity_2_to_entity_1() {
+        let ids = entities(2);
+        let a = AABB::around_origin(Vec2::splat(2.0));
+        for offset in [
+            Vec2::new(1.5, 0.2),
+            Vec2::new(-1.5, 0.2),
+            Vec2::new(0.2, 1.5),
+            Vec2::new(0.2, -1.5),
+        ] {
+            let b = AABB::around_point(offset, Vec2::splat(2.0));
+            let mut out = Vec::new();
+            test_pair(
+                &(ids[0], a, ASTEROID_TAG, false),
+                &(ids[1], b, ASTEROID_TAG, false),
+                ASTEROID_TAG.src,
+                &mut out,
+            );
+            let event = &out[0];
+            let toward_1 = if event.entity_1 == ids[0] {
+                -offset
+            } else {
+                offset
+            };
+            // the normal is the axis the boxes are pushed apart along
+            assert_eq!(event.normal.length(), 1.0);
+            assert!(event.normal.dot(toward_1) > 0.0, "{offset} {event:?}");
+            assert!((event.penetration - 0.5).abs() < 1e-5, "{event:?}");
+            assert!(a.contains(event.contact_point) && b.contains(event.contact_point));
+        }
+    }
+
+    /// `n` colliders scattered over a square of `size`, sorted along `sort_axis` like the sweep
+    /// expects
+    fn random_colliders(seed: u64, n: usize, size: f32, sort_axis: usize) -> Vec<Collider> {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let ids = entities(n);
+        let boxes: Vec<_> = (0..n)
+            .map(|_| {
+                let center = (Vec2::new(rng.f32(), rng.f32()) - 0.5) * size;
+                let extents = Vec2::new(rng.f32(), rng.f32()) * 1.5 + 0.25;
+                let tag = if rng.bool() { ASTEROID_TAG } else { BULLET_TAG };
+                (AABB::around_point(center, extents), tag)
+            })
+            .collect();
+        let mut out = colliders(&ids, &boxes, None);
+        out.sort_unstable_by(|a, b| a.1.min[sort_axis].total_cmp(&b.1.min[sort_axis]));
+        out
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn parallel_narrow_phase_matches_serial_sweep() {
+        for (seed, workers) in [(1, 2), (2, 3), (3, 8)] {
+            let sort_axis = seed as usize % 2;
+            let buffer =
+                random_colliders(seed, PARALLEL_NARROW_PHASE_THRESHOLD * 2, 60.0, sort_axis);
+            let mut serial = Vec::new();
+            sweep_range(
+                &buffer,
+                0..buffer.len(),
+                sort_axis,
+                ASTEROID_TAG.src,
+                &mut serial,
+            );
+            assert!(!serial.is_empty());
+
+            let mut scratch = NarrowPhaseScratch {
+                pool: Some(NarrowPhasePool::new(workers)),
+            };
+            // the second frame reuses the buffers the first one handed back
+            for _ in 0..2 {
+                let mut parallel = Vec::new();
+                narrow_phase(
+                    &buffer,
+                    sort_axis,
+                    ASTEROID_TAG.src,
+                    &mut scratch,
+                    &mut parallel,
+                );
+                assert_eq!(parallel, serial);
+            }
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn collision_buffers_keep_their_capacity() {
+        let scene = random_colliders(5, PARALLEL_NARROW_PHASE_THRESHOLD * 2, 60.0, 0);
+        let mut buffer = AABBBuffer(Vec::new());
+        let mut collisions = Collisions::default();
+        let mut axis = SortAxis(0);
+        let mut scratch = NarrowPhaseScratch {
+            pool: Some(NarrowPhasePool::new(3)),
+        };
+        let mut quadtree = Quadtree::default();
+        let mut frame = || {
+            buffer.0.clear();
+            for (id, aabb, tag, _) in &scene {
+                push_collider(&mut buffer.0, *id, *aabb, *tag, Some(25.0));
+            }
+            collisions.clear();
+            find_collisions(
+                &mut buffer.0,
+                BroadphaseMode::Sweep,
+                true,
+                ASTEROID_TAG.src,
+                &mut axis,
+                &mut scratch,
+                &mut quadtree,
+                collisions.buffer_mut(),
+            );
+            assert!(collisions.iter().next().is_some());
+            let pool = scratch.pool.as_ref().unwrap();
+            (
+                buffer.0.capacity(),
+                collisions.buffer_mut().capacity(),
+                pool.colliders.read().unwrap().capacity(),
+                pool.events.iter().map(Vec::capacity).collect::<Vec<_>>(),
+            )
+        };
+        // the first frames grow the buffers, and may flip the sort axis
+        frame();
+        let warm = frame();
+        for _ in 0..100 {
+            assert_eq!(frame(), warm);
+        }
+    }
+
+    #[test]
+    fn sort_axis_stays_valid_with_fewer_than_two_colliders() {
+        let ids = entities(1);
+        let one = colliders(
+            &ids,
+            &[(AABB::around_origin(Vec2::ONE), ASTEROID_TAG)],
+            None,
+        );
+        for mut buffer in [Vec::new(), one] {
+            for mode in MODES {
+                for start in [0, 1] {
+                    let mut axis = SortAxis(start);
+                    let mut out = Vec::new();
+                    find_collisions(
+                        &mut buffer,
+                        mode,
+                        false,
+                        0,
+                        &mut axis,
+                        &mut NarrowPhaseScratch::default(),
+                        &mut Quadtree::default(),
+                        &mut out,
+                    );
+                    assert!(out.is_empty());
+                    assert_eq!(axis.0, start);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn asteroids_collide_with_each_other_only_when_enabled() {
+        let ids = entities(3);
+        let boxes = [
+            (AABB::around_origin(Vec2::splat(2.0)), ASTEROID_TAG),
+            (AABB::around_point(Vec2::X, Vec2::splat(2.0)), ASTEROID_TAG),
+            (AABB::around_point(-Vec2::X, Vec2::ONE), BULLET_TAG),
+        ];
+        for mode in MODES {
+            for (self_colliding, pairs) in [(0, 1), (ASTEROID_TAG.src, 2), (BULLET_TAG.src, 1)] {
+                let mut buffer = colliders(&ids, &boxes, None);
+                let mut out = Vec::new();
+                find_collisions(
+                    &mut buffer,
+                    mode,
+                    false,
+                    self_colliding,
+                    &mut SortAxis(0),
+                    &mut NarrowPhaseScratch::default(),
+                    &mut Quadtree::default(),
+                    &mut out,
+                );
+                assert_eq!(out.len(), pairs, "{mode:?} {self_colliding}");
+            }
+        }
+    }
+
+    #[test]
+    fn separation_pushes_solids_apart() {
+        let ids = entities(2);
+        for offset in [Vec2::new(0.7, 0.3), Vec2::new(-0.2, -1.1)] {
+            let mut a = AABB::around_origin(Vec2::splat(2.0));
+            let mut b = AABB::around_point(offset, Vec2::new(1.0, 2.0));
+            let mut out = Vec::new();
+            test_pair(
+                &(ids[0], a, ASTEROID_TAG, false),
+                &(ids[1], b, ASTEROID_TAG, false),
+                ASTEROID_TAG.src,
+                &mut out,
+            );
+            let push = separation(&out[0]);
+            let (first, second) = if out[0].entity_1 == ids[0] {
+                (&mut a, &mut b)
+            } else {
+                (&mut b, &mut a)
+            };
+            *first = AABB::from_min_max(first.min + push, first.max + push);
+            *second = AABB::from_min_max(second.min - push, second.max - push);
+            // at most touching along the normal
+            let (_, _, depth) = contact_aabb_aabb(&a, &b);
+            assert!(depth.abs() < 1e-5, "{offset} {depth}");
+        }
+    }
+
+    #[test]
+    fn aabb_constructors_agree() {
+        let a = AABB::around_point(Vec2::new(1.0, -2.0), Vec2::new(4.0, 2.0));
+        assert_eq!(a.min, Vec2::new(-1.0, -3.0));
+        assert_eq!(a.max, Vec2::new(3.0, -1.0));
+        assert_eq!(a.center(), Vec2::new(1.0, -2.0));
+        assert_eq!(a.size(), Vec2::new(4.0, 2.0));
+        let b = AABB::from_min_max(a.min, a.max);
+        assert_eq!((b.min, b.max), (a.min, a.max));
+        let origin = AABB::around_origin(Vec2::new(4.0, 2.0));
+        assert_eq!(origin.center(), Vec2::ZERO);
+        assert_eq!(origin.size(), a.size());
+    }
+
+    #[test]
+    fn aabb_merge_covers_disjoint_boxes() {
+        let a = AABB::from_min_max(Vec2::new(-3.0, -1.0), Vec2::new(-2.0, 0.0));
+        let b = AABB::from_min_max(Vec2::new(4.0, 2.0), Vec2::new(5.0, 6.0));
+        assert!(!test_aabb_aabb(&a, &b));
+        for merged in [a.merge(&b), b.merge(&a)] {
+            assert_eq!(merged.min, Vec2::new(-3.0, -1.0));
+            assert_eq!(merged.max, Vec2::new(5.0, 6.0));
+            for corner in [a.min, a.max, b.min, b.max] {
+                assert!(merged.contains(corner));
+            }
+        }
+        // overlapping or nested boxes merge into the outer one
+        let outer = AABB::around_origin(Vec2::splat(4.0));
+        let inner = AABB::around_origin(Vec2::ONE);
+        let merged = inner.merge(&outer);
+        assert_eq!((merged.min, merged.max), (outer.min, outer.max));
+    }
+
+    #[test]
+    fn aabb_contains_its_edges() {
+        let a = AABB::around_origin(Vec2::splat(2.0));
+        assert!(a.contains(Vec2::ZERO));
+        assert!(a.contains(Vec2::new(1.0, 1.0)));
+        assert!(a.contains(Vec2::new(-1.0, 0.5)));
+        assert!(!a.contains(Vec2::new(1.01, 0.0)));
+        assert!(!a.contains(Vec2::new(0.0, -1.01)));
+    }
+
+    #[test]
+    fn ray_aabb_cases() {
+        let aabb = AABB::from_min_max(Vec2::new(2.0, -1.0), Vec2::new(4.0, 1.0));
+        // straight at the box, along an axis
+        assert_eq!(ray_aabb(Vec2::ZERO, Vec2::X, &aabb), Some(2.0));
+        // lengths of `dir`
+        assert_eq!(ray_aabb(Vec2::ZERO, Vec2::X * 2.0, &aabb), Some(1.0));
+        // misses: pointing away, passing beside, parallel outside the slab
+        assert_eq!(ray_aabb(Vec2::ZERO, -Vec2::X, &aabb), None);
+        assert_eq!(ray_aabb(Vec2::ZERO, Vec2::new(1.0, 1.0), &aabb), None);
+        assert_eq!(ray_aabb(Vec2::new(0.0, 2.0), Vec2::X, &aabb), None);
+        assert_eq!(ray_aabb(Vec2::new(3.0, 3.0), Vec2::X, &aabb), None);
+        // parallel to an axis inside the slab
+        assert_eq!(ray_aabb(Vec2::new(3.0, 5.0), -Vec2::Y, &aabb), Some(4.0));
+        // starting inside
+        assert_eq!(ray_aabb(Vec2::new(3.0, 0.0), Vec2::Y, &aabb), Some(0.0));
+        assert_eq!(ray_aabb(Vec2::new(3.0, 0.0), -Vec2::X, &aabb), Some(0.0));
+        // diagonal entry through the corner region
+        let t = ray_aabb(Vec2::new(0.0, -3.0), Vec2::new(1.0, 1.0), &aabb).unwrap();
+        assert!((t - 2.0).abs() < 1e-6, "{t}");
+    }
+
+    #[test]
+    fn raycast_picks_the_nearest_in_the_mask() {
+        let ids = entities(3);
+        let boxes = [
+            (
+                AABB::around_point(Vec2::new(6.0, 0.0), Vec2::ONE),
+                ASTEROID_TAG,
+            ),
+            (
+                AABB::around_point(Vec2::new(3.0, 0.0), Vec2::ONE),
+                BULLET_TAG,
+            ),
+            (
+                AABB::around_point(Vec2::new(9.0, 0.0), Vec2::ONE),
+                ASTEROID_TAG,
+            ),
+        ];
+        let buffer = AABBBuffer(colliders(&ids, &boxes, None));
+        assert_eq!(
+            raycast(&buffer, Vec2::ZERO, Vec2::X, ASTEROID_TAG.src),
+            Some((ids[0], 5.5))
+        );
+        assert_eq!(
+            raycast(
+                &buffer,
+                Vec2::ZERO,
+                Vec2::X,
+                ASTEROID_TAG.src | BULLET_TAG.src
+            ),
+            Some((ids[1], 2.5))
+        );
+        assert_eq!(
+            raycast(&buffer, Vec2::ZERO, -Vec2::X, ASTEROID_TAG.src),
+            None
+        );
+        assert_eq!(raycast(&buffer, Vec2::ZERO, Vec2::X, 0), None);
+    }
+
+    #[test]
+    fn quadtree_finds_the_sweep_pairs() {
+        // dense, sparse, and bunched up in a corner of a large map
+        for (seed, n, size, offset) in [
+            (1, 200, 20.0, Vec2::ZERO),
+            (2, 300, 200.0, Vec2::ZERO),
+            (3, 300, 30.0, Vec2::splat(80.0)),
+            (4, 2, 10.0, Vec2::ZERO),
+        ] {
+            let scene = random_colliders(seed, n, size, 0);
+            for wrap_radius in [None, Some(100.0), Some(size * 0.4)] {
+                let pairs = |mode| {
+                    let mut buffer = Vec::new();
+                    for (id, aabb, tag, _) in &scene {
+                        let aabb = AABB::from_min_max(aabb.min + offset, aabb.max + offset);
+                        push_collider(&mut buffer, *id, aabb, *tag, wrap_radius);
+                    }
+                    collisions(&mut buffer, mode, wrap_radius.is_some(), 0)
+                        .iter()
+                        .map(pair_key)
+                        .collect::<Vec<_>>()
+                };
+                let sweep = pairs(BroadphaseMode::Sweep);
+                assert_eq!(
+                    pairs(BroadphaseMode::Quadtree),
+                    sweep,
+                    "{seed} {wrap_radius:?}"
+                );
+                assert!(n == 2 || !sweep.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn events_dont_depend_on_the_sort_axis() {
+        for (seed, wrap_radius) in [(6, None), (7, Some(12.0))] {
+            let scene = random_colliders(seed, 300, 30.0, 0);
+            let events = |mode, axis| {
+                let mut buffer = Vec::new();
+                // shuffled, the broadphase gets the colliders in a different order every frame
+                let mut order: Vec<_> = scene.iter().collect();
+                fastrand::Rng::with_seed(axis as u64).shuffle(&mut order);
+                for (id, aabb, tag, _) in order {
+                    push_collider(&mut buffer, *id, *aabb, *tag, wrap_radius);
+                }
+                collisions(&mut buffer, mode, wrap_radius.is_some(), axis)
+            };
+            let reference = events(BroadphaseMode::Sweep, 0);
+            assert!(!reference.is_empty());
+            assert_eq!(events(BroadphaseMode::Sweep, 1), reference, "{seed}");
+            assert_eq!(events(BroadphaseMode::Quadtree, 0), reference, "{seed}");
+            for event in &reference {
+                assert!(event.entity_1 < event.entity_2);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_size_collider_reports_nothing() {
+        let ids = entities(2);
+        let asteroid = (AABB::around_origin(Vec2::splat(2.0)), ASTEROID_TAG);
+        // scaled to zero, and flattened along a single axis
+        for size in [Vec2::ZERO, Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)] {
+            let boxes = [(AABB::around_origin(size), BULLET_TAG), asteroid];
+            for mode in MODES {
+                let mut buffer = colliders(&ids, &boxes, Some(25.0));
+                assert_eq!(buffer.len(), 1);
+                assert!(collisions(&mut buffer, mode, true, 0).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn bullet_hits_asteroid_across_the_seam() {
+        const RADIUS: f32 = 25.0;
+        let ids = entities(2);
+        for (bullet, asteroid) in [
+            (Vec2::new(RADIUS - 0.1, 0.0), Vec2::new(-RADIUS + 0.1, 0.0)),
+            // in opposite corners both axes wrap
+            (
+                Vec2::new(RADIUS - 0.1, RADIUS - 0.1),
+                Vec2::new(-RADIUS + 0.1, -RADIUS + 0.1),
+            ),
+        ] {
+            let boxes = [
+                (AABB::around_point(bullet, Vec2::new(0.25, 0.5)), BULLET_TAG),
+                (AABB::around_point(asteroid, Vec2::splat(1.0)), ASTEROID_TAG),
+            ];
+            for mode in MODES {
+                let mut buffer = colliders(&ids, &boxes, Some(RADIUS));
+                let events = collisions(&mut buffer, mode, true, 0);
+                assert_eq!(events.len(), 1, "{mode:?} {bullet} {asteroid}");
+                assert_eq!(pair_key(&events[0]), (ids[0], ids[1]));
+
+                let mut buffer = colliders(&ids, &boxes, None);
+                assert!(collisions(&mut buffer, mode, false, 0).is_empty());
+            }
+        }
+    }
 }
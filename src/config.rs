@@ -0,0 +1,235 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::telegraph::TelegraphConfig;
+use crate::time_attack::TimeAttackConfig;
+use crate::{AsteroidConfig, DifficultyConfig, MapConfig, ShipPhysics, WeaponHeatConfig};
+
+#[cfg(not(target_family = "wasm"))]
+use crate::{Difficulty, Plugin};
+#[cfg(not(target_family = "wasm"))]
+use brengin::{cecs::prelude::*, DeltaTime, Timer};
+#[cfg(not(target_family = "wasm"))]
+use std::time::SystemTime;
+
+#[cfg(not(target_family = "wasm"))]
+const CONFIG_PATH: &str = "config.toml";
+/// How often `config.toml` is checked for changes
+#[cfg(not(target_family = "wasm"))]
+const RELOAD_POLL: Duration = Duration::from_millis(500);
+
+/// Tuning of the game, read from `config.toml`
+///
+/// Every table and field is optional, anything the file leaves out keeps its default. The web
+/// build has no file to read and uses the copy of `config.toml` built into it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct GameConfig {
+    pub ship: ShipPhysics,
+    pub map: MapConfig,
+    pub asteroids: AsteroidConfig,
+    pub difficulty: DifficultyConfig,
+    pub time_attack: TimeAttackConfig,
+    pub telegraph: TelegraphConfig,
+    pub weapon_heat: WeaponHeatConfig,
+}
+
+impl GameConfig {
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// `None` if the file exists but couldn't be read or parsed, the error is logged
+    #[cfg(not(target_family = "wasm"))]
+    fn read() -> Option<Self> {
+        match std::fs::read_to_string(CONFIG_PATH) {
+            Ok(text) => Self::parse(&text)
+                .map_err(|err| tracing::error!(%err, "Failed to parse the config"))
+                .ok(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Some(Self::default()),
+            Err(err) => {
+                tracing::error!(?err, "Failed to read the config");
+                None
+            }
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load() -> Self {
+        Self::read().unwrap_or_default()
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn load() -> Self {
+        Self::parse(include_str!("../config.toml")).unwrap_or_else(|err| {
+            tracing::error!(%err, "Failed to parse the built in config, using the defaults");
+            Self::default()
+        })
+    }
+}
+
+/// Durations are written as seconds in the config
+pub(crate) fn secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let secs = f32::deserialize(deserializer)?;
+    Duration::try_from_secs_f32(secs).map_err(serde::de::Error::custom)
+}
+
+#[cfg(not(target_family = "wasm"))]
+struct ConfigWatcher {
+    poll: Timer,
+    /// Modification time of the file when it was last read, `None` if it didn't exist
+    modified: Option<SystemTime>,
+    /// The config the resources were last set from
+    current: GameConfig,
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn modified() -> Option<SystemTime> {
+    std::fs::metadata(CONFIG_PATH)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Sets `live` to `new` if the table changed since `old`, logging the change
+#[cfg(not(target_family = "wasm"))]
+fn apply<T: PartialEq + Clone + std::fmt::Debug>(table: &str, old: &T, new: &T, live: &mut T) {
+    if old != new {
+        tracing::info!(table, ?old, ?new, "Config changed");
+        *live = new.clone();
+    }
+}
+
+/// Applies `config.toml` again whenever it's saved, while the game runs
+///
+/// Only the tables that changed are applied. While the file doesn't parse, say halfway through an
+/// edit, the game keeps the last config that did.
+#[cfg(not(target_family = "wasm"))]
+fn config_reload_system(
+    dt: Res<DeltaTime>,
+    mut watcher: ResMut<ConfigWatcher>,
+    mut ship: ResMut<ShipPhysics>,
+    mut map: ResMut<MapConfig>,
+    mut asteroids: ResMut<AsteroidConfig>,
+    mut difficulty: ResMut<Difficulty>,
+    mut time_attack: ResMut<TimeAttackConfig>,
+    mut telegraph: ResMut<TelegraphConfig>,
+    mut weapon_heat: ResMut<WeaponHeatConfig>,
+) {
+    watcher.poll.update(dt.0);
+    if !watcher.poll.just_finished() {
+        return;
+    }
+    let modified = modified();
+    if modified == watcher.modified {
+        return;
+    }
+    watcher.modified = modified;
+    let Some(config) = GameConfig::read() else {
+        tracing::warn!("Keeping the last good config");
+        return;
+    };
+    let old = &watcher.current;
+    apply("ship", &old.ship, &config.ship, &mut ship);
+    apply("map", &old.map, &config.map, &mut map);
+    apply(
+        "asteroids",
+        &old.asteroids,
+        &config.asteroids,
+        &mut asteroids,
+    );
+    apply(
+        "difficulty",
+        &old.difficulty,
+        &config.difficulty,
+        &mut difficulty.config,
+    );
+    apply(
+        "time_attack",
+        &old.time_attack,
+        &config.time_attack,
+        &mut time_attack,
+    );
+    apply(
+        "telegraph",
+        &old.telegraph,
+        &config.telegraph,
+        &mut telegraph,
+    );
+    apply(
+        "weapon_heat",
+        &old.weapon_heat,
+        &config.weapon_heat,
+        &mut weapon_heat,
+    );
+    watcher.current = config;
+}
+
+/// Watches `config.toml` for changes, `0` is the config the game started with
+///
+/// Native only, the web build's config is built in and never changes.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) struct ConfigReloadPlugin(pub GameConfig);
+
+#[cfg(not(target_family = "wasm"))]
+impl Plugin for ConfigReloadPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::PreUpdate)
+            .add_system(config_reload_system);
+        app.insert_resource(ConfigWatcher {
+            poll: Timer::new(RELOAD_POLL, true),
+            modified: modified(),
+            current: self.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compares every table, [GameConfig] itself isn't `PartialEq`
+    fn assert_tables_eq(a: &GameConfig, b: &GameConfig) {
+        assert_eq!(a.ship, b.ship);
+        assert_eq!(a.map, b.map);
+        assert_eq!(a.asteroids, b.asteroids);
+        assert_eq!(a.difficulty, b.difficulty);
+        assert_eq!(a.time_attack, b.time_attack);
+        assert_eq!(a.telegraph, b.telegraph);
+        assert_eq!(a.weapon_heat, b.weapon_heat);
+    }
+
+    #[test]
+    fn shipped_config_lists_the_defaults() {
+        let config = GameConfig::parse(include_str!("../config.toml")).unwrap();
+        assert_tables_eq(&config, &GameConfig::default());
+        // the original handling, force is opt-in
+        assert_eq!(config.ship.thrust_model, crate::ThrustModel::Steer);
+    }
+
+    #[test]
+    fn missing_tables_and_fields_keep_their_defaults() {
+        assert_tables_eq(&GameConfig::parse("").unwrap(), &GameConfig::default());
+
+        let config = GameConfig::parse(
+            "[ship]\nmax_vel = 20.0\nthrust_model = \"force\"\n\n[map]\ngravity_well = true\n\n[difficulty]\nspawn_mode = \"endless\"\n\n[weapon_heat]\ncooling = 1.5\n\n[time_attack]\nduration = 90.0\n",
+        )
+        .unwrap();
+        let mut expected = GameConfig::default();
+        expected.ship.max_vel = 20.0;
+        expected.ship.thrust_model = crate::ThrustModel::Force;
+        expected.map.gravity_well = true;
+        expected.difficulty.spawn_mode = crate::SpawnMode::Endless;
+        expected.weapon_heat.cooling = 1.5;
+        expected.time_attack.duration = Duration::from_secs(90);
+        assert_tables_eq(&config, &expected);
+    }
+
+    #[test]
+    fn invalid_values_fail_to_parse() {
+        assert!(GameConfig::parse("[time_attack]\nduration = -1.0\n").is_err());
+        assert!(GameConfig::parse("[ship]\nmax_vel = \"fast\"\n").is_err());
+        assert!(GameConfig::parse("[ship]\nthrust_model = \"hover\"\n").is_err());
+        assert!(GameConfig::parse("[difficulty]\nspawn_mode = \"forever\"\n").is_err());
+    }
+}
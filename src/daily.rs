@@ -0,0 +1,140 @@
+use brengin::cecs::prelude::*;
+use brengin::glam::Vec3;
+
+use crate::{
+    layers, setup_player, spawn_digits, spawn_text, Player, PlayerCamera, Plugin, Rng,
+    SecondPlayer, Sprites,
+};
+
+/// Which game is played, picked when the game is launched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GameMode {
+    /// A fresh random field every launch
+    #[default]
+    Endless,
+    /// The field of the day, the same for everyone playing on the same UTC date
+    Daily,
+    /// As much score as possible before the clock runs out
+    TimeAttack,
+}
+
+/// A day of the Gregorian calendar, in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct UtcDate {
+    pub year: u32,
+    /// 1 to 12
+    pub month: u32,
+    /// 1 to 31
+    pub day: u32,
+}
+
+impl UtcDate {
+    #[cfg(not(target_family = "wasm"))]
+    pub fn today() -> Self {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Self::from_days_since_epoch(secs / (24 * 60 * 60))
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn today() -> Self {
+        let now = js_sys::Date::new_0();
+        Self {
+            year: now.get_utc_full_year(),
+            month: now.get_utc_month() + 1,
+            day: now.get_utc_date(),
+        }
+    }
+
+    /// Howard Hinnant's `civil_from_days`, counting 400 year eras from March 1st 0000 so leap
+    /// days fall at the end of the year
+    #[cfg(not(target_family = "wasm"))]
+    fn from_days_since_epoch(days: u64) -> Self {
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + u64::from(month <= 2);
+        Self {
+            year: year as u32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// The date read as a number, 2024-03-01 is 20240301
+    pub fn seed(&self) -> u64 {
+        u64::from(self.year) * 10_000 + u64::from(self.month) * 100 + u64::from(self.day)
+    }
+}
+
+/// Seed of the daily field, every attempt of the day starts from it
+struct DailySeed(pub u64);
+
+/// On the ship of an attempt that started from [DailySeed]
+struct Reseeded;
+
+/// Puts the daily seed back into [Rng] when a new first ship appears, so every attempt plays the
+/// same field instead of carrying on the sequence of the last one
+///
+/// Runs before anything of the new game had a chance to draw a number. A second player joining
+/// mid-game doesn't count as a new attempt.
+fn daily_reseed_system(
+    mut cmd: Commands,
+    seed: Res<DailySeed>,
+    mut rng: ResMut<Rng>,
+    q_player: Query<EntityId, (With<Player>, WithOut<Reseeded>, WithOut<SecondPlayer>)>,
+) {
+    let Some(id) = q_player.single() else {
+        return;
+    };
+    rng.0 = fastrand::Rng::with_seed(seed.0);
+    cmd.entity(id).insert(Reseeded);
+}
+
+/// "DAILY" above the date of the challenge, in the top corner of the screen
+fn setup_daily_hud(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    seed: Res<DailySeed>,
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+) {
+    let Some(camera_id) = q_camera.single() else {
+        return;
+    };
+    spawn_text(
+        &mut cmd,
+        Some(camera_id),
+        &sprites,
+        "DAILY",
+        Vec3::new(-45.0, 43.0, layers::UI),
+        1.0,
+        |_| {},
+    );
+    spawn_digits(
+        &mut cmd,
+        Some(camera_id),
+        &sprites,
+        seed.0,
+        Vec3::new(-45.0, 41.5, layers::UI),
+        1.0,
+        |_| {},
+    );
+}
+
+/// The daily challenge, added on top of a game seeded with [UtcDate::seed]
+pub(crate) struct DailyPlugin(pub UtcDate);
+
+impl Plugin for DailyPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.add_startup_system(setup_daily_hud.after(setup_player));
+        app.stage(crate::Stage::PreUpdate)
+            .add_system(daily_reseed_system);
+        app.insert_resource(DailySeed(self.0.seed()));
+    }
+}
@@ -0,0 +1,29 @@
+/// Queue of the events produced this frame
+///
+/// One system writes the queue, clearing it at the start of its run, and any number of systems
+/// ordered after it read it through `Res<Events<T>>`. The backing buffer keeps its capacity
+/// between frames.
+pub struct Events<T> {
+    events: Vec<T>,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter()
+    }
+
+    /// The queue itself, for the writer to fill in bulk
+    pub fn buffer_mut(&mut self) -> &mut Vec<T> {
+        &mut self.events
+    }
+}
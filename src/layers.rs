@@ -0,0 +1,50 @@
+//! Sprite depths, every z a sprite is placed at comes from here
+//!
+//! The camera looks down -z, so a larger z is nearer to the eye. Game objects live at z = 0 and
+//! the offsets are added to the position of whatever a sprite belongs to. Draw order itself is up
+//! to the brengin sprite renderer, give sprites that may overlap distinct depths instead of
+//! relying on spawn order.
+
+/// Map bounds and grid, under the backdrop
+pub const GRID: f32 = -0.6;
+/// Backdrop under the play field
+pub const BACKGROUND: f32 = -0.5;
+/// Asteroids, bosses included
+pub const ASTEROID: f32 = 0.0;
+pub const PLAYER: f32 = 0.0;
+pub const UFO: f32 = 0.0;
+pub const POWERUP: f32 = 0.0;
+/// Bullets and missiles fired by the ship
+pub const BULLET: f32 = 0.0;
+/// Thrust flame, relative to the ship
+pub const THRUST: f32 = 0.1;
+/// Shield ring, relative to the ship
+pub const SHIELD: f32 = 0.2;
+/// Bullet tracer, relative to the bullet
+pub const TRACER: f32 = -0.1;
+/// Colorblind mode markers, relative to the marked object
+pub const MARKER: f32 = 0.1;
+/// Health pips, relative to the damaged asteroid
+pub const HEALTH_PIP: f32 = 0.1;
+/// Heat gauge track, relative to the ship
+pub const HEAT_GAUGE: f32 = 0.2;
+/// Heat filled in over the track
+pub const HEAT_GAUGE_FILL: f32 = 0.25;
+/// Above the boss it sits on
+pub const WEAK_POINT: f32 = 0.1;
+/// Arrows pointing at off-screen threats, relative to the camera
+pub const INDICATOR: f32 = 0.5;
+/// Warning blinking where a UFO or boss is about to spawn
+pub const TELEGRAPH: f32 = 0.4;
+/// Relative to the spot the ship jumped from or landed on
+pub const TELEPORT_EFFECT: f32 = 0.2;
+/// Relative to the destroyed object
+pub const EXPLOSION: f32 = 0.3;
+/// Game over and wave banners, absolute
+pub const BANNER: f32 = -1.0;
+/// HUD digits and text, relative to the camera
+pub const UI: f32 = -5.0;
+/// Full screen fade, relative to the camera, in front of the HUD
+pub const OVERLAY: f32 = -4.0;
+/// Debug collider outlines, absolute
+pub const DEBUG: f32 = 1.0;
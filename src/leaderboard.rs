@@ -0,0 +1,322 @@
+use std::fmt::Write as _;
+
+use brengin::cecs::commands::EntityCommands;
+use brengin::cecs::prelude::*;
+use brengin::glam::Vec3;
+use brengin::transform::GlobalTransform;
+use brengin::winit::event::VirtualKeyCode;
+use brengin::KeyBoardInputs;
+
+use crate::{spawn_digits, spawn_text, GameEntity, GameOver, Plugin, Score, Sprites};
+
+/// Entries kept on the board
+pub const MAX_ENTRIES: usize = 10;
+pub const NAME_LEN: usize = 3;
+
+#[cfg(not(target_family = "wasm"))]
+const LEADERBOARD_PATH: &str = "leaderboard.txt";
+#[cfg(target_family = "wasm")]
+const LEADERBOARD_KEY: &str = "asteroids-leaderboard";
+
+const LETTER_KEYS: [VirtualKeyCode; 26] = [
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    /// Upper case ASCII letters
+    pub name: [u8; NAME_LEN],
+    pub score: u64,
+}
+
+impl LeaderboardEntry {
+    pub fn name(&self) -> &str {
+        std::str::from_utf8(&self.name).unwrap_or("???")
+    }
+}
+
+/// The best scores, highest first
+#[derive(Debug, Default, Clone)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    /// Whether `score` would make it onto the board
+    pub fn qualifies(&self, score: u64) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_ENTRIES || score > self.entries[MAX_ENTRIES - 1].score)
+    }
+
+    /// Returns the rank of the new entry, `None` if it didn't make the cut
+    ///
+    /// Ties go below the entries already on the board, the earlier run keeps its place.
+    pub fn insert(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+        let rank = self.entries.partition_point(|e| e.score >= entry.score);
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    /// One `NAME SCORE` line per entry
+    fn to_text(&self) -> String {
+        let mut out = String::with_capacity(16 * self.entries.len());
+        for entry in &self.entries {
+            let _ = writeln!(out, "{} {}", entry.name(), entry.score);
+        }
+        out
+    }
+
+    /// Lines that don't parse are skipped, a damaged file costs those entries and not the board
+    fn from_text(text: &str) -> Self {
+        let mut board = Self::default();
+        for line in text.lines() {
+            let Some((name, score)) = line.split_once(' ') else {
+                continue;
+            };
+            let (Ok(name), Ok(score)) =
+                (<[u8; NAME_LEN]>::try_from(name.as_bytes()), score.parse())
+            else {
+                continue;
+            };
+            if name.iter().all(u8::is_ascii_uppercase) {
+                board.insert(LeaderboardEntry { name, score });
+            }
+        }
+        board
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load() -> Self {
+        match std::fs::read_to_string(LEADERBOARD_PATH) {
+            Ok(text) => Self::from_text(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                tracing::error!(?err, "Failed to read the leaderboard");
+                Self::default()
+            }
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn save(&self) {
+        if let Err(err) = std::fs::write(LEADERBOARD_PATH, self.to_text()) {
+            tracing::error!(?err, "Failed to save the leaderboard");
+        }
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn load() -> Self {
+        local_storage()
+            .and_then(|storage| storage.get_item(LEADERBOARD_KEY).ok().flatten())
+            .map(|text| Self::from_text(&text))
+            .unwrap_or_default()
+    }
+
+    #[cfg(target_family = "wasm")]
+    pub fn save(&self) {
+        let saved = local_storage()
+            .map(|storage| storage.set_item(LEADERBOARD_KEY, &self.to_text()).is_ok())
+            .unwrap_or(false);
+        if !saved {
+            tracing::error!("Failed to save the leaderboard");
+        }
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+/// Progress of typing a name for the board on the game over screen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NameEntry {
+    /// No game over yet
+    #[default]
+    Idle,
+    Entering {
+        name: [u8; NAME_LEN],
+        len: usize,
+        score: u64,
+    },
+    /// Name submitted, or the score didn't qualify, the board is shown
+    Done,
+}
+
+impl NameEntry {
+    pub fn is_entering(&self) -> bool {
+        matches!(self, NameEntry::Entering { .. })
+    }
+}
+
+/// Letter keys type the name, Back erases, Return submits a full name
+fn name_entry_system(
+    inputs: Res<KeyBoardInputs>,
+    score: Res<Score>,
+    mut entry: ResMut<NameEntry>,
+    mut board: ResMut<Leaderboard>,
+    q_game_over: Query<&(), With<GameOver>>,
+) {
+    if q_game_over.is_empty() {
+        *entry = NameEntry::Idle;
+        return;
+    }
+    match &mut *entry {
+        NameEntry::Idle => {
+            let score = score.score.0;
+            *entry = if board.qualifies(score) {
+                NameEntry::Entering {
+                    name: [b'_'; NAME_LEN],
+                    len: 0,
+                    score,
+                }
+            } else {
+                NameEntry::Done
+            };
+        }
+        NameEntry::Entering { name, len, score } => {
+            for (i, key) in LETTER_KEYS.iter().enumerate() {
+                if *len < NAME_LEN && inputs.just_released.contains(key) {
+                    name[*len] = b'A' + i as u8;
+                    *len += 1;
+                }
+            }
+            if *len > 0 && inputs.just_released.contains(&VirtualKeyCode::Back) {
+                *len -= 1;
+                name[*len] = b'_';
+            }
+            if *len == NAME_LEN && inputs.just_released.contains(&VirtualKeyCode::Return) {
+                board.insert(LeaderboardEntry {
+                    name: *name,
+                    score: *score,
+                });
+                board.save();
+                *entry = NameEntry::Done;
+            }
+        }
+        NameEntry::Done => {}
+    }
+}
+
+struct LeaderboardGlyph;
+
+/// The [NameEntry] state on screen, laid out again whenever it changes
+struct RenderedNameEntry(Option<NameEntry>);
+
+const GLYPH_SCALE: f32 = 2.0;
+const ROW_SCALE: f32 = 1.0;
+const ROW_HEIGHT: f32 = 1.5;
+/// Below the final score of the game over screen
+const TOP_OFFSET: f32 = 16.0;
+
+fn render_leaderboard_system(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    entry: Res<NameEntry>,
+    board: Res<Leaderboard>,
+    mut rendered: ResMut<RenderedNameEntry>,
+    q_game_over: Query<&GlobalTransform, With<GameOver>>,
+    q_glyphs: Query<EntityId, With<LeaderboardGlyph>>,
+) {
+    // dying to two things in the same frame stacks two game over screens in the same spot
+    let Some(tr) = q_game_over.iter().next() else {
+        // the glyphs are game entities, the restart took them
+        rendered.0 = None;
+        return;
+    };
+    if rendered.0 == Some(*entry) {
+        return;
+    }
+    rendered.0 = Some(*entry);
+    for id in q_glyphs.iter() {
+        cmd.delete(id);
+    }
+    let mut tag = |cmd: &mut EntityCommands| {
+        cmd.insert_bundle((LeaderboardGlyph, GameEntity));
+    };
+    let mut origin = tr.0.pos;
+    origin.y -= TOP_OFFSET;
+    match *entry {
+        NameEntry::Idle => {}
+        NameEntry::Entering { name, .. } => {
+            origin.x -= (NAME_LEN as f32 - 1.0) * GLYPH_SCALE * 0.5;
+            let name = std::str::from_utf8(&name).unwrap_or_default();
+            spawn_text(
+                &mut cmd,
+                None,
+                &sprites,
+                name,
+                origin,
+                GLYPH_SCALE,
+                &mut tag,
+            );
+        }
+        NameEntry::Done => {
+            for (i, entry) in board.entries.iter().enumerate() {
+                let row = origin - Vec3::Y * ROW_HEIGHT * i as f32;
+                // name on the left, score on the right, screen x runs opposite to world x
+                spawn_text(
+                    &mut cmd,
+                    None,
+                    &sprites,
+                    entry.name(),
+                    row + Vec3::X * 2.0 * ROW_SCALE,
+                    ROW_SCALE,
+                    &mut tag,
+                );
+                let width = entry.score.to_string().len() as f32 * ROW_SCALE;
+                spawn_digits(
+                    &mut cmd,
+                    None,
+                    &sprites,
+                    entry.score,
+                    row - Vec3::X * width,
+                    ROW_SCALE,
+                    &mut tag,
+                );
+            }
+        }
+    }
+}
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::Update)
+            .add_system(name_entry_system)
+            .add_system(render_leaderboard_system.after(name_entry_system));
+        app.insert_resource(Leaderboard::load());
+        app.insert_resource(NameEntry::default());
+        app.insert_resource(RenderedNameEntry(None));
+    }
+}
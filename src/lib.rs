@@ -1,7 +1,25 @@
-#![windows_subsystem = "windows"]
+// systems take every resource and query they touch as a parameter, and the queries spell out
+// their fragments and filters inline, grouping them would only hide what a system reads
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
+mod boss;
 mod collision;
+mod config;
+mod daily;
+mod events;
+mod layers;
+mod leaderboard;
+mod placeholder;
+#[cfg(not(target_family = "wasm"))]
+mod replay;
+mod score_feed;
+mod telegraph;
+mod tilt;
+mod time_attack;
+mod touch;
+mod ufo;
 
+use std::collections::HashSet;
 use std::num::Wrapping;
 use std::time::Duration;
 use std::usize;
@@ -13,36 +31,491 @@ use brengin::glam::{self, Vec2, Vec3};
 use brengin::renderer::sprite_renderer::{self, sprite_sheet_bundle, SpriteInstance, SpriteSheet};
 use brengin::renderer::{self, GraphicsState};
 use brengin::transform::{self, transform_bundle, GlobalTransform, Transform};
+use serde::Deserialize;
+use smallvec::SmallVec;
+
 use brengin::{
     assets, winit::event::VirtualKeyCode, App, DefaultPlugins, DeltaTime, KeyBoardInputs, Plugin,
     Stage, Timer,
 };
-use collision::{aabb_bundle, CollisionEvent, CollisionPlugin, CollisionTag, Collisions, AABB};
+use collision::{
+    aabb_bundle, raycast, untagged_aabb_bundle, AABBBuffer, BroadphaseMode, ColliderKind,
+    CollisionEvent, CollisionPlugin, CollisionTag, Collisions, GlobalAABB, SelfColliding,
+    WrapRadius, AABB,
+};
+use config::GameConfig;
 
 use brengin::cecs::prelude::*;
 
 use brengin::quat_ext::{PrimaryAxis, RotationExtension};
 
+pub use daily::GameMode;
+pub use leaderboard::{Leaderboard, LeaderboardEntry};
+#[cfg(not(target_family = "wasm"))]
+pub use replay::{Replay, ReplayFrame};
+pub use score_feed::ScoreFeed;
+pub use tilt::{Tilt, TiltConfig};
+pub use touch::{TouchAction, VirtualKeys};
+
+/// Default [MapConfig::radius]
 const MAP_RADIUS: f32 = 25.0;
-const TARGET: usize = 100;
-const MAX_ACC: f32 = 25.0;
-const MAX_VEL: f32 = 12.0;
-const INERTIA: f32 = 1.0;
-
-const ASTEROID_TAG: CollisionTag = CollisionTag { src: 1, dst: 0xFE };
-const BULLET_TAG: CollisionTag = CollisionTag {
-    src: 1 << 1,
-    dst: 1,
+/// Tries at finding a free spot for a new asteroid or power-up before giving up on it
+const MAX_SPAWN_ATTEMPTS: u32 = 64;
+/// New asteroids keep at least this far from a ship
+const SHIP_SPAWN_CLEARANCE: f32 = 5.0;
+// waves
+const WAVE_BASE_COUNT: usize = 20;
+const WAVE_COUNT_STEP: usize = 8;
+const WAVE_SPEED_STEP: f32 = 0.1;
+const WAVE_CLEAR_BONUS: u64 = 50;
+const WAVE_BREAK: Duration = Duration::from_secs(2);
+const PLAYER_SCALE: f32 = 0.5;
+/// Side of [Sprites::placeholder], in pixels
+const PLACEHOLDER_SIZE: f32 = 32.0;
+/// Time a turn has to be held to reach the full turn rate
+const ROTATION_RAMP: Duration = Duration::from_millis(600);
+/// Fraction of the turn rate a turn starts at
+const MIN_TURN_FRACTION: f32 = 0.3;
+const PLAYER_COLLIDER: Vec2 = Vec2::splat(0.5);
+/// Gap between the edge of the ship's collider and where its shots appear
+const MUZZLE_GAP: f32 = 0.05;
+/// How much the ship is squashed horizontally at full bank
+const MAX_BANK: f32 = 0.2;
+/// Scale of the thrust flame at full acceleration
+const THRUST_MAX_SCALE: f32 = 1.4;
+/// Animation speed multiplier of the thrust flame at full acceleration
+const THRUST_MAX_ANIMATION_SPEED: f32 = 2.0;
+/// Trail particles emitted per second while thrusting at standstill
+const TRAIL_BASE_RATE: f32 = 10.0;
+/// Extra trail particles per second for each unit of ship speed
+const TRAIL_RATE_PER_SPEED: f32 = 2.0;
+/// Fraction of the ship velocity a trail particle starts with
+const TRAIL_INHERIT_VELOCITY: f32 = 0.3;
+const HYPERSPACE_COOLDOWN: Duration = Duration::from_secs(3);
+const POWERUP_INTERVAL: Duration = Duration::from_secs(12);
+const POWERUP_LIFETIME: Duration = Duration::from_secs(10);
+const MAX_SHIELD: u32 = 3;
+const FIRE_COOLDOWN: Duration = Duration::from_millis(200);
+const RAPID_FIRE_COOLDOWN: Duration = Duration::from_millis(80);
+const WEAPON_UPGRADE_DURATION: Duration = Duration::from_secs(10);
+/// Angle between the bullets of a spread shot
+const SPREAD_ANGLE: f32 = 0.2;
+/// Asteroids a bullet fired while [Piercing] passes through before it's spent
+const PIERCE_COUNT: u32 = 2;
+/// Largest random deviation of a bullet, in radians per unit of ship speed
+const BULLET_INACCURACY: f32 = 0.005;
+/// Mass of a bullet relative to a full size asteroid, for the momentum it passes to the pieces
+const BULLET_MASS: f32 = 0.05;
+/// Speed at which split pieces move apart, relative to the speed of the parent
+const SPLIT_SPREAD: f32 = 0.5;
+const INITIAL_BOMBS: u8 = 1;
+const MAX_BOMBS: u8 = 3;
+const BOMB_RADIUS: f32 = 15.0;
+/// Missiles granted by a power-up
+const MISSILE_AMMO: u8 = 3;
+const MAX_MISSILES: u8 = 9;
+const MISSILE_SPEED: f32 = 8.0;
+/// Largest change of heading, in radians per second
+const MISSILE_TURN_RATE: f32 = 3.0;
+const MISSILE_LIFETIME: Duration = Duration::from_secs(4);
+/// Bullet hits a missile is worth
+const MISSILE_DAMAGE: u32 = 3;
+/// Mass of a missile relative to a full size asteroid, see [BULLET_MASS]
+const MISSILE_MASS: f32 = 0.15;
+// laser
+const LASER_RANGE: f32 = 20.0;
+const LASER_COOLDOWN: Duration = Duration::from_millis(150);
+const LASER_BEAM_TIME: Duration = Duration::from_millis(60);
+const LASER_BEAM_WIDTH: f32 = 0.1;
+const BULLET_POOL_SIZE: usize = 64;
+/// Length of a screen fade
+const FADE_TIME: Duration = Duration::from_millis(400);
+/// How dark the screen gets behind the game over screen
+const DEATH_FADE_ALPHA: f32 = 0.5;
+/// How dark the screen gets between waves
+const WAVE_FADE_ALPHA: f32 = 0.3;
+/// Weight of the newest frame in the smoothed frame rate
+const FPS_SMOOTHING: f32 = 0.1;
+/// How often the displayed frame rate changes
+const FPS_REFRESH: Duration = Duration::from_millis(250);
+/// Fraction of its speed the wreck of the ship loses per second
+const DEAD_DRIFT_DRAG: f32 = 0.5;
+/// Half the size of the view on the play field, around the camera position
+const VIEW_HALF_EXTENT: Vec2 = Vec2::new(20.0, 12.0);
+/// Distance of the camera eye from the play field
+const CAMERA_DISTANCE: f32 = 20.0;
+/// Room kept between the ships and the edge of the view in a co-op game
+const COOP_VIEW_MARGIN: f32 = 4.0;
+/// The camera stops pulling back at this zoom, ships further apart can leave the view
+const MAX_CAMERA_ZOOM: f32 = 2.0;
+/// Where the second ship joins, relative to the first one
+const SECOND_PLAYER_OFFSET: Vec3 = Vec3::new(2.0, 0.0, 0.0);
+/// Gap between an off-screen arrow and the edge of the view
+const INDICATOR_MARGIN: f32 = 1.0;
+/// Game speed right after the player dies
+const DEATH_TIME_SCALE: f32 = 0.3;
+/// Time it takes for the game to get back to full speed after a death
+const SLOW_MOTION_RECOVERY: Duration = Duration::from_secs(1);
+const MAX_BULLETS: usize = 32;
+/// Travel time covered by a bullet tracer, the tracer is longer the faster the bullet goes
+const TRACER_TIME: f32 = 0.03;
+const TRACER_WIDTH: f32 = 0.1;
+/// Distance under which gravity wells stop getting stronger
+const GRAVITY_MIN_DISTANCE: f32 = 2.0;
+
+/// Collision classes, each one owns a single bit of [CollisionTag::src]
+///
+/// Add new kinds of colliders here instead of allocating bits by hand. The pairs that produce
+/// events, checked at compile time below:
+///
+/// |             | Asteroid | Bullet | Player | PowerUp | Missile | Ufo | EnemyBullet | Boss |
+/// |-------------|----------|--------|--------|---------|---------|-----|-------------|------|
+/// | Asteroid    | config   | yes    | yes    | no      | yes     | yes | no          | no   |
+/// | Bullet      |          | no     | no     | no      | no      | yes | no          | yes  |
+/// | Player      |          |        | no     | yes     | no      | yes | yes         | yes  |
+/// | PowerUp     |          |        |        | no      | no      | no  | no          | no   |
+/// | Missile     |          |        |        |         | no      | yes | no          | yes  |
+/// | Ufo         |          |        |        |         |         | no  | no          | no   |
+/// | EnemyBullet |          |        |        |         |         |     | no          | no   |
+/// | Boss        |          |        |        |         |         |     |             | no   |
+///
+/// Asteroids only hit each other while [AsteroidConfig::collisions] is on, through
+/// [SelfColliding].
+///
+/// Bullets and missiles never hit each other nor the ship that fired them, even though they spawn
+/// inside it. The same goes for the shots of a UFO. The boss and its weak points share a layer and
+/// pass through asteroids, the boss plows through the field instead of bouncing around in it.
+///
+/// Every bit of the `u8` is taken, another layer means widening [CollisionTag].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CollisionLayers {
+    Asteroid,
+    Bullet,
+    Player,
+    PowerUp,
+    Missile,
+    Ufo,
+    EnemyBullet,
+    Boss,
+}
+
+impl CollisionLayers {
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Tag of a collider in this layer, colliding with the layers in `dst`
+    const fn tag(self, dst: &[Self]) -> CollisionTag {
+        let mut mask = 0;
+        let mut i = 0;
+        while i < dst.len() {
+            mask |= dst[i].bit();
+            i += 1;
+        }
+        CollisionTag {
+            src: self.bit(),
+            dst: mask,
+        }
+    }
+
+    pub const fn asteroid() -> CollisionTag {
+        Self::Asteroid.tag(&[Self::Bullet, Self::Player, Self::Missile, Self::Ufo])
+    }
+
+    pub const fn bullet() -> CollisionTag {
+        Self::Bullet.tag(&[Self::Asteroid, Self::Ufo, Self::Boss])
+    }
+
+    pub const fn player() -> CollisionTag {
+        Self::Player.tag(&[Self::Asteroid, Self::Ufo, Self::EnemyBullet, Self::Boss])
+    }
+
+    pub const fn powerup() -> CollisionTag {
+        Self::PowerUp.tag(&[Self::Player])
+    }
+
+    pub const fn missile() -> CollisionTag {
+        Self::Missile.tag(&[Self::Asteroid, Self::Ufo, Self::Boss])
+    }
+
+    pub const fn ufo() -> CollisionTag {
+        Self::Ufo.tag(&[Self::Asteroid, Self::Bullet, Self::Player, Self::Missile])
+    }
+
+    pub const fn enemy_bullet() -> CollisionTag {
+        Self::EnemyBullet.tag(&[Self::Player])
+    }
+
+    pub const fn boss() -> CollisionTag {
+        Self::Boss.tag(&[Self::Bullet, Self::Player, Self::Missile])
+    }
+}
+
+const ASTEROID_TAG: CollisionTag = CollisionLayers::asteroid();
+const BULLET_TAG: CollisionTag = CollisionLayers::bullet();
+const PLAYER_TAG: CollisionTag = CollisionLayers::player();
+const POWERUP_TAG: CollisionTag = CollisionLayers::powerup();
+const MISSILE_TAG: CollisionTag = CollisionLayers::missile();
+const UFO_TAG: CollisionTag = CollisionLayers::ufo();
+const ENEMY_BULLET_TAG: CollisionTag = CollisionLayers::enemy_bullet();
+const BOSS_TAG: CollisionTag = CollisionLayers::boss();
+
+/// Whether a pair of colliders produces collision events, matching the broadphase
+const fn interacts(a: CollisionTag, b: CollisionTag) -> bool {
+    (a.src & b.dst != 0) || (b.src & a.dst != 0)
+}
+
+// the interaction table of [CollisionLayers]
+const _: () = {
+    let tags = [
+        ASTEROID_TAG,
+        BULLET_TAG,
+        PLAYER_TAG,
+        POWERUP_TAG,
+        MISSILE_TAG,
+        UFO_TAG,
+        ENEMY_BULLET_TAG,
+        BOSS_TAG,
+    ];
+    let expected = [
+        [false, true, true, false, true, true, false, false],
+        [true, false, false, false, false, true, false, true],
+        [true, false, false, true, false, true, true, true],
+        [false, false, true, false, false, false, false, false],
+        [true, false, false, false, false, true, false, true],
+        [true, true, true, false, true, false, false, false],
+        [false, false, true, false, false, false, false, false],
+        [false, true, true, false, true, false, false, false],
+    ];
+    let mut i = 0;
+    while i < tags.len() {
+        let mut j = 0;
+        while j < tags.len() {
+            assert!(
+                interacts(tags[i], tags[j]) == expected[i][j],
+                "collision tags don't match the interaction table"
+            );
+            j += 1;
+        }
+        i += 1;
+    }
 };
-const PLAYER_TAG: CollisionTag = CollisionTag {
-    src: 1 << 2,
-    dst: 1,
+
+// every tag must own exactly one src bit, and no two tags may share it
+const _: () = {
+    let tags = [
+        ASTEROID_TAG,
+        BULLET_TAG,
+        PLAYER_TAG,
+        POWERUP_TAG,
+        MISSILE_TAG,
+        UFO_TAG,
+        ENEMY_BULLET_TAG,
+        BOSS_TAG,
+    ];
+    let mut seen = 0u8;
+    let mut i = 0;
+    while i < tags.len() {
+        let src = tags[i].src;
+        assert!(
+            src.count_ones() == 1,
+            "collision tags need exactly one src bit"
+        );
+        assert!(seen & src == 0, "collision tags share a src bit");
+        seen |= src;
+        i += 1;
+    }
 };
 
+/// Source of all gameplay randomness, seeded at startup so runs can be reproduced
+struct Rng(pub fastrand::Rng);
+
+/// The difficulty ramp
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct DifficultyConfig {
+    pub spawn_mode: SpawnMode,
+    /// Asteroids spawned at once when an endless game starts
+    pub initial_asteroids: usize,
+    /// Most asteroids on the map at level 0, endless games top the field up to it
+    pub base_asteroids: usize,
+    /// Asteroids spawned per second while an endless field is below its size
+    pub spawn_rate: f32,
+    /// Time alive per difficulty level
+    #[serde(deserialize_with = "config::secs")]
+    pub level_time: Duration,
+    pub max_level: u32,
+    /// Extra asteroids on the map per difficulty level
+    pub asteroids_per_level: usize,
+    /// Asteroid speed multiplier gained per difficulty level
+    pub speed_per_level: f32,
+}
+
+impl Default for DifficultyConfig {
+    fn default() -> Self {
+        Self {
+            spawn_mode: SpawnMode::Waves,
+            initial_asteroids: 40,
+            base_asteroids: 100,
+            spawn_rate: 10.0,
+            level_time: Duration::from_secs(30),
+            max_level: 10,
+            asteroids_per_level: 10,
+            speed_per_level: 0.15,
+        }
+    }
+}
+
+/// Increases with time spent alive, see [DifficultyConfig] for the curve
+#[derive(Default)]
+struct Difficulty {
+    pub elapsed: Duration,
+    pub level: u32,
+    /// Asteroids that can be spawned right away, builds up at [DifficultyConfig::spawn_rate]
+    pub spawn_budget: f32,
+    pub config: DifficultyConfig,
+}
+
+impl Difficulty {
+    fn new(config: DifficultyConfig) -> Self {
+        let mut difficulty = Self {
+            config,
+            ..Default::default()
+        };
+        difficulty.reset();
+        difficulty
+    }
+
+    fn target_asteroids(&self) -> usize {
+        self.config.base_asteroids + self.level as usize * self.config.asteroids_per_level
+    }
+
+    fn speed_multiplier(&self) -> f32 {
+        1.0 + self.level as f32 * self.config.speed_per_level
+    }
+
+    /// Asteroids to spawn this frame, `dt` after the last one, with `count` on the map
+    fn take_spawns(&mut self, dt: Duration, count: usize) -> usize {
+        let target = self.target_asteroids();
+        if count >= target {
+            return 0;
+        }
+        self.spawn_budget += self.config.spawn_rate * dt.as_secs_f32();
+        let n = (self.spawn_budget as usize).min(target - count);
+        self.spawn_budget -= n as f32;
+        if count + n == target {
+            // a full field doesn't save up, the next asteroid destroyed isn't replaced in a burst
+            self.spawn_budget = self.spawn_budget.fract();
+        }
+        n
+    }
+
+    /// Back to level 0 with the initial asteroids due, keeping the config
+    fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.level = 0;
+        self.spawn_budget = self.config.initial_asteroids as f32;
+    }
+}
+
+fn difficulty_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut difficulty: ResMut<Difficulty>,
+    q_player: Query<&(), With<Player>>,
+) {
+    if q_player.is_empty() {
+        return;
+    }
+    difficulty.elapsed += time_scale.scale(&dt);
+    let config = &difficulty.config;
+    let level = (difficulty.elapsed.as_secs_f32() / config.level_time.as_secs_f32()) as u32;
+    let level = level.min(config.max_level);
+    if level != difficulty.level {
+        tracing::debug!(level, "Difficulty increased");
+        difficulty.level = level;
+    }
+}
+
 /// Every entity that's part of the game logic (that needs to be deleted on restart)
 struct GameEntity;
 struct Asteroid;
+
+/// Species of asteroid, split pieces keep the kind of their parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AsteroidKind {
+    Rocky,
+    Icy,
+    Metallic,
+}
+
+struct AsteroidStats {
+    /// Collider size at full scale, the art of each sheet is drawn to match
+    pub size: f32,
+    /// Bullet hits it takes to destroy one
+    pub health: u32,
+    pub score: u64,
+    /// Relative chance of spawning
+    pub spawn_weight: f32,
+    /// Frames in the sheet of the kind, each asteroid picks one for its lifetime
+    pub variants: u32,
+}
+
+impl AsteroidKind {
+    const ALL: [Self; 3] = [Self::Rocky, Self::Icy, Self::Metallic];
+
+    pub fn stats(self) -> AsteroidStats {
+        match self {
+            AsteroidKind::Rocky => AsteroidStats {
+                size: 0.8,
+                health: 1,
+                score: 1,
+                spawn_weight: 0.7,
+                variants: 2,
+            },
+            AsteroidKind::Icy => AsteroidStats {
+                size: 0.7,
+                health: 1,
+                score: 2,
+                spawn_weight: 0.2,
+                variants: 2,
+            },
+            AsteroidKind::Metallic => AsteroidStats {
+                size: 0.9,
+                health: 3,
+                score: 5,
+                spawn_weight: 0.1,
+                variants: 2,
+            },
+        }
+    }
+
+    pub fn sheet(self, sprites: &Sprites) -> Handle<SpriteSheet> {
+        match self {
+            AsteroidKind::Rocky => sprites.asteroid_sheet.clone(),
+            AsteroidKind::Icy => sprites.asteroid_icy_sheet.clone(),
+            AsteroidKind::Metallic => sprites.asteroid_metal_sheet.clone(),
+        }
+    }
+
+    pub fn random(rng: &mut fastrand::Rng) -> Self {
+        let total: f32 = Self::ALL.iter().map(|k| k.stats().spawn_weight).sum();
+        let mut pick = rng.f32() * total;
+        for kind in Self::ALL {
+            pick -= kind.stats().spawn_weight;
+            if pick < 0.0 {
+                return kind;
+            }
+        }
+        AsteroidKind::Rocky
+    }
+}
+
+/// Remaining bullet hits an asteroid can take
+struct Health(pub u32);
 struct Bullet;
+/// Bullet owned by the [BulletPool], deactivated instead of deleted
+struct Pooled;
 struct LifeTime(pub Timer);
 
 struct Score {
@@ -56,6 +529,55 @@ struct Player {
     pub acceleration: f32,
 }
 
+/// Keys flying a ship
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeyBindings {
+    pub left: VirtualKeyCode,
+    pub right: VirtualKeyCode,
+    pub thrust: VirtualKeyCode,
+    pub fire: VirtualKeyCode,
+}
+
+impl KeyBindings {
+    /// Turn input, -1 is left and 1 is right
+    pub fn turn(&self, inputs: &KeyBoardInputs) -> f32 {
+        let mut dir = 0.0;
+        if inputs.pressed.contains(&self.right) {
+            dir += 1.0;
+        }
+        if inputs.pressed.contains(&self.left) {
+            dir -= 1.0;
+        }
+        dir
+    }
+}
+
+const PLAYER_ONE_KEYS: KeyBindings = KeyBindings {
+    left: VirtualKeyCode::A,
+    right: VirtualKeyCode::D,
+    thrust: VirtualKeyCode::W,
+    fire: VirtualKeyCode::Space,
+};
+
+const PLAYER_TWO_KEYS: KeyBindings = KeyBindings {
+    left: VirtualKeyCode::Left,
+    right: VirtualKeyCode::Right,
+    thrust: VirtualKeyCode::Up,
+    fire: VirtualKeyCode::Return,
+};
+
+/// Ship of the second player in a co-op game
+///
+/// Flies with the blaster only, bombs, missiles, the laser and hyperspace belong to the first
+/// player.
+struct SecondPlayer;
+
+/// Co-op is on, toggled with F5
+struct CoOp(pub bool);
+
+/// New asteroids keep at least this far from the entity
+struct SpawnClearance(pub f32);
+
 #[derive(Default)]
 struct Velocity(pub Vec2);
 
@@ -64,11 +586,244 @@ struct UniformAnimation {
     pub n: u32,
 }
 struct RotationTime(pub Duration);
+
+impl RotationTime {
+    /// Advances the ramp of a held turn by `dt`, returning the rotation speed, which goes from a
+    /// fraction of `turn_rate` to the full rate over [ROTATION_RAMP]
+    fn ramp(&mut self, dt: Duration, turn_rate: f32) -> f32 {
+        self.0 = (self.0 + dt).min(ROTATION_RAMP);
+        let t = self.0.as_secs_f32() / ROTATION_RAMP.as_secs_f32();
+        turn_rate * (MIN_TURN_FRACTION + (1.0 - MIN_TURN_FRACTION) * t)
+    }
+}
+
+/// How thrust moves the ship
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ThrustModel {
+    /// Thrust accelerates the ship along its heading, momentum carries it sideways while it turns
+    Force,
+    /// The velocity swings round to the heading while thrusting, the ship goes where it points
+    #[default]
+    Steer,
+}
+
+/// Handling of the player's ship
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct ShipPhysics {
+    pub thrust_model: ThrustModel,
+    pub max_acc: f32,
+    pub max_vel: f32,
+    /// Speed lost per second while not thrusting
+    pub friction: f32,
+    /// Rotation speed once a turn has been held for [ROTATION_RAMP], in radians per second
+    pub turn_rate: f32,
+    /// Chance that a hyperspace jump lands the ship on an asteroid, see
+    /// [ShipPhysics::hyperspace_risk]
+    pub hyperspace_risk: f32,
+}
+
+impl ShipPhysics {
+    /// [ShipPhysics::hyperspace_risk] as a probability, 0..1
+    fn hyperspace_risk(&self) -> f32 {
+        self.hyperspace_risk.clamp(0.0, 1.0)
+    }
+
+    /// Velocity of a ship moving at `vel` after thrusting towards `facing` for `dt` seconds
+    fn thrust(&self, player: &mut Player, vel: Vec2, facing: Vec2, dt: f32) -> Vec2 {
+        // max acceleration in 0.3 seconds
+        player.acceleration = (player.acceleration + dt * self.max_acc * 3.0).min(self.max_acc);
+        match self.thrust_model {
+            ThrustModel::Force => {
+                let vel = (vel + facing * player.acceleration * dt).clamp_length_max(self.max_vel);
+                player.velocity = vel.length();
+                vel
+            }
+            ThrustModel::Steer => {
+                player.velocity = (player.velocity + player.acceleration * dt).min(self.max_vel);
+                vel.lerp(facing * player.velocity, dt)
+            }
+        }
+    }
+}
+
+impl Default for ShipPhysics {
+    fn default() -> Self {
+        Self {
+            thrust_model: ThrustModel::default(),
+            max_acc: 25.0,
+            max_vel: 12.0,
+            friction: 1.0,
+            turn_rate: 3.0,
+            hyperspace_risk: 0.1,
+        }
+    }
+}
+/// Current banking of the ship, -1..1, negative when turning left
+struct Bank(pub f32);
 struct PlayerCamera {
     follow_speed: f32,
+    /// Where the player was last seen, the camera settles here once the player is gone
+    last_seen: Vec3,
+    /// How much further the camera pulls back to keep every ship in view, 1 is no zoom
+    zoom: f32,
+}
+
+/// Leads the camera in the direction the player is moving
+struct LookAhead {
+    /// Seconds of player movement to look ahead
+    factor: f32,
+    /// How quickly [LookAhead::offset] eases towards its target
+    ease_speed: f32,
+    offset: Vec2,
 }
 
-struct Thrust;
+/// Flame of the ship it's attached to
+struct Thrust(pub EntityId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PowerUpKind {
+    Shield,
+    RapidFire,
+    SpreadShot,
+    Bomb,
+    Missiles,
+    /// Extra time on the clock, only spawned in time attack
+    Clock,
+    Piercing,
+}
+
+impl PowerUpKind {
+    /// The kinds spawned in every mode
+    const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::Shield,
+        PowerUpKind::RapidFire,
+        PowerUpKind::SpreadShot,
+        PowerUpKind::Bomb,
+        PowerUpKind::Missiles,
+        PowerUpKind::Piercing,
+    ];
+}
+
+struct PowerUp(pub PowerUpKind);
+
+struct PowerUpSpawner(pub Timer);
+
+/// Number of asteroid hits the player can absorb
+struct Shield(pub u32);
+
+/// Ring around the ship it's attached to
+struct ShieldRing(pub EntityId);
+
+/// Shortens the fire cooldown while the timer runs
+struct RapidFire(pub Timer);
+
+/// Fire three bullets in a fan while the timer runs
+struct SpreadShot(pub Timer);
+
+/// Bullets pass through the asteroids they destroy while the timer runs
+struct Piercing {
+    pub timer: Timer,
+    /// [Pierce] of the bullets fired meanwhile
+    pub count: u32,
+}
+
+/// Asteroids [Piercing] bullets pass through before they're spent
+struct PierceCount(pub u32);
+
+/// Asteroids the bullet can still pass through, 0 stops it at the next one
+struct Pierce(pub u32);
+
+/// Box of the asteroid the bullet last passed through
+///
+/// The pieces spawn inside it, the bullet leaves them alone until it's out of the box.
+struct PiercedThrough(pub AABB);
+
+/// Remaining smart bombs
+struct Bombs(pub u8);
+
+struct BombDigit;
+
+/// Number of bombs currently displayed on the HUD
+struct RenderedBombs(pub Option<u8>);
+
+/// Homing missiles left, fired with E
+struct Missiles(pub u8);
+
+struct MissileDigit;
+
+/// Number of missiles currently displayed on the HUD
+struct RenderedMissiles(pub Option<u8>);
+
+/// Projectile steering towards the nearest asteroid
+struct Missile;
+
+/// Weapon fired with Space, switched with Q
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Weapon {
+    #[default]
+    Blaster,
+    Laser,
+}
+
+/// Rate of fire of the laser, it heats up the same [WeaponHeat] as the blaster
+#[derive(Default)]
+struct Laser {
+    /// Time until the next shot
+    pub cooldown: Duration,
+}
+
+/// How quickly the guns of a ship overheat
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct WeaponHeatConfig {
+    /// Heat at which the guns overheat
+    pub max: f32,
+    /// Heat added by a volley of the blaster
+    pub blaster_shot: f32,
+    /// Heat added by a shot of the laser
+    pub laser_shot: f32,
+    /// Heat lost per second
+    pub cooling: f32,
+    /// Overheated guns fire again once cooled below this
+    pub resume: f32,
+}
+
+impl Default for WeaponHeatConfig {
+    fn default() -> Self {
+        Self {
+            max: 1.0,
+            blaster_shot: 0.1,
+            laser_shot: 0.12,
+            cooling: 0.4,
+            resume: 0.3,
+        }
+    }
+}
+
+/// Heat of the guns of a ship, every shot heats them up and they cool down over time
+struct WeaponHeat(pub f32);
+
+/// The guns of the ship reached [WeaponHeatConfig::max] and can't fire until they cool below
+/// [WeaponHeatConfig::resume]
+struct Overheated;
+
+struct LaserBeam;
+
+struct AsteroidCountDigit;
+
+/// Number of asteroids left on the map, shown on the HUD
+struct AsteroidCountHud {
+    /// Position of the lowest digit, relative to the camera
+    pub position: Vec3,
+    pub rendered_count: Option<usize>,
+}
+
+struct Explosion;
+
+/// Short lived visual effect, spawned at both ends of a hyperspace jump
+struct TeleportEffect;
 
 #[derive(Default)]
 struct Sprites {
@@ -76,641 +831,3889 @@ struct Sprites {
     pub thrust_n: u32,
     pub bullet_sheet: Handle<SpriteSheet>,
     pub bullet_n: u32,
+    pub missile_sheet: Handle<SpriteSheet>,
+    /// Solid square stretched into the laser beam
+    pub laser_sheet: Handle<SpriteSheet>,
+    pub ufo_sheet: Handle<SpriteSheet>,
+    pub enemy_bullet_sheet: Handle<SpriteSheet>,
+    /// Points up, shown at the edge of the view towards off-screen threats
+    pub arrow_sheet: Handle<SpriteSheet>,
+    pub weak_point_sheet: Handle<SpriteSheet>,
+    /// Warning sign, the second frame is faint
+    pub warning_sheet: Handle<SpriteSheet>,
+    pub tracer_sheet: Handle<SpriteSheet>,
     pub asteroid_sheet: Handle<SpriteSheet>,
-    pub asteroid_n: u32,
+    pub asteroid_icy_sheet: Handle<SpriteSheet>,
+    pub asteroid_metal_sheet: Handle<SpriteSheet>,
     pub game_over_sheet: Handle<SpriteSheet>,
+    pub gravity_well_sheet: Handle<SpriteSheet>,
+    pub explosion_sheet: Handle<SpriteSheet>,
+    pub explosion_n: u32,
+    pub powerup_sheet: Handle<SpriteSheet>,
+    pub shield_sheet: Handle<SpriteSheet>,
+    pub shield_n: u32,
     pub player: Handle<SpriteSheet>,
     pub digits: Handle<SpriteSheet>,
+    /// A to Z, then an underscore
+    pub letters: Handle<SpriteSheet>,
+    pub wave_banner: Handle<SpriteSheet>,
+    /// Black frames of increasing opacity
+    pub fade_sheet: Handle<SpriteSheet>,
+    pub fade_n: u32,
+    /// Box outline, the second frame marks colliding boxes
+    pub collider_outline: Handle<SpriteSheet>,
+    /// Solid square stretched into lines, the second frame is faint
+    pub line: Handle<SpriteSheet>,
+    /// Magenta and black checkerboard, drawn in place of single frame sheets that failed to load
+    pub placeholder: Handle<SpriteSheet>,
 }
 
 struct ScoreDigit;
 
-fn rotator(dt: Res<DeltaTime>, mut q: Query<&mut transform::Transform, With<Asteroid>>) {
-    let dt = dt.0.as_secs_f32();
-    q.par_for_each_mut(|tr| {
-        tr.rot = tr.rot.rotate_around_self(PrimaryAxis::Z, dt);
+/// Something the player should know about while it's out of view
+pub struct Threat;
+
+/// Arrow at the edge of the view pointing at the [Threat] entity
+struct OffscreenIndicator(pub EntityId);
+
+/// Spin around the Z axis, in radians per second
+struct AngularVelocity(pub f32);
+
+fn rotator(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut q: Query<(&mut transform::Transform, &AngularVelocity)>,
+) {
+    let dt = time_scale.scale(&dt).as_secs_f32();
+    q.par_for_each_mut(|(tr, w)| {
+        tr.rot = tr.rot.rotate_around_self(PrimaryAxis::Z, w.0 * dt);
     });
 }
 
-fn sprite_animator(dt: Res<DeltaTime>, mut q: Query<(&mut SpriteInstance, &mut UniformAnimation)>) {
-    let dt = dt.0;
-    q.par_for_each_mut(|(s, anim)| {
-        anim.timer.update(dt);
+/// Playback rate multiplier of a [UniformAnimation]
+struct AnimationSpeed(pub f32);
+
+fn sprite_animator(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut q: Query<(
+        &mut SpriteInstance,
+        &mut UniformAnimation,
+        Option<&AnimationSpeed>,
+    )>,
+) {
+    let dt = time_scale.scale(&dt);
+    q.par_for_each_mut(|(s, anim, speed)| {
+        anim.timer
+            .update(speed.map_or(dt, |speed| dt.mul_f32(speed.0.max(0.0))));
         if anim.timer.just_finished() {
             s.index = (s.index + 1) % anim.n;
         }
     });
 }
 
-fn update_lifetime(mut cmd: Commands, mut q: Query<(EntityId, &mut LifeTime)>, dt: Res<DeltaTime>) {
-    for (id, lt) in q.iter_mut() {
-        lt.0.update(dt.0);
+fn update_lifetime(
+    mut cmd: Commands,
+    mut q: Query<(EntityId, &mut LifeTime, Option<&Pooled>)>,
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+) {
+    for (id, lt, pooled) in q.iter_mut() {
+        lt.0.update(time_scale.scale(&dt));
         if lt.0.just_finished() {
-            cmd.delete(id);
+            if pooled.is_some() {
+                deactivate_bullet(cmd.entity(id));
+            } else {
+                cmd.delete(id);
+            }
         }
     }
 }
 
-fn split_asteroid(cmd: &mut Commands, v: &Velocity, tr: &Transform, assets: &Sprites, scale: f32) {
-    let vel_mag = v.0.length() * 1.05;
-    let v = v.0.normalize_or_zero();
-    let mut tr = tr.clone();
-    tr.scale *= scale;
-    {
-        let rot = fastrand::f32() * std::f32::consts::TAU;
-        let (s, c) = rot.sin_cos();
-        let v = Vec2::new(v.x * c + v.y * s, v.x * -s + v.y * c) * vel_mag;
-        spawn_asteroid(
-            cmd.spawn(),
-            tr.clone(),
-            assets.asteroid_sheet.clone(),
-            fastrand::u32(..assets.asteroid_n),
-            Velocity(v),
-        );
-    }
-    {
-        let rot = fastrand::f32() * std::f32::consts::TAU;
-        let (s, c) = rot.sin_cos();
-        let v = Vec2::new(v.x * c + v.y * s, v.x * -s + v.y * c) * vel_mag;
-        spawn_asteroid(
-            cmd.spawn(),
-            tr,
-            assets.asteroid_sheet.clone(),
-            fastrand::u32(..assets.asteroid_n),
-            Velocity(v),
-        );
+/// How destroyed asteroids break up
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct AsteroidConfig {
+    /// Number of pieces a destroyed asteroid breaks into
+    pub split_count: u32,
+    /// Scale of each piece relative to its parent
+    pub split_scale: f32,
+    /// Number of generations an initial asteroid can split into
+    pub max_splits: u32,
+    /// Speed range of newly spawned asteroids, before the difficulty multiplier
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Whether asteroids bounce off of each other, see [bounce_system]
+    pub collisions: bool,
+}
+
+impl Default for AsteroidConfig {
+    fn default() -> Self {
+        Self {
+            split_count: 2,
+            split_scale: 0.8,
+            max_splits: 3,
+            min_speed: 0.3,
+            max_speed: 1.2,
+            collisions: false,
+        }
     }
 }
 
-struct GameOver;
+impl AsteroidConfig {
+    /// Scale of the last generation
+    pub fn min_scale(&self) -> f32 {
+        self.split_scale.powi(self.max_splits as i32)
+    }
 
-fn game_over(sprites: &Sprites, cmd: &mut EntityCommands, mut pos: Vec3) {
-    // TODO: score
-    pos.z = -1.0;
-    let tr = Transform {
-        pos,
-        scale: Vec3::new(40.0, 20., 0.),
-        ..Default::default()
-    };
-    cmd.insert_bundle(transform_bundle(tr))
-        .insert_bundle(sprite_sheet_bundle(
-            sprites.game_over_sheet.clone(),
-            SpriteInstance {
-                index: 0,
-                flip: true,
-            },
-        ))
-        .insert_bundle((
-            GameOver,
-            GameEntity,
-            Cooldown(Timer::new(Duration::from_millis(500), false)),
-        ));
+    /// Whether an asteroid of `scale` splits again
+    ///
+    /// Compares against the midpoint between the last two generations, so rounding in the
+    /// repeated scaling can't add or drop a generation
+    pub fn can_split(&self, scale: f32) -> bool {
+        scale > self.min_scale() * (1.0 + 1.0 / self.split_scale) * 0.5
+    }
+
+    /// Speed the pieces of an asteroid moving at `speed` spread apart with
+    ///
+    /// Never below `min_speed`, so pieces of a still asteroid don't stay stacked
+    pub fn split_spread(&self, speed: f32) -> f32 {
+        (speed * SPLIT_SPREAD).max(self.min_speed)
+    }
 }
 
-fn handle_collisions(
-    collisions: Res<Collisions>,
-    mut cmd: Commands,
-    q_asteroid: Query<(&Velocity, &GlobalTransform)>,
-    q_camera_pos: Query<&GlobalTransform, With<Camera3d>>,
-    mut score: ResMut<Score>,
-    sprites: Res<Sprites>,
+/// What destroyed an asteroid
+struct Hit {
+    /// Where the explosion is placed
+    pub point: Vec2,
+    /// Momentum passed on to the pieces
+    pub momentum: Vec2,
+}
+
+/// Splits an asteroid into [AsteroidConfig::split_count] pieces moving at [split_velocities]
+fn split_asteroid(
+    cmd: &mut Commands,
+    rng: &mut fastrand::Rng,
+    v: &Velocity,
+    tr: &Transform,
+    assets: &Sprites,
+    config: &AsteroidConfig,
+    kind: AsteroidKind,
+    momentum: Vec2,
 ) {
-    for event in collisions.0.iter() {
-        let CollisionEvent {
-            mut entity_1,
-            mut tag1,
-            mut entity_2,
-            mut tag2,
-        } = *event;
-        const SPLIT_SCALE: f32 = 0.8;
-        // at most 3 splits
-        const MIN_SCALE: f32 = SPLIT_SCALE * SPLIT_SCALE * SPLIT_SCALE;
-        if tag1 == ASTEROID_TAG && tag2 == BULLET_TAG {
-            std::mem::swap(&mut entity_1, &mut entity_2);
-            std::mem::swap(&mut tag1, &mut tag2);
-        }
-        if tag2 == ASTEROID_TAG && tag1 == BULLET_TAG {
-            score.score += 1;
-            cmd.delete(entity_1);
-            cmd.delete(entity_2);
-            if let Some((v, tr)) = q_asteroid.fetch(entity_2) {
-                if tr.0.scale.x > MIN_SCALE {
-                    split_asteroid(&mut cmd, v, &tr.0, &sprites, SPLIT_SCALE);
-                }
-            }
-        }
-        if tag2 == ASTEROID_TAG && tag1 == PLAYER_TAG {
-            std::mem::swap(&mut entity_1, &mut entity_2);
-            std::mem::swap(&mut tag1, &mut tag2);
-        }
-        if tag1 == ASTEROID_TAG && tag2 == PLAYER_TAG {
-            cmd.entity(entity_2)
-                .remove::<Player>()
-                .remove::<CollisionTag>()
-                .remove::<Velocity>();
-            let pos = q_camera_pos.single().map(|tr| tr.0.pos).unwrap_or_default();
-            game_over(&sprites, cmd.spawn(), pos);
-        }
+    let velocities = split_velocities(rng, v.0, tr.scale.x, config, momentum);
+    let mut tr = tr.clone();
+    tr.scale *= config.split_scale;
+    for v in velocities {
+        spawn_asteroid(cmd.spawn(), rng, tr.clone(), assets, kind, Velocity(v));
     }
 }
 
-fn camera_controller(
-    dt: Res<DeltaTime>,
-    q_player: Query<&GlobalTransform, With<Player>>,
-    mut q_cam: Query<(&mut Transform, &PlayerCamera)>,
-) {
-    let Some(tr) = q_player.single() else {
-        return;
+/// Velocities of the pieces of an asteroid of `scale` moving at `v`, hit with `momentum`
+///
+/// The pieces share the parent's momentum plus the momentum of the hit, and spread out
+/// symmetrically so the spread itself adds no momentum
+fn split_velocities(
+    rng: &mut fastrand::Rng,
+    v: Vec2,
+    scale: f32,
+    config: &AsteroidConfig,
+    momentum: Vec2,
+) -> SmallVec<[Vec2; 4]> {
+    // mass by area, like in bounce_system
+    let parent_mass = scale * scale;
+    let child_mass = parent_mass * config.split_scale * config.split_scale;
+    let n = config.split_count;
+    let base = (v * parent_mass + momentum) / (child_mass * n.max(1) as f32);
+
+    // evenly spaced spread directions cancel out, starting perpendicular to the direction of travel
+    let spread = if n >= 2 {
+        config.split_spread(v.length())
+    } else {
+        0.0
     };
-    let player_pos = tr.0.pos;
+    let jitter = (rng.f32() - 0.5) * 0.5;
+    let phase = if base == Vec2::ZERO {
+        rng.f32() * std::f32::consts::TAU
+    } else {
+        base.perp().y.atan2(base.perp().x) + jitter
+    };
+
+    (0..n)
+        .map(|i| {
+            let angle = phase + i as f32 * std::f32::consts::TAU / n as f32;
+            let (s, c) = angle.sin_cos();
+            base + Vec2::new(c, s) * spread
+        })
+        .collect()
+}
 
-    for (tr, cam) in q_cam.iter_mut() {
-        let d = player_pos - tr.pos;
-        tr.pos += d * dt.0.as_secs_f32() * cam.follow_speed;
-        const PADDING_X: f32 = 20.0;
-        const PADDING_Y: f32 = 12.0;
-        tr.pos.x = tr
-            .pos
-            .x
-            .clamp(-MAP_RADIUS + PADDING_X, MAP_RADIUS - PADDING_X);
-        tr.pos.y = tr
-            .pos
-            .y
-            .clamp(-MAP_RADIUS + PADDING_Y, MAP_RADIUS - PADDING_Y);
+/// Scores, removes and splits an asteroid
+fn destroy_asteroid(
+    cmd: &mut Commands,
+    rng: &mut fastrand::Rng,
+    score: &mut Score,
+    sprites: &Sprites,
+    config: &AsteroidConfig,
+    id: EntityId,
+    kind: AsteroidKind,
+    v: &Velocity,
+    tr: &Transform,
+    hit: Hit,
+) {
+    score.score += kind.stats().score;
+    cmd.delete(id);
+    spawn_explosion(cmd.spawn(), sprites, hit.point.extend(tr.pos.z), tr.scale.x);
+    if config.can_split(tr.scale.x) {
+        split_asteroid(cmd, rng, v, tr, sprites, config, kind, hit.momentum);
     }
 }
 
-fn wraparound_system(mut q: Query<(&mut Transform, &GlobalTransform)>) {
-    q.par_for_each_mut(|(tr, g)| {
-        let g = &g.0;
-        if g.pos.x < -MAP_RADIUS {
-            tr.pos.x += 2.0 * MAP_RADIUS;
-        }
-        if g.pos.y < -MAP_RADIUS {
-            tr.pos.y += 2.0 * MAP_RADIUS;
-        }
-        if MAP_RADIUS < g.pos.x {
-            tr.pos.x -= 2.0 * MAP_RADIUS;
-        }
-        if MAP_RADIUS < g.pos.y {
-            tr.pos.y -= 2.0 * MAP_RADIUS;
-        }
-    });
+fn spawn_explosion(cmd: &mut EntityCommands, sprites: &Sprites, mut pos: Vec3, scale: f32) {
+    pos.z += layers::EXPLOSION;
+    cmd.insert_bundle(transform_bundle(Transform {
+        pos,
+        scale: Vec3::splat(scale * 1.5),
+        ..Default::default()
+    }))
+    .insert_bundle(sprite_sheet_bundle(sprites.explosion_sheet.clone(), None))
+    .insert_bundle((
+        Explosion,
+        GameEntity,
+        LifeTime(Timer::new(
+            Duration::from_millis(80) * sprites.explosion_n,
+            false,
+        )),
+        UniformAnimation {
+            timer: Timer::new(Duration::from_millis(80), true),
+            n: sprites.explosion_n,
+        },
+    ));
 }
 
-fn spawn_asteroid(
-    cmd: &mut EntityCommands,
-    transform: Transform,
-    sheet: Handle<SpriteSheet>,
-    index: u32,
-    vel: Velocity,
-) {
-    cmd.insert_bundle(sprite_renderer::sprite_sheet_bundle(
-        sheet,
-        SpriteInstance {
-            index,
-            flip: fastrand::bool(),
-        },
-    ))
-    .insert_bundle((Asteroid, vel, GameEntity))
-    .insert_bundle(aabb_bundle(
-        AABB::around_origin(Vec2::splat(0.8)),
-        ASTEROID_TAG,
-    ))
-    .insert_bundle(transform_bundle(transform));
+/// Size of the arena, objects leaving it wrap around to the opposite edge
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct MapConfig {
+    /// Half the side length of the square map
+    pub radius: f32,
+    pub broadphase: BroadphaseMode,
+    /// Whether a gravity well sits in the middle of the map, see [MapModifiers]
+    pub gravity_well: bool,
 }
 
-fn spawn_asteroids_system(
-    q_asteroid: Query<&(), With<Asteroid>>,
-    mut cmd: Commands,
-    assets: Res<Sprites>,
-    q_player: Query<&GlobalTransform, With<Player>>,
-) {
-    let count = q_asteroid.count();
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            radius: MAP_RADIUS,
+            broadphase: BroadphaseMode::default(),
+            gravity_well: false,
+        }
+    }
+}
 
-    let Some(player_pos) = q_player.single() else {
-        return;
-    };
+impl MapConfig {
+    pub fn random_point(&self, rng: &mut fastrand::Rng) -> Vec2 {
+        Vec2::new(
+            rng.f32() * 2.0 * self.radius - self.radius,
+            rng.f32() * 2.0 * self.radius - self.radius,
+        )
+    }
 
-    for _ in (count..TARGET).take(5) {
-        let mut pos = Vec3::ZERO;
-        loop {
-            pos.x = fastrand::f32() * 2.0 * MAP_RADIUS - MAP_RADIUS;
-            pos.y = fastrand::f32() * 2.0 * MAP_RADIUS - MAP_RADIUS;
-            if pos.distance(player_pos.0.pos) > 5.0 {
-                break;
+    /// Whether `pos` is on the map, edges included
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.abs().max_element() <= self.radius
+    }
+
+    /// Offset moving a point that left the map back in from the opposite edge, zero inside it
+    pub fn wrap_shift(&self, pos: Vec2) -> Vec2 {
+        let shift = |p: f32| {
+            if p < -self.radius {
+                2.0 * self.radius
+            } else if self.radius < p {
+                -2.0 * self.radius
+            } else {
+                0.0
             }
-        }
-        let rot = glam::Quat::from_axis_angle(Vec3::Z, fastrand::f32() * std::f32::consts::TAU);
+        };
+        Vec2::new(shift(pos.x), shift(pos.y))
+    }
+}
+
+/// Keeps the broadphase wrapping at the map edge, with the mode the map asks for, and pairing up
+/// asteroids only while they bounce
+fn sync_broadphase_system(
+    map: Res<MapConfig>,
+    asteroids: Res<AsteroidConfig>,
+    mut wrap: ResMut<WrapRadius>,
+    mut mode: ResMut<BroadphaseMode>,
+    mut self_colliding: ResMut<SelfColliding>,
+) {
+    wrap.0 = Some(map.radius);
+    *mode = map.broadphase;
+    self_colliding.0 = if asteroids.collisions {
+        ASTEROID_TAG.src
+    } else {
+        0
+    };
+}
 
-        let vx = fastrand::f32();
-        let vy = fastrand::f32();
-        let vrot = fastrand::f32();
-        let (vc, vs) = vrot.sin_cos();
+/// Optional hazards placed on the map at startup, taken from the [MapConfig] the game starts with
+struct MapModifiers {
+    pub gravity_well: bool,
+}
 
-        let vel = Vec2::new(vx * vc - vy * vs, vx * vc + vy * vs);
+/// Attracts every moving collider, by the inverse square of the distance
+struct GravityWell {
+    pub strength: f32,
+}
 
-        spawn_asteroid(
-            cmd.spawn(),
-            transform::Transform {
-                pos,
-                rot,
-                scale: Vec3::ONE,
-            },
-            assets.asteroid_sheet.clone(),
-            fastrand::u32(0..assets.asteroid_n),
-            Velocity(vel),
-        );
+fn setup_gravity_well(mut cmd: Commands, modifiers: Res<MapModifiers>, sprites: Res<Sprites>) {
+    if !modifiers.gravity_well {
+        return;
     }
+    cmd.spawn()
+        .insert_bundle(transform_bundle(Transform {
+            pos: Vec3::new(0.0, 0.0, layers::BACKGROUND),
+            scale: Vec3::splat(4.0),
+            ..Default::default()
+        }))
+        .insert_bundle(sprite_sheet_bundle(
+            sprites.gravity_well_sheet.clone(),
+            None,
+        ))
+        .insert(GravityWell { strength: 40.0 });
 }
 
-fn player_rotation_system(
+fn gravity_system(
     dt: Res<DeltaTime>,
-    inputs: Res<KeyBoardInputs>,
-    mut q: Query<(&mut transform::Transform, &mut RotationTime), With<Player>>,
+    time_scale: Res<TimeScale>,
+    q_wells: Query<(&GlobalTransform, &GravityWell)>,
+    mut q: Query<(&mut Velocity, &GlobalTransform), With<CollisionTag>>,
 ) {
-    for (tr, rot_time) in q.iter_mut() {
-        let mut rot = 0.0;
-        for k in inputs.pressed.iter() {
-            match k {
-                VirtualKeyCode::D => rot += 1.0,
-                VirtualKeyCode::A => rot -= 1.0,
-                _ => continue,
-            }
+    if q_wells.is_empty() {
+        return;
+    }
+    let dt = time_scale.scale(&dt).as_secs_f32();
+    let wells: SmallVec<[(Vec2, f32); 4]> = q_wells
+        .iter()
+        .map(|(tr, well)| (tr.0.pos.truncate(), well.strength))
+        .collect();
+    q.par_for_each_mut(|(vel, tr)| {
+        let pos = tr.0.pos.truncate();
+        for (well_pos, strength) in wells.iter() {
+            let d = *well_pos - pos;
+            // clamp the distance so objects passing through the center aren't flung away
+            let dist = d.length().max(GRAVITY_MIN_DISTANCE);
+            let acc = d.normalize_or_zero() * *strength / (dist * dist);
+            vel.0 += acc * dt;
         }
+    });
+}
 
-        if rot != 0.0 {
-            // ramp up rotation speed
-            rot_time.0 = (rot_time.0 + dt.0).max(Duration::from_millis(300));
-            let rotation_velocity = rot_time.0.as_secs_f32() * 3.0;
-            let rotation = rot * dt.0.as_secs_f32() * rotation_velocity;
-            tr.rot = tr.rot.rotate_around_self(PrimaryAxis::Z, rotation);
-        } else {
-            rot_time.0 = Default::default();
+/// Elastic bounce between colliding asteroids, using their area as mass
+fn bounce_system(
+    config: Res<AsteroidConfig>,
+    collisions: Res<Collisions>,
+    mut q: Query<(&mut Velocity, &GlobalTransform), With<Asteroid>>,
+) {
+    if !config.collisions {
+        return;
+    }
+    for event in collisions.iter() {
+        if event.tag1 != ASTEROID_TAG || event.tag2 != ASTEROID_TAG {
+            continue;
+        }
+        let (Some((v1, tr1)), Some((v2, tr2))) = (q.fetch(event.entity_1), q.fetch(event.entity_2))
+        else {
+            continue;
+        };
+        let (v1, v2) = (v1.0, v2.0);
+        let m1 = tr1.0.scale.x * tr1.0.scale.x;
+        let m2 = tr2.0.scale.x * tr2.0.scale.x;
+        // the contact normal also holds for pairs touching across the wraparound seam
+        let n = event.normal;
+        let vn = (v1 - v2).dot(n);
+        // already separating
+        if vn >= 0.0 {
+            continue;
+        }
+        let j = 2.0 * vn / (m1 + m2);
+        if let Some((v, _)) = q.fetch_mut(event.entity_1) {
+            v.0 = v1 - n * j * m2;
+        }
+        if let Some((v, _)) = q.fetch_mut(event.entity_2) {
+            v.0 = v2 + n * j * m1;
         }
     }
 }
 
-fn player_thrust_system(
-    dt: Res<DeltaTime>,
-    inputs: Res<KeyBoardInputs>,
-    mut q: Query<(EntityId, &transform::Transform, &mut Velocity, &mut Player)>,
+struct GameOver;
+
+fn game_over(sprites: &Sprites, cmd: &mut Commands, mut pos: Vec3, score: u64) {
+    pos.z = layers::BANNER;
+    let tr = Transform {
+        pos,
+        scale: Vec3::new(40.0, 20., 0.),
+        ..Default::default()
+    };
+
+    // final score, centered beneath the graphic
+    const SCORE_SCALE: f32 = 2.0;
+    let width = score.to_string().len() as f32 * SCORE_SCALE;
+    let mut origin = pos;
+    origin.x -= (width - SCORE_SCALE) * 0.5;
+    origin.y -= 12.0;
+    spawn_digits(cmd, None, sprites, score, origin, SCORE_SCALE, |cmd| {
+        cmd.insert(GameEntity);
+    });
+
+    cmd.spawn()
+        .insert_bundle(transform_bundle(tr))
+        .insert_bundle(sprite_sheet_bundle(
+            sprites.game_over_sheet.clone(),
+            SpriteInstance {
+                index: 0,
+                flip: true,
+            },
+        ))
+        .insert_bundle((
+            GameOver,
+            GameEntity,
+            Cooldown::new(Duration::from_millis(500)),
+        ));
+}
+
+/// Outcome of a bullet hitting an asteroid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BulletHit {
+    /// The bullet stops, otherwise it pierces on
+    spent: bool,
+    /// The asteroid breaks, otherwise its armour held
+    destroyed: bool,
+}
+
+/// Entities already done with this frame, shared by every system that damages asteroids
+///
+/// Deletes only land once the commands are applied, until then a destroyed asteroid or a spent
+/// projectile can still turn up in another hit. Emptied at the start of every frame.
+#[derive(Default)]
+struct Consumed(pub HashSet<EntityId>);
+
+fn clear_consumed_system(mut consumed: ResMut<Consumed>) {
+    consumed.0.clear();
+}
+
+/// Damages the asteroid and spends the bullet, or one of its pierces
+///
+/// `consumed` holds the entities already done with this frame, see [Consumed]. Hits involving
+/// one of them are skipped, returning `None`, so an asteroid hit twice is only scored and split
+/// once and a spent bullet stops at the first asteroid.
+fn bullet_hit(
+    consumed: &mut HashSet<EntityId>,
+    bullet: EntityId,
+    asteroid: EntityId,
+    pierce: Option<&mut Pierce>,
+    health: Option<&mut Health>,
+) -> Option<BulletHit> {
+    if consumed.contains(&bullet) || consumed.contains(&asteroid) {
+        return None;
+    }
+    let survived = match health {
+        Some(health) => {
+            health.0 = health.0.saturating_sub(1);
+            health.0 > 0
+        }
+        None => false,
+    };
+    let spent = match pierce {
+        // armour stops piercing bullets too
+        Some(pierce) if pierce.0 > 0 && !survived => {
+            pierce.0 -= 1;
+            false
+        }
+        _ => true,
+    };
+    if spent {
+        consumed.insert(bullet);
+    }
+    if !survived {
+        consumed.insert(asteroid);
+    }
+    Some(BulletHit {
+        spent,
+        destroyed: !survived,
+    })
+}
+
+fn handle_collisions(
+    collisions: Res<Collisions>,
     mut cmd: Commands,
+    // asteroids, and the bullets hitting them
+    q_asteroid: Query<(&Velocity, &GlobalTransform)>,
+    q_pooled: Query<&(), With<Pooled>>,
+    q_kind: Query<&AsteroidKind>,
+    mut q_health: Query<&mut Health>,
+    mut q_shield: Query<&mut Shield>,
+    mut q_pierce: Query<(&mut Pierce, Option<&PiercedThrough>)>,
+    q_bounds: Query<&GlobalAABB>,
+    mut score: ResMut<Score>,
     sprites: Res<Sprites>,
-    thrusters: Query<EntityId, With<Thrust>>,
+    asteroid_config: Res<AsteroidConfig>,
+    mut rng: ResMut<Rng>,
+    mut consumed: ResMut<Consumed>,
 ) {
-    let dt = dt.0.as_secs_f32();
-    if let Some((id, tr, vel, player)) = q.single_mut() {
-        for key in inputs.just_released.iter() {
-            if let VirtualKeyCode::W = key {
-                player.acceleration = 0.0;
-                player.velocity = vel.0.length();
-                for id in thrusters.iter() {
-                    cmd.delete(id);
+    // more than one asteroid can reach a ship in a frame, it only goes down once
+    let mut downed: SmallVec<[EntityId; 2]> = SmallVec::new();
+    // likewise for asteroids and bullets, see [bullet_hit]
+    let consumed = &mut consumed.0;
+    for event in collisions.iter() {
+        let CollisionEvent {
+            mut entity_1,
+            mut tag1,
+            mut entity_2,
+            mut tag2,
+            contact_point,
+            ..
+        } = *event;
+        if tag1 == ASTEROID_TAG && tag2 == BULLET_TAG {
+            std::mem::swap(&mut entity_1, &mut entity_2);
+            std::mem::swap(&mut tag1, &mut tag2);
+        }
+        if tag2 == ASTEROID_TAG && tag1 == BULLET_TAG {
+            let pierce = q_pierce.fetch_mut(entity_1);
+            if let Some((_, Some(through))) = &pierce {
+                if through.0.contains(contact_point) {
+                    // a piece of the asteroid it just passed through
+                    continue;
                 }
             }
-        }
-        if !inputs.pressed.iter().any(|k| match k {
-            VirtualKeyCode::W => {
-                if thrusters.is_empty() {
-                    transform::spawn_child(id, &mut cmd, |cmd| {
-                        cmd.insert_bundle(transform_bundle(Transform::from_position(Vec3::new(
-                            0.0, -0.5, 0.1,
-                        ))))
-                        .insert_bundle((
-                            Thrust,
-                            GameEntity,
-                            UniformAnimation {
-                                timer: Timer::new(Duration::from_millis(100), true),
-                                n: sprites.thrust_n,
-                            },
-                        ))
-                        .insert_bundle(
-                            sprite_renderer::sprite_sheet_bundle(
-                                sprites.thrust_sheet.clone(),
-                                None,
-                            ),
-                        );
-                    });
+            let bullet_vel = q_asteroid
+                .fetch(entity_1)
+                .map(|(v, _)| v.0)
+                .unwrap_or_default();
+            let Some(hit) = bullet_hit(
+                consumed,
+                entity_1,
+                entity_2,
+                pierce.map(|(pierce, _)| pierce),
+                q_health.fetch_mut(entity_2),
+            ) else {
+                continue;
+            };
+            if hit.spent {
+                if q_pooled.fetch(entity_1).is_some() {
+                    deactivate_bullet(cmd.entity(entity_1));
+                } else {
+                    cmd.delete(entity_1);
                 }
-                // max acceleration in 0.3 seconds
-                player.acceleration = (player.acceleration + dt * MAX_ACC * 3.0).min(MAX_ACC);
-                player.velocity = (player.velocity + player.acceleration * dt).min(MAX_VEL);
-                vel.0 = vel
-                    .0
-                    .lerp((tr.rot * Vec3::Y).truncate() * player.velocity, dt);
-                true
-            }
-            _ => false,
-        }) {
-            player.velocity = (vel.0.length() - dt * INERTIA).max(0.0);
-            vel.0 = vel.0.normalize_or_zero() * player.velocity;
+            } else if let Some(bounds) = q_bounds.fetch(entity_2) {
+                cmd.entity(entity_1).insert(PiercedThrough(bounds.0));
+            }
+            if !hit.destroyed {
+                continue;
+            }
+            let kind = q_kind
+                .fetch(entity_2)
+                .copied()
+                .unwrap_or(AsteroidKind::Rocky);
+            if let Some((v, tr)) = q_asteroid.fetch(entity_2) {
+                destroy_asteroid(
+                    &mut cmd,
+                    &mut rng.0,
+                    &mut score,
+                    &sprites,
+                    &asteroid_config,
+                    entity_2,
+                    kind,
+                    v,
+                    &tr.0,
+                    Hit {
+                        point: contact_point,
+                        momentum: bullet_vel * BULLET_MASS,
+                    },
+                );
+            } else {
+                cmd.delete(entity_2);
+            }
+        }
+        if tag2 == ASTEROID_TAG && tag1 == PLAYER_TAG {
+            std::mem::swap(&mut entity_1, &mut entity_2);
+            std::mem::swap(&mut tag1, &mut tag2);
+        }
+        if tag1 == ASTEROID_TAG
+            && tag2 == PLAYER_TAG
+            && !downed.contains(&entity_2)
+            && !consumed.contains(&entity_1)
+        {
+            if !hit_player(&mut cmd, entity_2, &mut q_shield) {
+                downed.push(entity_2);
+                continue;
+            }
+            // shields smash through armour regardless of health
+            consumed.insert(entity_1);
+            let kind = q_kind
+                .fetch(entity_1)
+                .copied()
+                .unwrap_or(AsteroidKind::Rocky);
+            if let Some((v, tr)) = q_asteroid.fetch(entity_1) {
+                destroy_asteroid(
+                    &mut cmd,
+                    &mut rng.0,
+                    &mut score,
+                    &sprites,
+                    &asteroid_config,
+                    entity_1,
+                    kind,
+                    v,
+                    &tr.0,
+                    Hit {
+                        point: contact_point,
+                        momentum: Vec2::ZERO,
+                    },
+                );
+            } else {
+                cmd.delete(entity_1);
+            }
         }
     }
 }
 
-fn move_system(dt: Res<DeltaTime>, mut q: Query<(&mut Transform, &Velocity)>) {
-    let dt = dt.0.as_secs_f32();
-    q.par_for_each_mut(|(tr, v)| {
-        tr.pos += v.0.extend(0.0) * dt;
-    });
+/// A charge of the shield soaks up the hit, without one the ship is wrecked
+///
+/// Returns whether the shield took the hit.
+fn hit_player(cmd: &mut Commands, player: EntityId, q_shield: &mut Query<&mut Shield>) -> bool {
+    if let Some(shield) = q_shield.fetch_mut(player) {
+        if shield.0 > 0 {
+            shield.0 -= 1;
+            if shield.0 == 0 {
+                cmd.entity(player).remove::<Shield>();
+            }
+            return true;
+        }
+    }
+    wreck_ship(cmd, player);
+    false
 }
 
-struct Cooldown(pub Timer);
+/// Takes the ship out of the game, [game_over_system] ends it once no ship is left
+fn wreck_ship(cmd: &mut Commands, player: EntityId) {
+    // the wreck keeps its momentum and slowly comes to rest
+    cmd.entity(player)
+        .remove::<Player>()
+        .remove::<CollisionTag>()
+        .insert(DeadDrift);
+}
 
-fn cooldown_system(
+/// Brings up the game over screen once every ship is wrecked
+fn game_over_system(
     mut cmd: Commands,
-    dt: Res<DeltaTime>,
-    mut cd: Query<(EntityId, &mut Cooldown)>,
+    q_player: Query<&(), With<Player>>,
+    q_game_over: Query<&(), With<GameOver>>,
+    q_camera_pos: Query<&GlobalTransform, With<Camera3d>>,
+    sprites: Res<Sprites>,
+    score: Res<Score>,
+    mut fade: ResMut<ScreenFade>,
+    mut time_scale: ResMut<TimeScale>,
 ) {
-    for (id, cd) in cd.iter_mut() {
-        cd.0.update(dt.0);
-        if cd.0.just_finished() {
-            cmd.entity(id).remove::<Cooldown>();
-        }
+    if !q_player.is_empty() || !q_game_over.is_empty() {
+        return;
     }
+    let pos = q_camera_pos.single().map(|tr| tr.0.pos).unwrap_or_default();
+    game_over(&sprites, &mut cmd, pos, score.score.0);
+    fade.darken(DEATH_FADE_ALPHA);
+    time_scale.0 = DEATH_TIME_SCALE;
 }
 
-#[allow(unused)]
-struct FireSound;
+/// Position of the ship closest to `pos`
+fn nearest_ship(q_player: &Query<&GlobalTransform, With<Player>>, pos: Vec2) -> Option<Vec2> {
+    nearest(q_player.iter().map(|tr| tr.0.pos.truncate()), pos)
+}
 
-#[cfg(not(target_family = "wasm"))]
-fn setup_slash(mut cmd: Commands, mut assets: ResMut<assets::Assets<brengin::audio::Audio>>) {
-    let bytes = include_bytes!("../assets/slash.mp3");
-    let music = brengin::audio::Audio::load_audio_bytes(bytes, &mut assets).unwrap();
-    cmd.spawn().insert_bundle((FireSound, music));
+/// The point closest to `pos`, the first one on a tie
+fn nearest(points: impl Iterator<Item = Vec2>, pos: Vec2) -> Option<Vec2> {
+    points.min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
 }
 
-#[cfg(target_family = "wasm")]
-fn setup_slash() {}
+fn pickup_powerup_system(
+    collisions: Res<Collisions>,
+    mut cmd: Commands,
+    q_powerup: Query<&PowerUp>,
+    mut q_shield: Query<&mut Shield>,
+    mut bombs: ResMut<Bombs>,
+    mut missiles: ResMut<Missiles>,
+    mut q_clock: Query<&mut time_attack::TimeAttack>,
+    time_attack: Res<time_attack::TimeAttackConfig>,
+    pierce_count: Res<PierceCount>,
+) {
+    // the deletes only land after the system, in co-op both ships can touch a power-up in the
+    // same frame and only the first one gets it
+    let mut picked_up = HashSet::new();
+    for event in collisions.iter() {
+        let CollisionEvent {
+            mut entity_1,
+            mut tag1,
+            mut entity_2,
+            mut tag2,
+            ..
+        } = *event;
+        if tag2 == POWERUP_TAG && tag1 == PLAYER_TAG {
+            std::mem::swap(&mut entity_1, &mut entity_2);
+            std::mem::swap(&mut tag1, &mut tag2);
+        }
+        if tag1 == POWERUP_TAG && tag2 == PLAYER_TAG {
+            if !picked_up.insert(entity_1) {
+                continue;
+            }
+            cmd.delete(entity_1);
+            match q_powerup.fetch(entity_1).map(|p| p.0) {
+                Some(PowerUpKind::Shield) => {
+                    if let Some(shield) = q_shield.fetch_mut(entity_2) {
+                        shield.0 = (shield.0 + 1).min(MAX_SHIELD);
+                    } else {
+                        cmd.entity(entity_2).insert(Shield(1));
+                    }
+                }
+                Some(PowerUpKind::RapidFire) => {
+                    cmd.entity(entity_2)
+                        .insert(RapidFire(Timer::new(WEAPON_UPGRADE_DURATION, false)));
+                }
+                Some(PowerUpKind::SpreadShot) => {
+                    cmd.entity(entity_2)
+                        .insert(SpreadShot(Timer::new(WEAPON_UPGRADE_DURATION, false)));
+                }
+                Some(PowerUpKind::Bomb) => {
+                    bombs.0 = (bombs.0 + 1).min(MAX_BOMBS);
+                }
+                Some(PowerUpKind::Missiles) => {
+                    missiles.0 = (missiles.0 + MISSILE_AMMO).min(MAX_MISSILES);
+                }
+                Some(PowerUpKind::Clock) => {
+                    // the clock is shared, whichever ship picks it up
+                    for clock in q_clock.iter_mut() {
+                        clock.remaining += time_attack.clock_bonus;
+                    }
+                }
+                Some(PowerUpKind::Piercing) => {
+                    cmd.entity(entity_2).insert(Piercing {
+                        timer: Timer::new(WEAPON_UPGRADE_DURATION, false),
+                        count: pierce_count.0,
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+}
 
-fn fire_system(
+fn bomb_system(
     inputs: Res<KeyBoardInputs>,
+    mut bombs: ResMut<Bombs>,
+    mut score: ResMut<Score>,
     sprites: Res<Sprites>,
     mut cmd: Commands,
-    q_player: Query<(&GlobalTransform, &Player)>,
-    q_cd: Query<&(), (With<Cooldown>, With<Bullet>)>,
-
-    #[cfg(not(target_family = "wasm"))] audio: Res<assets::Assets<brengin::audio::Audio>>,
-    #[cfg(not(target_family = "wasm"))] am: Res<brengin::audio::AudioManager>,
-    #[cfg(not(target_family = "wasm"))] slash: Query<
-        &assets::Handle<brengin::audio::Audio>,
-        With<FireSound>,
-    >,
+    q_player: Query<&GlobalTransform, (With<Player>, WithOut<SecondPlayer>)>,
+    q_asteroids: Query<(EntityId, &AsteroidKind, &Velocity, &GlobalTransform), With<Asteroid>>,
+    asteroid_config: Res<AsteroidConfig>,
+    mut rng: ResMut<Rng>,
+    mut consumed: ResMut<Consumed>,
 ) {
-    if q_cd.single().is_some() {
+    if bombs.0 == 0 || !inputs.just_released.contains(&VirtualKeyCode::B) {
         return;
     }
-
-    for key in inputs.pressed.iter() {
-        if let VirtualKeyCode::Space = key {
-            if let Some((tr, player)) = q_player.single() {
-                #[cfg(not(target_family = "wasm"))]
-                if let Some(s) = slash.single() {
-                    let music = audio.get(s);
-                    am.play(music);
-                }
-                let rot = tr.0.rot;
-                let v = tr.0.rot * Vec3::Y;
-                let vel = v * (1.0 + player.velocity).min(MAX_VEL + 1.0);
-                let pos = tr.0.pos + v * 0.5;
-
-                cmd.spawn()
-                    .insert_bundle(sprite_renderer::sprite_sheet_bundle(
-                        sprites.bullet_sheet.clone(),
-                        None,
-                    ))
-                    .insert_bundle((
-                        LifeTime(Timer::new(Duration::from_secs(5), false)),
-                        Bullet,
-                        GameEntity,
-                        UniformAnimation {
-                            timer: Timer::new(Duration::from_millis(100), true),
-                            n: sprites.bullet_n,
-                        },
-                        Velocity(vel.truncate()),
-                        Cooldown(Timer::new(Duration::from_millis(200), false)),
-                    ))
-                    .insert_bundle(aabb_bundle(
-                        AABB::around_origin(Vec2::new(0.25, 0.5)),
-                        BULLET_TAG,
-                    ))
-                    .insert_bundle(transform::transform_bundle(transform::Transform {
-                        pos,
-                        rot,
-                        scale: Vec3::splat(0.2),
-                    }));
-            }
+    let Some(player_tr) = q_player.single() else {
+        return;
+    };
+    bombs.0 -= 1;
+    let center = player_tr.0.pos.truncate();
+    for (id, kind, v, tr) in q_asteroids.iter() {
+        if tr.0.pos.truncate().distance(center) <= BOMB_RADIUS && consumed.0.insert(id) {
+            destroy_asteroid(
+                &mut cmd,
+                &mut rng.0,
+                &mut score,
+                &sprites,
+                &asteroid_config,
+                id,
+                *kind,
+                v,
+                &tr.0,
+                Hit {
+                    point: tr.0.pos.truncate(),
+                    momentum: Vec2::ZERO,
+                },
+            );
         }
     }
 }
 
-fn setup_player(mut cmd: Commands, assets: Res<Sprites>) {
-    // player
-    spawn_player(cmd.spawn(), assets.player.clone());
-
-    // camera
+fn fire_missile_system(
+    inputs: Res<KeyBoardInputs>,
+    mut missiles: ResMut<Missiles>,
+    sprites: Res<Sprites>,
+    mut cmd: Commands,
+    q_player: Query<(&GlobalTransform, &AABB), (With<Player>, WithOut<SecondPlayer>)>,
+) {
+    if missiles.0 == 0 || !inputs.just_released.contains(&VirtualKeyCode::E) {
+        return;
+    }
+    let Some((tr, collider)) = q_player.single() else {
+        return;
+    };
+    missiles.0 -= 1;
+    let dir = (tr.0.rot * Vec3::Y).truncate();
     cmd.spawn()
-        .insert(PlayerCamera { follow_speed: 5.0 })
-        .insert_bundle(renderer::camera_bundle(Camera3d {
-            eye: Vec3::new(0.0, 0.0, 20.0),
-            target: Vec3::ZERO,
-            up: Vec3::Y,
-            aspect: 16.0 / 9.0,
-            fovy: 45.0,
-            znear: 5.0,
-            zfar: 50.0,
+        .insert_bundle(transform_bundle(Transform {
+            pos: muzzle_position(tr, collider, dir),
+            rot: tr.0.rot,
+            scale: Vec3::splat(0.6),
         }))
-        .insert_bundle(transform_bundle(transform::Transform::default()));
+        .insert_bundle(sprite_sheet_bundle(sprites.missile_sheet.clone(), None))
+        .insert_bundle(aabb_bundle(
+            AABB::around_origin(Vec2::new(0.3, 0.6)),
+            MISSILE_TAG,
+            ColliderKind::Trigger,
+        ))
+        .insert_bundle((
+            Missile,
+            GameEntity,
+            Velocity(dir * MISSILE_SPEED),
+            LifeTime(Timer::new(MISSILE_LIFETIME, false)),
+        ));
 }
 
-fn spawn_player(cmd: &mut EntityCommands, sprite_handle: Handle<SpriteSheet>) {
-    cmd.insert_bundle(transform::transform_bundle(
-        transform::Transform::from_scale(Vec3::splat(0.5)),
-    ))
-    .insert_bundle(sprite_renderer::sprite_sheet_bundle(sprite_handle, None))
+/// Turns each missile towards the closest asteroid, by at most [MISSILE_TURN_RATE]
+///
+/// Missiles fly straight on while there is nothing to chase.
+fn missile_guidance_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut q_missiles: Query<(&mut Transform, &mut Velocity), With<Missile>>,
+    q_asteroids: Query<&GlobalTransform, With<Asteroid>>,
+) {
+    let max_turn = MISSILE_TURN_RATE * time_scale.scale(&dt).as_secs_f32();
+    for (tr, vel) in q_missiles.iter_mut() {
+        let pos = tr.pos.truncate();
+        let Some(target) = q_asteroids
+            .iter()
+            .map(|a| a.0.pos.truncate())
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+        else {
+            continue;
+        };
+        let heading = vel.0.y.atan2(vel.0.x);
+        let to_target = target - pos;
+        let wanted = to_target.y.atan2(to_target.x);
+        // shortest way round, in -PI..PI
+        let diff = (wanted - heading + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        let heading = heading + diff.clamp(-max_turn, max_turn);
+        let (s, c) = heading.sin_cos();
+        vel.0 = Vec2::new(c, s) * vel.0.length();
+        tr.rot = glam::Quat::from_rotation_z(heading - std::f32::consts::FRAC_PI_2);
+    }
+}
+
+/// Damages the asteroid and spends the missile, returns whether the asteroid broke
+///
+/// Like [bullet_hit], hits involving an entity in `consumed` are skipped and return `None`, so a
+/// missile touching two asteroids at once only takes out one of them.
+fn missile_hit(
+    consumed: &mut HashSet<EntityId>,
+    missile: EntityId,
+    asteroid: EntityId,
+    health: Option<&mut Health>,
+) -> Option<bool> {
+    if consumed.contains(&missile) || consumed.contains(&asteroid) {
+        return None;
+    }
+    consumed.insert(missile);
+    let destroyed = match health {
+        Some(health) => {
+            health.0 = health.0.saturating_sub(MISSILE_DAMAGE);
+            health.0 == 0
+        }
+        None => true,
+    };
+    if destroyed {
+        consumed.insert(asteroid);
+    }
+    Some(destroyed)
+}
+
+fn missile_hit_system(
+    collisions: Res<Collisions>,
+    mut cmd: Commands,
+    q_missile: Query<&Velocity, With<Missile>>,
+    q_asteroid: Query<(&Velocity, &GlobalTransform, &AsteroidKind)>,
+    mut q_health: Query<&mut Health>,
+    mut score: ResMut<Score>,
+    sprites: Res<Sprites>,
+    asteroid_config: Res<AsteroidConfig>,
+    mut rng: ResMut<Rng>,
+    mut consumed: ResMut<Consumed>,
+) {
+    for event in collisions.iter() {
+        let CollisionEvent {
+            mut entity_1,
+            mut tag1,
+            mut entity_2,
+            mut tag2,
+            contact_point,
+            ..
+        } = *event;
+        if tag1 == ASTEROID_TAG && tag2 == MISSILE_TAG {
+            std::mem::swap(&mut entity_1, &mut entity_2);
+            std::mem::swap(&mut tag1, &mut tag2);
+        }
+        if tag1 != MISSILE_TAG || tag2 != ASTEROID_TAG {
+            continue;
+        }
+        let Some(destroyed) = missile_hit(
+            &mut consumed.0,
+            entity_1,
+            entity_2,
+            q_health.fetch_mut(entity_2),
+        ) else {
+            continue;
+        };
+        let missile_vel = q_missile.fetch(entity_1).map(|v| v.0).unwrap_or_default();
+        cmd.delete(entity_1);
+        if !destroyed {
+            continue;
+        }
+        if let Some((v, tr, kind)) = q_asteroid.fetch(entity_2) {
+            destroy_asteroid(
+                &mut cmd,
+                &mut rng.0,
+                &mut score,
+                &sprites,
+                &asteroid_config,
+                entity_2,
+                *kind,
+                v,
+                &tr.0,
+                Hit {
+                    point: contact_point,
+                    momentum: missile_vel * MISSILE_MASS,
+                },
+            );
+        } else {
+            cmd.delete(entity_2);
+        }
+    }
+}
+
+/// Hitscan weapon, each shot damages the first asteroid along the ship's heading
+fn laser_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    inputs: Res<KeyBoardInputs>,
+    mut weapon: ResMut<Weapon>,
+    mut laser: ResMut<Laser>,
+    heat_config: Res<WeaponHeatConfig>,
+    colliders: Res<AABBBuffer>,
+    mut cmd: Commands,
+    mut q_player: Query<
+        (
+            EntityId,
+            &GlobalTransform,
+            &AABB,
+            &mut WeaponHeat,
+            Option<&Overheated>,
+        ),
+        (With<Player>, WithOut<SecondPlayer>),
+    >,
+    q_asteroid: Query<(&Velocity, &GlobalTransform, &AsteroidKind)>,
+    mut q_health: Query<&mut Health>,
+    mut score: ResMut<Score>,
+    sprites: Res<Sprites>,
+    asteroid_config: Res<AsteroidConfig>,
+    mut rng: ResMut<Rng>,
+    mut consumed: ResMut<Consumed>,
+) {
+    if inputs.just_released.contains(&VirtualKeyCode::Q) {
+        *weapon = match *weapon {
+            Weapon::Blaster => Weapon::Laser,
+            Weapon::Laser => Weapon::Blaster,
+        };
+    }
+    let dt = time_scale.scale(&dt);
+    laser.cooldown = laser.cooldown.saturating_sub(dt);
+    if *weapon != Weapon::Laser
+        || !laser.cooldown.is_zero()
+        || !inputs.pressed.contains(&VirtualKeyCode::Space)
+    {
+        return;
+    }
+    let Some((ship, tr, collider, heat, overheated)) = q_player.iter_mut().next() else {
+        return;
+    };
+    if overheated.is_some() {
+        return;
+    }
+    laser.cooldown = LASER_COOLDOWN;
+    heat.0 += heat_config.laser_shot;
+    if heat.0 >= heat_config.max {
+        cmd.entity(ship).insert(Overheated);
+    }
+
+    let dir = (tr.0.rot * Vec3::Y).truncate();
+    let origin = muzzle_position(tr, collider, dir).truncate();
+    let hit = raycast(&colliders, origin, dir, ASTEROID_TAG.src).filter(|(_, t)| *t <= LASER_RANGE);
+    let length = hit.map_or(LASER_RANGE, |(_, t)| t);
+    let end = origin + dir * length;
+
+    cmd.spawn()
+        .insert_bundle(transform_bundle(Transform {
+            pos: ((origin + end) * 0.5).extend(tr.0.pos.z),
+            rot: tr.0.rot,
+            scale: Vec3::new(LASER_BEAM_WIDTH, length, 1.0),
+        }))
+        .insert_bundle(sprite_sheet_bundle(sprites.laser_sheet.clone(), None))
+        .insert_bundle((
+            LaserBeam,
+            GameEntity,
+            LifeTime(Timer::new(LASER_BEAM_TIME, false)),
+        ));
+
+    let Some((target, _)) = hit else {
+        return;
+    };
+    if consumed.0.contains(&target) {
+        // already destroyed by something else this frame
+        return;
+    }
+    if let Some(health) = q_health.fetch_mut(target) {
+        health.0 = health.0.saturating_sub(1);
+        if health.0 > 0 {
+            return;
+        }
+    }
+    consumed.0.insert(target);
+    if let Some((v, tr, kind)) = q_asteroid.fetch(target) {
+        destroy_asteroid(
+            &mut cmd,
+            &mut rng.0,
+            &mut score,
+            &sprites,
+            &asteroid_config,
+            target,
+            *kind,
+            v,
+            &tr.0,
+            Hit {
+                point: end,
+                momentum: Vec2::ZERO,
+            },
+        );
+    }
+}
+
+fn spawn_powerups_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut spawner: ResMut<PowerUpSpawner>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    mut rng: ResMut<Rng>,
+    map: Res<MapConfig>,
+) {
+    spawner.0.update(time_scale.scale(&dt));
+    if !spawner.0.just_finished() {
+        return;
+    }
+    if q_player.is_empty() {
+        return;
+    }
+
+    // a small map can be covered by the ships, skip the power-up instead of searching forever
+    let pos = (0..MAX_SPAWN_ATTEMPTS)
+        .map(|_| map.random_point(&mut rng.0).extend(layers::POWERUP))
+        .find(|pos| q_player.iter().all(|tr| pos.distance(tr.0.pos) > 5.0));
+    let Some(pos) = pos else {
+        return;
+    };
+    let kind = PowerUpKind::ALL[rng.0.usize(..PowerUpKind::ALL.len())];
+    spawn_powerup(cmd.spawn(), &mut rng.0, &sprites, kind, pos);
+}
+
+/// A power-up drifting off from `pos` in a random direction
+fn spawn_powerup(
+    cmd: &mut EntityCommands,
+    rng: &mut fastrand::Rng,
+    sprites: &Sprites,
+    kind: PowerUpKind,
+    pos: Vec3,
+) {
+    let (s, c) = (rng.f32() * std::f32::consts::TAU).sin_cos();
+    let vel = Vec2::new(c, s) * 0.5;
+
+    cmd.insert_bundle(transform_bundle(Transform {
+        pos,
+        scale: Vec3::splat(0.8),
+        ..Default::default()
+    }))
+    .insert_bundle(sprite_sheet_bundle(
+        sprites.powerup_sheet.clone(),
+        SpriteInstance {
+            index: kind as u32,
+            flip: false,
+        },
+    ))
     .insert_bundle(aabb_bundle(
-        AABB::around_origin(Vec2::splat(0.5)),
-        PLAYER_TAG,
+        AABB::around_origin(Vec2::splat(0.8)),
+        POWERUP_TAG,
+        ColliderKind::Trigger,
     ))
     .insert_bundle((
+        PowerUp(kind),
         GameEntity,
-        Player::default(),
-        Velocity::default(),
-        RotationTime(Duration::default()),
+        Velocity(vel),
+        LifeTime(Timer::new(POWERUP_LIFETIME, false)),
     ));
 }
 
-fn setup_sprite_sheets(
-    graphics_state: Res<GraphicsState>,
-    mut assets: ResMut<assets::Assets<SpriteSheet>>,
-    mut sprites: ResMut<Sprites>,
+/// Keeps the shield ring around each ship in sync with its remaining charges
+fn shield_visual_system(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_player: Query<(EntityId, Option<&Shield>), With<Player>>,
+    mut q_rings: Query<(EntityId, &ShieldRing, &mut SpriteInstance)>,
 ) {
-    *sprites = Sprites {
-        bullet_sheet: load_sprite_sheet(
-            &graphics_state,
-            include_bytes!("../assets/bullet.png"),
-            Vec2::splat(128.0),
-            2,
-            "bullet",
-            &mut assets,
-        ),
-        bullet_n: 2,
-        asteroid_sheet: load_sprite_sheet(
-            &graphics_state,
-            include_bytes!("../assets/asteroids.png"),
-            Vec2::splat(128.0),
-            2,
-            "asteroids",
-            &mut assets,
-        ),
-        asteroid_n: 2,
-        game_over_sheet: load_sprite_sheet(
-            &graphics_state,
-            include_bytes!("../assets/game_over.png"),
-            Vec2::new(128.0, 32.0),
-            1,
-            "game_over",
-            &mut assets,
-        ),
-        player: load_sprite_sheet(
-            &graphics_state,
-            include_bytes!("../assets/ship.png"),
-            Vec2::new(32.0, 45.0),
-            1,
-            "ship",
-            &mut assets,
-        ),
-        thrust_sheet: load_sprite_sheet(
-            &graphics_state,
-            include_bytes!("../assets/flame.png"),
-            Vec2::splat(32.0),
-            4,
-            "flame",
-            &mut assets,
-        ),
-        thrust_n: 4,
-        digits: load_sprite_sheet(
-            &graphics_state,
-            include_bytes!("../assets/digits.png"),
-            Vec2::splat(16.0),
-            10,
-            "digits",
-            &mut assets,
+    // fewer charges -> more transparent frame
+    let frame = |shield: Option<&Shield>| {
+        shield
+            .map(|s| s.0)
+            .filter(|n| *n > 0)
+            .map(|n| sprites.shield_n - n.min(sprites.shield_n))
+    };
+    let mut ringed: SmallVec<[EntityId; 2]> = SmallVec::new();
+    for (id, ring, sprite) in q_rings.iter_mut() {
+        let Some(index) = q_player.fetch(ring.0).and_then(|(_, shield)| frame(shield)) else {
+            cmd.delete(id);
+            continue;
+        };
+        sprite.index = index;
+        ringed.push(ring.0);
+    }
+    for (player_id, shield) in q_player.iter() {
+        if ringed.contains(&player_id) {
+            continue;
+        }
+        let Some(index) = frame(shield) else {
+            continue;
+        };
+        transform::spawn_child(player_id, &mut cmd, |cmd| {
+            cmd.insert_bundle(transform_bundle(Transform {
+                pos: Vec3::new(0.0, 0.0, layers::SHIELD),
+                scale: Vec3::splat(2.5),
+                ..Default::default()
+            }))
+            .insert_bundle(sprite_sheet_bundle(
+                sprites.shield_sheet.clone(),
+                SpriteInstance { index, flip: false },
+            ))
+            .insert_bundle((ShieldRing(player_id), GameEntity));
+        });
+    }
+}
+
+/// Follows the midpoint of the ships, pulling back while they're too far apart to fit the view
+fn camera_controller(
+    dt: Res<DeltaTime>,
+    q_player: Query<(&GlobalTransform, &Velocity), With<Player>>,
+    mut q_cam: Query<(&mut Transform, &mut PlayerCamera, &mut LookAhead)>,
+    map: Res<MapConfig>,
+) {
+    let mut ships = q_player
+        .iter()
+        .map(|(tr, vel)| (tr.0.pos.truncate(), vel.0));
+    let framing = ships.next().map(|(pos, vel)| {
+        let (min, max, vel, n) = ships
+            .fold((pos, pos, vel, 1.0), |(min, max, v, n), (pos, vel)| {
+                (min.min(pos), max.max(pos), v + vel, n + 1.0)
+            });
+        let half_spread = (max - min) * 0.5;
+        let zoom = if n > 1.0 {
+            ((half_spread + Vec2::splat(COOP_VIEW_MARGIN)) / VIEW_HALF_EXTENT).max_element()
+        } else {
+            1.0
+        };
+        ((min + half_spread).extend(0.0), vel / n, zoom)
+    });
+    let dt = dt.0.as_secs_f32();
+
+    for (tr, cam, look_ahead) in q_cam.iter_mut() {
+        // after a death keep easing towards the last known position while the lead dies out
+        let (player_pos, vel, zoom) = framing.unwrap_or((cam.last_seen, Vec2::ZERO, 1.0));
+        cam.last_seen = player_pos;
+        // ease the offset instead of snapping it, so quick turns don't jerk the camera around
+        let target_offset = vel * look_ahead.factor;
+        let t = (dt * look_ahead.ease_speed).min(1.0);
+        look_ahead.offset = look_ahead.offset.lerp(target_offset, t);
+
+        let target_zoom = zoom.clamp(1.0, MAX_CAMERA_ZOOM);
+        cam.zoom += (target_zoom - cam.zoom) * (dt * cam.follow_speed).min(1.0);
+
+        let d = player_pos + look_ahead.offset.extend(0.0) - tr.pos;
+        tr.pos += d * dt * cam.follow_speed;
+        // pulling the camera back widens the view by the same factor
+        tr.pos.z = CAMERA_DISTANCE * (cam.zoom - 1.0);
+        // keep the view inside the map, a map smaller than the view is centered
+        let bound = (Vec2::splat(map.radius) - VIEW_HALF_EXTENT * cam.zoom).max(Vec2::ZERO);
+        tr.pos.x = tr.pos.x.clamp(-bound.x, bound.x);
+        tr.pos.y = tr.pos.y.clamp(-bound.y, bound.y);
+    }
+}
+
+/// Wreck of the player ship, drifting to a stop
+struct DeadDrift;
+
+fn dead_drift_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut q: Query<&mut Velocity, With<DeadDrift>>,
+) {
+    let dt = time_scale.scale(&dt).as_secs_f32();
+    for v in q.iter_mut() {
+        v.0 *= (1.0 - DEAD_DRIFT_DRAG * dt).max(0.0);
+    }
+}
+
+/// What an entity does when it crosses the edge of the map, entities without one wrap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WrapBehavior {
+    /// Comes back in on the opposite edge
+    Wrap,
+    /// Removed, pooled bullets go back to the pool
+    Despawn,
+}
+
+fn wraparound_system(
+    mut cmd: Commands,
+    map: Res<MapConfig>,
+    mut q: Query<(&mut Transform, &GlobalTransform, Option<&WrapBehavior>), WithOut<WrapGhost>>,
+    q_despawn: Query<(EntityId, &GlobalTransform, &WrapBehavior, Option<&Pooled>)>,
+) {
+    for (id, g, behavior, pooled) in q_despawn.iter() {
+        if *behavior != WrapBehavior::Despawn || map.contains(g.0.pos.truncate()) {
+            continue;
+        }
+        if pooled.is_some() {
+            deactivate_bullet(cmd.entity(id));
+        } else {
+            cmd.delete(id);
+        }
+    }
+    let map: &MapConfig = &map;
+    q.par_for_each_mut(|(tr, g, behavior)| {
+        if behavior == Some(&WrapBehavior::Despawn) {
+            return;
+        }
+        tr.pos += map.wrap_shift(g.0.pos.truncate()).extend(0.0);
+    });
+}
+
+/// Copy of a sprite drawn on the opposite side of the map while its source straddles the edge
+struct WrapGhost {
+    pub source: EntityId,
+    pub offset: Vec3,
+}
+
+/// Distance from the map edge at which objects start rendering a ghost
+const GHOST_MARGIN: f32 = 1.0;
+
+fn wrap_offsets(pos: Vec3, radius: f32) -> SmallVec<[Vec3; 3]> {
+    let size = 2.0 * radius;
+    let shift = |p: f32| {
+        if p < -radius + GHOST_MARGIN {
+            size
+        } else if p > radius - GHOST_MARGIN {
+            -size
+        } else {
+            0.0
+        }
+    };
+    let dx = shift(pos.x);
+    let dy = shift(pos.y);
+    let mut result = SmallVec::new();
+    if dx != 0.0 {
+        result.push(Vec3::new(dx, 0.0, 0.0));
+    }
+    if dy != 0.0 {
+        result.push(Vec3::new(0.0, dy, 0.0));
+    }
+    if dx != 0.0 && dy != 0.0 {
+        result.push(Vec3::new(dx, dy, 0.0));
+    }
+    result
+}
+
+fn wrap_ghost_system(
+    mut cmd: Commands,
+    q_sources: Query<
+        (
+            EntityId,
+            &GlobalTransform,
+            &SpriteInstance,
+            &Handle<SpriteSheet>,
         ),
+        (With<Velocity>, With<CollisionTag>),
+    >,
+    mut q_ghosts: Query<
+        (EntityId, &WrapGhost, &mut Transform, &mut SpriteInstance),
+        WithOut<Velocity>,
+    >,
+    map: Res<MapConfig>,
+) {
+    let mut existing = Vec::new();
+    // ghosts have no velocity of their own, so their sprites can be written while the sources'
+    // are read
+    for (id, ghost, tr, ghost_sprite) in q_ghosts.iter_mut() {
+        let source = q_sources.fetch(ghost.source).filter(|(_, src_tr, _, _)| {
+            wrap_offsets(src_tr.0.pos, map.radius).contains(&ghost.offset)
+        });
+        let Some((_, src_tr, sprite, _)) = source else {
+            cmd.delete(id);
+            continue;
+        };
+        *tr = Transform {
+            pos: src_tr.0.pos + ghost.offset,
+            ..src_tr.0
+        };
+        ghost_sprite.index = sprite.index;
+        ghost_sprite.flip = sprite.flip;
+        existing.push((ghost.source, ghost.offset));
+    }
+    for (source, tr, sprite, sheet) in q_sources.iter() {
+        for offset in wrap_offsets(tr.0.pos, map.radius) {
+            if existing.contains(&(source, offset)) {
+                continue;
+            }
+            let ghost_tr = Transform {
+                pos: tr.0.pos + offset,
+                ..tr.0
+            };
+            cmd.spawn()
+                .insert_bundle(transform_bundle(ghost_tr))
+                .insert_bundle(sprite_sheet_bundle(
+                    sheet.clone(),
+                    SpriteInstance {
+                        index: sprite.index,
+                        flip: sprite.flip,
+                    },
+                ))
+                .insert_bundle((WrapGhost { source, offset }, GameEntity));
+        }
+    }
+}
+
+fn spawn_asteroid(
+    cmd: &mut EntityCommands,
+    rng: &mut fastrand::Rng,
+    transform: Transform,
+    assets: &Sprites,
+    kind: AsteroidKind,
+    vel: Velocity,
+) {
+    let stats = kind.stats();
+    let index = rng.u32(..stats.variants);
+    let spin = 0.5 + rng.f32();
+    let angular_vel = AngularVelocity(if rng.bool() { spin } else { -spin });
+    cmd.insert_bundle(sprite_renderer::sprite_sheet_bundle(
+        kind.sheet(assets),
+        SpriteInstance {
+            index,
+            flip: rng.bool(),
+        },
+    ))
+    .insert_bundle((
+        Asteroid,
+        kind,
+        Health(stats.health),
+        vel,
+        angular_vel,
+        GameEntity,
+        WrapBehavior::Wrap,
+    ))
+    .insert_bundle(aabb_bundle(
+        AABB::around_origin(Vec2::splat(stats.size)),
+        ASTEROID_TAG,
+        ColliderKind::Solid,
+    ))
+    .insert_bundle(transform_bundle(transform));
+}
+
+/// How asteroids come in, picked by [DifficultyConfig::spawn_mode]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SpawnMode {
+    /// Keep the field topped up to the [Difficulty] target
+    Endless,
+    /// Discrete waves, the next one starts when every asteroid is destroyed
+    Waves,
+}
+
+/// Current wave number, 0 before the first wave starts
+struct Wave(pub u32);
+
+/// Break between two waves, the next wave spawns when it finishes
+struct WaveCountdown(pub Option<Timer>);
+
+/// Tops the field up to [Difficulty::target_asteroids], at the spawn rate whatever the frame rate
+fn spawn_asteroids_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    q_asteroid: Query<(&GlobalTransform, &AsteroidKind), With<Asteroid>>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+    q_clearance: Query<(&GlobalTransform, &SpawnClearance, Option<&Player>)>,
+    mut rng: ResMut<Rng>,
+    mut difficulty: ResMut<Difficulty>,
+    asteroid_config: Res<AsteroidConfig>,
+    spawn_density: Res<SpawnDensity>,
+    map: Res<MapConfig>,
+) {
+    if difficulty.config.spawn_mode != SpawnMode::Endless {
+        return;
+    }
+    let count = q_asteroid.count();
+
+    let Some(player_pos) = first_ship(&q_clearance) else {
+        return;
     };
+
+    let n = difficulty.take_spawns(time_scale.scale(&dt), count);
+    if n == 0 {
+        return;
+    }
+    let mut occupied = occupied_spawn_area(&q_asteroid, &q_clearance);
+    for _ in 0..n {
+        spawn_random_asteroid(
+            &mut cmd,
+            &mut rng.0,
+            &assets,
+            &asteroid_config,
+            &spawn_density,
+            &mut occupied,
+            player_pos,
+            difficulty.speed_multiplier(),
+            &map,
+        );
+    }
 }
 
-fn load_sprite_sheet(
-    graphics_state: &GraphicsState,
-    bytes: &[u8],
-    box_size: Vec2,
-    num_cols: u32,
-    label: &str,
+/// Spawns the next wave when the countdown between waves finishes
+fn wave_system(
+    dt: Res<DeltaTime>,
+    wave: Res<Wave>,
+    mut countdown: ResMut<WaveCountdown>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+    mut rng: ResMut<Rng>,
+    difficulty: Res<Difficulty>,
+    asteroid_config: Res<AsteroidConfig>,
+    spawn_density: Res<SpawnDensity>,
+    q_asteroid: Query<(&GlobalTransform, &AsteroidKind), With<Asteroid>>,
+    q_clearance: Query<(&GlobalTransform, &SpawnClearance, Option<&Player>)>,
+    mut fade: ResMut<ScreenFade>,
+    map: Res<MapConfig>,
+    boss_config: Res<boss::BossConfig>,
+    telegraph_config: Res<telegraph::TelegraphConfig>,
+) {
+    if difficulty.config.spawn_mode != SpawnMode::Waves {
+        return;
+    }
+    let Some(player_pos) = first_ship(&q_clearance) else {
+        return;
+    };
+    let Some(timer) = countdown.0.as_mut() else {
+        return;
+    };
+    timer.update(dt.0);
+    if !timer.just_finished() {
+        return;
+    }
+    countdown.0 = None;
+    fade.lighten(0.0);
+    let n = wave.0.saturating_sub(1);
+    let count = (WAVE_BASE_COUNT + n as usize * WAVE_COUNT_STEP).min(difficulty.target_asteroids());
+    let speed = difficulty.speed_multiplier() * (1.0 + n as f32 * WAVE_SPEED_STEP);
+    let mut occupied = occupied_spawn_area(&q_asteroid, &q_clearance);
+    for _ in 0..count {
+        spawn_random_asteroid(
+            &mut cmd,
+            &mut rng.0,
+            &assets,
+            &asteroid_config,
+            &spawn_density,
+            &mut occupied,
+            player_pos,
+            speed,
+            &map,
+        );
+    }
+    if boss_config.is_boss_wave(wave.0) {
+        let pos = boss::boss_spawn_point(&mut rng.0, &boss_config, player_pos, map.radius);
+        telegraph::spawn_telegraph(
+            &mut cmd,
+            &assets,
+            &telegraph_config,
+            telegraph::TelegraphKind::Boss,
+            pos,
+        );
+    }
+}
+
+/// Starts the countdown to the next wave once every asteroid and boss is destroyed
+///
+/// Runs before [wave_system], the asteroids of a wave that just spawned aren't in the world yet.
+/// A boss still being announced counts as there.
+fn wave_clear_system(
+    difficulty: Res<Difficulty>,
+    mut wave: ResMut<Wave>,
+    mut countdown: ResMut<WaveCountdown>,
+    mut score: ResMut<Score>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+    q_asteroid: Query<&(), With<Asteroid>>,
+    q_boss: Query<&(), With<boss::Boss>>,
+    q_telegraph: Query<&telegraph::SpawnTelegraph>,
+    q_player: Query<&(), With<Player>>,
+    q_camera: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut fade: ResMut<ScreenFade>,
+) {
+    if difficulty.config.spawn_mode != SpawnMode::Waves
+        || q_player.is_empty()
+        || countdown.0.is_some()
+    {
+        return;
+    }
+    let boss_due = q_telegraph
+        .iter()
+        .any(|t| t.kind == telegraph::TelegraphKind::Boss);
+    if !q_asteroid.is_empty() || !q_boss.is_empty() || boss_due {
+        return;
+    }
+    if wave.0 > 0 {
+        score.score += WAVE_CLEAR_BONUS * wave.0 as u64;
+    }
+    wave.0 += 1;
+    countdown.0 = Some(Timer::new(WAVE_BREAK, false));
+    let pos = q_camera.single().map(|tr| tr.0.pos).unwrap_or_default();
+    spawn_wave_banner(&mut cmd, &assets, wave.0, pos);
+    fade.darken(WAVE_FADE_ALPHA);
+}
+
+/// "WAVE N" centered on `pos`, removed when the wave starts
+fn spawn_wave_banner(cmd: &mut Commands, assets: &Sprites, wave: u32, pos: Vec3) {
+    const SCALE: f32 = 2.0;
+    let n_digits = wave.to_string().len() as f32;
+    // digits are laid out towards +x like the score, followed by a half glyph gap and the 4
+    // glyph wide label
+    let mut origin = pos;
+    origin.x -= (n_digits + 3.5) * 0.5 * SCALE;
+    origin.z = layers::BANNER;
+
+    spawn_digits(cmd, None, assets, wave as u64, origin, SCALE, |cmd| {
+        cmd.insert_bundle((GameEntity, LifeTime(Timer::new(WAVE_BREAK, false))));
+    });
+    cmd.spawn()
+        .insert_bundle(transform_bundle(Transform {
+            pos: origin + Vec3::X * (n_digits + 2.0) * SCALE,
+            scale: Vec3::new(4.0 * SCALE, SCALE, 1.0),
+            ..Default::default()
+        }))
+        .insert_bundle(sprite_sheet_bundle(
+            assets.wave_banner.clone(),
+            SpriteInstance {
+                index: 0,
+                flip: true,
+            },
+        ))
+        .insert_bundle((GameEntity, LifeTime(Timer::new(WAVE_BREAK, false))));
+}
+
+/// Relative likelihood of spawning asteroids, by distance from the player
+struct SpawnDensity {
+    /// Weight right outside the exclusion radius around the player
+    pub near: f32,
+    /// Weight at [SpawnDensity::falloff] distance and beyond
+    pub far: f32,
+    /// Distance over which the weight goes from `near` to `far`
+    pub falloff: f32,
+}
+
+impl Default for SpawnDensity {
+    fn default() -> Self {
+        Self {
+            near: 0.2,
+            far: 1.0,
+            falloff: MAP_RADIUS,
+        }
+    }
+}
+
+impl SpawnDensity {
+    /// Probability (0..1) of keeping a spawn candidate `distance` away from the player
+    pub fn acceptance(&self, distance: f32) -> f32 {
+        let max = self.near.max(self.far);
+        if max <= 0.0 {
+            return 1.0;
+        }
+        let t = (distance / self.falloff.max(f32::EPSILON)).clamp(0.0, 1.0);
+        (self.near + (self.far - self.near) * t) / max
+    }
+}
+
+/// Position of the ship the spawn density is centered on
+fn first_ship(
+    q_clearance: &Query<(&GlobalTransform, &SpawnClearance, Option<&Player>)>,
+) -> Option<Vec3> {
+    q_clearance
+        .iter()
+        .find(|(_, _, player)| player.is_some())
+        .map(|(tr, _, _)| tr.0.pos)
+}
+
+/// Position and radius of the existing asteroids and the [SpawnClearance] around ships and UFOs,
+/// new asteroids are kept clear of these
+fn occupied_spawn_area(
+    q_asteroid: &Query<(&GlobalTransform, &AsteroidKind), With<Asteroid>>,
+    q_clearance: &Query<(&GlobalTransform, &SpawnClearance, Option<&Player>)>,
+) -> Vec<(Vec2, f32)> {
+    let asteroids = q_asteroid.iter().map(|(tr, kind)| {
+        let radius = kind.stats().size * 0.5 * tr.0.scale.x;
+        (tr.0.pos.truncate(), radius)
+    });
+    let clearances = q_clearance
+        .iter()
+        .map(|(tr, clearance, _)| (tr.0.pos.truncate(), clearance.0));
+    asteroids.chain(clearances).collect()
+}
+
+/// Spawn a full size asteroid at a random position, see [roll_asteroid]
+fn spawn_random_asteroid(
+    cmd: &mut Commands,
+    rng: &mut fastrand::Rng,
+    assets: &Sprites,
+    config: &AsteroidConfig,
+    density: &SpawnDensity,
+    occupied: &mut Vec<(Vec2, f32)>,
+    player_pos: Vec3,
+    speed: f32,
+    map: &MapConfig,
+) {
+    let Some((kind, tr, vel)) =
+        roll_asteroid(rng, config, density, occupied, player_pos, speed, map)
+    else {
+        return;
+    };
+    spawn_asteroid(cmd.spawn(), rng, tr, assets, kind, vel);
+}
+
+/// Kind, placement and velocity of a full size asteroid at a random position, with the density
+/// around the player and away from the `occupied` areas, the new asteroid is added to them
+///
+/// Gives up after [MAX_SPAWN_ATTEMPTS] rejected positions, so a crowded map can't stall the frame
+fn roll_asteroid(
+    rng: &mut fastrand::Rng,
+    config: &AsteroidConfig,
+    density: &SpawnDensity,
+    occupied: &mut Vec<(Vec2, f32)>,
+    player_pos: Vec3,
+    speed: f32,
+    map: &MapConfig,
+) -> Option<(AsteroidKind, Transform, Velocity)> {
+    let kind = AsteroidKind::random(rng);
+    let radius = kind.stats().size * 0.5;
+    let mut pos;
+    let mut attempts = 0;
+    loop {
+        if attempts == MAX_SPAWN_ATTEMPTS {
+            return None;
+        }
+        attempts += 1;
+        pos = map.random_point(rng).extend(layers::ASTEROID);
+        let distance = pos.distance(player_pos);
+        if rng.f32() >= density.acceptance(distance) {
+            continue;
+        }
+        let clear = occupied
+            .iter()
+            .all(|(p, r)| p.distance(pos.truncate()) >= r + radius);
+        if clear {
+            break;
+        }
+    }
+    occupied.push((pos.truncate(), radius));
+    let rot = glam::Quat::from_axis_angle(Vec3::Z, rng.f32() * std::f32::consts::TAU);
+
+    let (s, c) = (rng.f32() * std::f32::consts::TAU).sin_cos();
+    let magnitude = config.min_speed + rng.f32() * (config.max_speed - config.min_speed);
+    let vel = Vec2::new(c, s) * magnitude * speed;
+
+    let tr = Transform {
+        pos,
+        rot,
+        scale: Vec3::ONE,
+    };
+    Some((kind, tr, Velocity(vel)))
+}
+
+fn player_rotation_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    inputs: Res<KeyBoardInputs>,
+    physics: Res<ShipPhysics>,
+    tilt: Res<tilt::TiltControls>,
+    mut q: Query<
+        (
+            &mut transform::Transform,
+            &mut RotationTime,
+            &KeyBindings,
+            Option<&SecondPlayer>,
+        ),
+        With<Player>,
+    >,
+) {
+    let dt = time_scale.scale(&dt);
+    for (tr, rot_time, keys, second) in q.iter_mut() {
+        let rot = ship_turn(&inputs, &tilt, keys, second).clamp(-1.0, 1.0);
+
+        if rot != 0.0 {
+            let rotation_velocity = rot_time.ramp(dt, physics.turn_rate);
+            let rotation = rot * dt.as_secs_f32() * rotation_velocity;
+            tr.rot = tr.rot.rotate_around_self(PrimaryAxis::Z, rotation);
+        } else {
+            rot_time.0 = Default::default();
+        }
+    }
+}
+
+/// Turn input of a ship, the device tilt steers the first player's
+fn ship_turn(
+    inputs: &KeyBoardInputs,
+    tilt: &tilt::TiltControls,
+    keys: &KeyBindings,
+    second: Option<&SecondPlayer>,
+) -> f32 {
+    let tilt = if second.is_none() { tilt.turn } else { 0.0 };
+    tilt + keys.turn(inputs)
+}
+
+/// Squashes the ship horizontally while it turns, easing back when it stops
+fn ship_bank_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    inputs: Res<KeyBoardInputs>,
+    tilt: Res<tilt::TiltControls>,
+    mut q: Query<
+        (
+            &mut Transform,
+            &mut AABB,
+            &mut Bank,
+            &RotationTime,
+            &KeyBindings,
+            Option<&SecondPlayer>,
+        ),
+        With<Player>,
+    >,
+) {
+    let dt = time_scale.scale(&dt).as_secs_f32();
+    for (tr, aabb, bank, rot_time, keys, second) in q.iter_mut() {
+        let dir = ship_turn(&inputs, &tilt, keys, second).clamp(-1.0, 1.0);
+        // bank further the longer the turn is held, like the rotation speed ramp
+        let target = dir * (rot_time.0.as_secs_f32() / ROTATION_RAMP.as_secs_f32()).min(1.0);
+        bank.0 += (target - bank.0) * (dt * 10.0).min(1.0);
+        let squash = 1.0 - MAX_BANK * bank.0.abs();
+        tr.scale.x = PLAYER_SCALE * squash;
+        // the collider is scaled by the transform, undo the squash so banking doesn't shrink it
+        *aabb = AABB::around_origin(Vec2::new(PLAYER_COLLIDER.x / squash, PLAYER_COLLIDER.y));
+    }
+}
+
+fn player_thrust_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    inputs: Res<KeyBoardInputs>,
+    physics: Res<ShipPhysics>,
+    mut q: Query<(
+        EntityId,
+        &transform::Transform,
+        &mut Velocity,
+        &mut Player,
+        &KeyBindings,
+    )>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    thrusters: Query<(EntityId, &Thrust)>,
+) {
+    let dt = time_scale.scale(&dt).as_secs_f32();
+    for (id, tr, vel, player, keys) in q.iter_mut() {
+        if inputs.just_released.contains(&keys.thrust) {
+            player.acceleration = 0.0;
+            player.velocity = vel.0.length();
+            for (flame, _) in thrusters.iter().filter(|(_, thrust)| thrust.0 == id) {
+                cmd.delete(flame);
+            }
+        }
+        if inputs.pressed.contains(&keys.thrust) {
+            if !thrusters.iter().any(|(_, thrust)| thrust.0 == id) {
+                transform::spawn_child(id, &mut cmd, |cmd| {
+                    cmd.insert_bundle(transform_bundle(Transform::from_position(Vec3::new(
+                        0.0,
+                        -0.5,
+                        layers::THRUST,
+                    ))))
+                    .insert_bundle((
+                        Thrust(id),
+                        ThrustTrail(0.0),
+                        GameEntity,
+                        UniformAnimation {
+                            timer: Timer::new(Duration::from_millis(100), true),
+                            n: sprites.thrust_n,
+                        },
+                        AnimationSpeed(1.0),
+                    ))
+                    .insert_bundle(sprite_renderer::sprite_sheet_bundle(
+                        sprites.thrust_sheet.clone(),
+                        None,
+                    ));
+                });
+            }
+            let facing = (tr.rot * Vec3::Y).truncate();
+            vel.0 = physics.thrust(player, vel.0, facing, dt);
+        } else {
+            player.velocity = (vel.0.length() - dt * physics.friction).max(0.0);
+            vel.0 = vel.0.normalize_or_zero() * player.velocity;
+        }
+    }
+}
+
+/// Fractional trail particles of a flame carried over between frames
+struct ThrustTrail(pub f32);
+
+struct TrailParticle;
+
+/// Leaves fading puffs behind the flame, more of them the faster the ship goes
+fn thrust_trail_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_player: Query<&Velocity, With<Player>>,
+    mut q_flame: Query<(&GlobalTransform, &Thrust, &mut ThrustTrail)>,
+) {
+    let dt = time_scale.scale(&dt).as_secs_f32();
+    for (flame, thrust, trail) in q_flame.iter_mut() {
+        let Some(vel) = q_player.fetch(thrust.0) else {
+            continue;
+        };
+        let rate = TRAIL_BASE_RATE + vel.0.length() * TRAIL_RATE_PER_SPEED;
+        trail.0 += rate * dt;
+        while trail.0 >= 1.0 {
+            trail.0 -= 1.0;
+            let mut pos = flame.0.pos;
+            pos.z -= layers::THRUST;
+            cmd.spawn()
+                .insert_bundle(transform_bundle(Transform {
+                    pos,
+                    scale: Vec3::splat(0.3),
+                    ..Default::default()
+                }))
+                .insert_bundle(sprite_sheet_bundle(sprites.explosion_sheet.clone(), None))
+                .insert_bundle((
+                    TrailParticle,
+                    GameEntity,
+                    Velocity(vel.0 * TRAIL_INHERIT_VELOCITY),
+                    LifeTime(Timer::new(
+                        Duration::from_millis(60) * sprites.explosion_n,
+                        false,
+                    )),
+                    UniformAnimation {
+                        timer: Timer::new(Duration::from_millis(60), true),
+                        n: sprites.explosion_n,
+                    },
+                ));
+        }
+    }
+}
+
+/// The flame grows and flickers faster as the ship builds up acceleration
+///
+/// Flames of wrecked ships go out here, [player_thrust_system] only puts them out for ships still
+/// flying. The restart clears the rest, flames are [GameEntity]s.
+fn thrust_flame_system(
+    mut cmd: Commands,
+    physics: Res<ShipPhysics>,
+    q_player: Query<&Player>,
+    mut q_flame: Query<(EntityId, &mut Transform, &mut AnimationSpeed, &Thrust)>,
+) {
+    for (id, tr, speed, thrust) in q_flame.iter_mut() {
+        let Some((scale, animation_speed)) = flame(&physics, q_player.fetch(thrust.0)) else {
+            cmd.delete(id);
+            continue;
+        };
+        tr.scale = Vec3::splat(scale);
+        speed.0 = animation_speed;
+    }
+}
+
+/// Scale and animation speed of the thrust flame of `player`, `None` once the ship is no longer a
+/// [Player], so a ship that died mid-thrust doesn't keep its flame
+fn flame(physics: &ShipPhysics, player: Option<&Player>) -> Option<(f32, f32)> {
+    let t = (player?.acceleration / physics.max_acc).clamp(0.0, 1.0);
+    Some((
+        1.0 + (THRUST_MAX_SCALE - 1.0) * t,
+        1.0 + (THRUST_MAX_ANIMATION_SPEED - 1.0) * t,
+    ))
+}
+
+/// Speed of game time relative to real time
+///
+/// Gameplay systems scale their `DeltaTime` by it, UI and cooldowns keep running in real time.
+struct TimeScale(pub f32);
+
+impl TimeScale {
+    pub fn scale(&self, dt: &DeltaTime) -> Duration {
+        dt.0.mul_f32(self.0)
+    }
+}
+
+/// Eases the time scale back to real time
+fn time_scale_system(dt: Res<DeltaTime>, mut time_scale: ResMut<TimeScale>) {
+    let recovery = (1.0 - DEATH_TIME_SCALE) / SLOW_MOTION_RECOVERY.as_secs_f32();
+    time_scale.0 = (time_scale.0 + recovery * dt.0.as_secs_f32()).min(1.0);
+}
+
+fn move_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut q: Query<(&mut Transform, &Velocity)>,
+) {
+    let dt = time_scale.scale(&dt).as_secs_f32();
+    q.par_for_each_mut(|(tr, v)| {
+        tr.pos += v.0.extend(0.0) * dt;
+    });
+}
+
+/// Time until the entity can act again
+///
+/// [cooldown_system] removes it once it's over, so `With<Cooldown>` and `WithOut<Cooldown>`
+/// filters tell whether it's running.
+struct Cooldown {
+    pub elapsed: Duration,
+    pub duration: Duration,
+}
+
+impl Cooldown {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            duration,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Share of the cooldown still to go, 1 when it just started and 0 once it's over
+    pub fn remaining_fraction(&self) -> f32 {
+        if self.is_finished() {
+            return 0.0;
+        }
+        1.0 - self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+    }
+}
+
+fn cooldown_system(
+    mut cmd: Commands,
+    dt: Res<DeltaTime>,
+    mut cd: Query<(EntityId, &mut Cooldown)>,
+) {
+    for (id, cd) in cd.iter_mut() {
+        cd.elapsed = (cd.elapsed + dt.0).min(cd.duration);
+        if cd.is_finished() {
+            cmd.entity(id).remove::<Cooldown>();
+        }
+    }
+}
+
+#[allow(unused)]
+struct FireSound;
+
+#[cfg(not(target_family = "wasm"))]
+fn setup_slash(mut cmd: Commands, mut assets: ResMut<assets::Assets<brengin::audio::Audio>>) {
+    let bytes = include_bytes!("../assets/slash.mp3");
+    let music = brengin::audio::Audio::load_audio_bytes(bytes, &mut assets).unwrap();
+    cmd.spawn().insert_bundle((FireSound, music));
+}
+
+#[cfg(target_family = "wasm")]
+fn setup_slash() {}
+
+/// A shot is waiting for the fire cooldown to run out
+struct FireBuffered(pub bool);
+
+impl FireBuffered {
+    /// Whether the ship fires this frame, given the state of its weapon and whether the trigger is
+    /// `pressed` or was just `released`
+    fn update(
+        &mut self,
+        cooling_down: bool,
+        overheated: bool,
+        pressed: bool,
+        released: bool,
+    ) -> bool {
+        if cooling_down {
+            // a tap that starts and ends within the cooldown fires once it's over instead of
+            // getting lost, letting go of a held trigger counts too
+            self.0 |= released;
+            return false;
+        }
+        // unlike the cooldown, overheating doesn't save the shot for later
+        let fire = !overheated && (pressed || self.0);
+        self.0 = false;
+        fire
+    }
+}
+
+/// Ship that fired the bullet, the fire cooldown of each ship rides on its last volley
+struct ShotBy(pub EntityId);
+
+/// Where the ship's shots heading in the unit vector `dir` appear, just past the edge of its
+/// `collider`, so they clear the ship whatever its size
+fn muzzle_position(tr: &GlobalTransform, collider: &AABB, dir: Vec2) -> Vec3 {
+    // the same box update_aabbs_system makes, from this frame's transform
+    let scale = tr.0.scale.truncate();
+    let center = collider.center() * scale + tr.0.pos.truncate();
+    let size = collider.size() * scale;
+    let extent = AABB::around_point(center, size).extent_along(dir);
+    (center + dir * (extent + MUZZLE_GAP)).extend(layers::BULLET)
+}
+
+fn fire_system(
+    inputs: Res<KeyBoardInputs>,
+    mut cmd: Commands,
+    mut pool: ResMut<BulletPool>,
+    mut q_player: Query<(
+        EntityId,
+        &GlobalTransform,
+        &AABB,
+        &Player,
+        &KeyBindings,
+        &mut FireBuffered,
+        Option<&SecondPlayer>,
+    )>,
+    q_cd: Query<&ShotBy, (With<Cooldown>, With<Bullet>)>,
+    mut q_weapon: Query<(
+        &mut WeaponHeat,
+        Option<&Overheated>,
+        Option<&Piercing>,
+        Option<&RapidFire>,
+        Option<&SpreadShot>,
+    )>,
+    heat_config: Res<WeaponHeatConfig>,
+    max_bullets: Res<MaxBullets>,
+    range: Res<BulletRange>,
+    physics: Res<ShipPhysics>,
+    mut rng: ResMut<Rng>,
+    weapon: Res<Weapon>,
+
+    #[cfg(not(target_family = "wasm"))] audio: Res<assets::Assets<brengin::audio::Audio>>,
+    #[cfg(not(target_family = "wasm"))] am: Res<brengin::audio::AudioManager>,
+    #[cfg(not(target_family = "wasm"))] slash: Query<
+        &assets::Handle<brengin::audio::Audio>,
+        With<FireSound>,
+    >,
+) {
+    let mut in_flight = pool.used;
+    for (ship, tr, collider, player, keys, buffered, second) in q_player.iter_mut() {
+        // the weapon switch belongs to the first player, the second always flies with the blaster
+        if second.is_none() && *weapon != Weapon::Blaster {
+            buffered.0 = false;
+            continue;
+        }
+        let cooling_down = q_cd.iter().any(|shot_by| shot_by.0 == ship);
+        let Some((heat, overheated, piercing, rapid_fire, spread_shot)) = q_weapon.fetch_mut(ship)
+        else {
+            continue;
+        };
+        let fire = buffered.update(
+            cooling_down,
+            overheated.is_some(),
+            inputs.pressed.contains(&keys.fire),
+            inputs.just_released.contains(&keys.fire),
+        );
+        if !fire {
+            continue;
+        }
+        heat.0 += heat_config.blaster_shot;
+        if heat.0 >= heat_config.max {
+            cmd.entity(ship).insert(Overheated);
+        }
+        #[cfg(not(target_family = "wasm"))]
+        if let Some(s) = slash.single() {
+            let music = audio.get(s);
+            am.play(music);
+        }
+        let cooldown = if rapid_fire.is_some() {
+            RAPID_FIRE_COOLDOWN
+        } else {
+            FIRE_COOLDOWN
+        };
+        let angles: &[f32] = if spread_shot.is_some() {
+            &[0.0, -SPREAD_ANGLE, SPREAD_ANGLE]
+        } else {
+            &[0.0]
+        };
+        let pierce = piercing.map_or(0, |piercing| piercing.count);
+        let shots = max_bullets.allowed(in_flight, angles.len());
+        for (i, angle) in angles.iter().take(shots).enumerate() {
+            // pool exhausted, skip the shot instead of growing the pool
+            let Some(id) = pool.free.pop() else {
+                break;
+            };
+            in_flight += 1;
+            // stationary ships fire straight, faster ones are less accurate
+            let deviation = (rng.0.f32() * 2.0 - 1.0) * BULLET_INACCURACY * player.velocity;
+            let rot = tr.0.rot * glam::Quat::from_rotation_z(*angle + deviation);
+            let v = rot * Vec3::Y;
+            let vel = v * (1.0 + player.velocity).min(physics.max_vel + 1.0);
+            let pos = muzzle_position(tr, collider, v.truncate());
+
+            let vel = vel.truncate();
+            let lifetime = range.lifetime(vel.length());
+            let bullet = activate_bullet(cmd.entity(id), pos, rot, vel, lifetime);
+            // pooled bullets keep the count of their last shot otherwise
+            bullet.insert(Pierce(pierce));
+            // a single cooldown gates the whole volley
+            if i == 0 {
+                bullet.insert_bundle((Cooldown::new(cooldown), ShotBy(ship)));
+            }
+        }
+    }
+}
+
+/// Upper bound on the bullets in flight
+struct MaxBullets(pub usize);
+
+impl MaxBullets {
+    /// How many of the `requested` shots fit with `in_flight` bullets already active
+    pub fn allowed(&self, in_flight: usize, requested: usize) -> usize {
+        requested.min(self.0.saturating_sub(in_flight))
+    }
+}
+
+/// Distance bullets travel before expiring, so faster bullets don't reach further
+struct BulletRange {
+    pub range: f32,
+    /// Upper bound on the lifetime, slow bullets expire before reaching the range
+    pub max_lifetime: Duration,
+}
+
+impl Default for BulletRange {
+    fn default() -> Self {
+        Self {
+            range: 25.0,
+            max_lifetime: Duration::from_secs(5),
+        }
+    }
+}
+
+impl BulletRange {
+    pub fn lifetime(&self, speed: f32) -> Duration {
+        if speed <= 0.0 {
+            return self.max_lifetime;
+        }
+        // clamp before converting, very slow bullets would overflow a Duration
+        let secs = (self.range / speed).min(self.max_lifetime.as_secs_f32());
+        Duration::from_secs_f32(secs.max(0.0))
+    }
+}
+
+/// Pre-spawned bullets, reused to avoid spawning and deleting an entity per shot
+struct BulletPool {
+    pub capacity: usize,
+    /// Inactive bullets, refreshed every frame by [refresh_bullet_pool]
+    pub free: Vec<EntityId>,
+    /// Number of bullets in flight
+    pub used: usize,
+}
+
+impl BulletPool {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            free: Vec::with_capacity(capacity),
+            used: 0,
+        }
+    }
+
+    /// Collects the inactive ones among the pooled `bullets`, each given with whether it's in
+    /// flight, and returns how many have to be spawned to fill the pool up again
+    fn refresh(&mut self, bullets: impl Iterator<Item = (EntityId, bool)>) -> usize {
+        self.free.clear();
+        let mut total = 0;
+        for (id, in_flight) in bullets {
+            total += 1;
+            if !in_flight {
+                self.free.push(id);
+            }
+        }
+        self.used = total - self.free.len();
+        self.capacity.saturating_sub(total)
+    }
+}
+
+fn setup_bullet_pool(mut cmd: Commands, sprites: Res<Sprites>, pool: Res<BulletPool>) {
+    for _ in 0..pool.capacity {
+        spawn_pooled_bullet(cmd.spawn(), &sprites);
+    }
+}
+
+/// Collect the inactive bullets and replace pooled bullets that have been deleted
+fn refresh_bullet_pool(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    mut pool: ResMut<BulletPool>,
+    q_pooled: Query<(EntityId, Option<&CollisionTag>), With<Pooled>>,
+) {
+    let missing = pool.refresh(q_pooled.iter().map(|(id, tag)| (id, tag.is_some())));
+    for _ in 0..missing {
+        spawn_pooled_bullet(cmd.spawn(), &sprites);
+    }
+}
+
+/// Spawns an inactive bullet, call [activate_bullet] to fire it
+fn spawn_pooled_bullet(cmd: &mut EntityCommands, sprites: &Sprites) {
+    let aabb = AABB::around_origin(Vec2::new(0.25, 0.5));
+    cmd.insert_bundle(sprite_renderer::sprite_sheet_bundle(
+        sprites.bullet_sheet.clone(),
+        None,
+    ))
+    .insert_bundle((
+        Bullet,
+        Pooled,
+        WrapBehavior::Despawn,
+        UniformAnimation {
+            timer: Timer::new(Duration::from_millis(100), true),
+            n: sprites.bullet_n,
+        },
+        Velocity::default(),
+    ))
+    .insert_bundle(untagged_aabb_bundle(aabb))
+    .insert_bundle(transform::transform_bundle(
+        transform::Transform::from_scale(Vec3::ZERO),
+    ));
+}
+
+fn activate_bullet(
+    cmd: &mut EntityCommands,
+    pos: Vec3,
+    rot: glam::Quat,
+    vel: Vec2,
+    lifetime: Duration,
+) -> &mut EntityCommands {
+    cmd.insert_bundle((
+        transform::Transform {
+            pos,
+            rot,
+            scale: Vec3::splat(0.2),
+        },
+        Velocity(vel),
+        LifeTime(Timer::new(lifetime, false)),
+        BULLET_TAG,
+    ))
+}
+
+/// Streak stretched behind a bullet along its velocity, child of the bullet it belongs to
+struct Tracer(pub EntityId);
+
+/// The bullet has its [Tracer] attached
+struct HasTracer;
+
+fn bullet_tracer_system(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_new: Query<EntityId, (With<Bullet>, WithOut<HasTracer>)>,
+    q_bullet: Query<(&GlobalTransform, &Velocity), With<Bullet>>,
+    mut q_tracer: Query<(EntityId, &Tracer, &mut Transform)>,
+) {
+    for id in q_new.iter() {
+        transform::spawn_child(id, &mut cmd, |cmd| {
+            cmd.insert_bundle(transform_bundle(Transform::from_scale(Vec3::ZERO)))
+                .insert_bundle(sprite_sheet_bundle(sprites.tracer_sheet.clone(), None))
+                .insert(Tracer(id));
+        });
+        cmd.entity(id).insert(HasTracer);
+    }
+    for (id, tracer, tr) in q_tracer.iter_mut() {
+        let Some((bullet, vel)) = q_bullet.fetch(tracer.0) else {
+            cmd.delete(id);
+            continue;
+        };
+        let bullet = &bullet.0;
+        let length = vel.0.length() * TRACER_TIME;
+        if length <= f32::EPSILON || bullet.scale.x <= f32::EPSILON {
+            tr.scale = Vec3::ZERO;
+            continue;
+        }
+        // the tracer lives in the bullet's space, undo its rotation and scale
+        let inv_rot = bullet.rot.inverse();
+        let dir = vel.0.normalize();
+        let angle = dir.y.atan2(dir.x) - std::f32::consts::FRAC_PI_2;
+        tr.rot = inv_rot * glam::Quat::from_rotation_z(angle);
+        tr.pos = (inv_rot * (-dir * length * 0.5).extend(0.0)) / bullet.scale.x;
+        tr.pos.z = layers::TRACER;
+        tr.scale = Vec3::new(TRACER_WIDTH, length, 1.0) / bullet.scale.x;
+    }
+}
+
+/// Return the bullet to the pool: stop it, hide it and remove it from the broadphase
+fn deactivate_bullet(cmd: &mut EntityCommands) {
+    cmd.insert_bundle((Transform::from_scale(Vec3::ZERO), Velocity::default()))
+        .remove::<LifeTime>()
+        .remove::<CollisionTag>()
+        .remove::<PiercedThrough>();
+}
+
+fn weapon_upgrade_system(
+    mut cmd: Commands,
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut rapid_fire: Query<(EntityId, &mut RapidFire)>,
+    mut spread_shot: Query<(EntityId, &mut SpreadShot)>,
+    mut piercing: Query<(EntityId, &mut Piercing)>,
+) {
+    let dt = time_scale.scale(&dt);
+    for (id, rf) in rapid_fire.iter_mut() {
+        rf.0.update(dt);
+        if rf.0.just_finished() {
+            cmd.entity(id).remove::<RapidFire>();
+        }
+    }
+    for (id, ss) in spread_shot.iter_mut() {
+        ss.0.update(dt);
+        if ss.0.just_finished() {
+            cmd.entity(id).remove::<SpreadShot>();
+        }
+    }
+    for (id, p) in piercing.iter_mut() {
+        p.timer.update(dt);
+        if p.timer.just_finished() {
+            cmd.entity(id).remove::<Piercing>();
+        }
+    }
+}
+
+/// Cools the guns down, overheated guns fire again once cool enough
+fn weapon_heat_system(
+    mut cmd: Commands,
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    config: Res<WeaponHeatConfig>,
+    mut q_heat: Query<(EntityId, &mut WeaponHeat, Option<&Overheated>)>,
+) {
+    let cooling = config.cooling * time_scale.scale(&dt).as_secs_f32();
+    for (id, heat, overheated) in q_heat.iter_mut() {
+        heat.0 = (heat.0 - cooling).max(0.0);
+        if overheated.is_some() && heat.0 <= config.resume {
+            cmd.entity(id).remove::<Overheated>();
+        }
+    }
+}
+
+fn hyperspace_system(
+    inputs: Res<KeyBoardInputs>,
+    physics: Res<ShipPhysics>,
+    sprites: Res<Sprites>,
+    mut cmd: Commands,
+    mut q_player: Query<
+        (EntityId, &mut Transform, &mut Velocity, &mut Player),
+        (WithOut<Cooldown>, WithOut<SecondPlayer>),
+    >,
+    q_asteroids: Query<&GlobalTransform, With<Asteroid>>,
+    mut rng: ResMut<Rng>,
+    map: Res<MapConfig>,
+) {
+    if !inputs
+        .pressed
+        .iter()
+        .any(|k| matches!(k, VirtualKeyCode::LShift | VirtualKeyCode::RShift))
+    {
+        return;
+    }
+    let Some((id, tr, vel, player)) = q_player.single_mut() else {
+        return;
+    };
+
+    let departure = tr.pos;
+    let mut arrival = map.random_point(&mut rng.0).extend(departure.z);
+    // unlucky jumps land on an asteroid, the collision system will take care of the rest
+    let count = q_asteroids.count();
+    if count > 0 && rng.0.f32() < physics.hyperspace_risk() {
+        if let Some(asteroid) = q_asteroids.iter().nth(rng.0.usize(..count)) {
+            arrival.x = asteroid.0.pos.x;
+            arrival.y = asteroid.0.pos.y;
+        }
+    }
+
+    tr.pos = arrival;
+    vel.0 = Vec2::ZERO;
+    player.velocity = 0.0;
+    player.acceleration = 0.0;
+    cmd.entity(id).insert(Cooldown::new(HYPERSPACE_COOLDOWN));
+
+    spawn_teleport_effect(cmd.spawn(), &sprites, departure);
+    spawn_teleport_effect(cmd.spawn(), &sprites, arrival);
+}
+
+fn spawn_teleport_effect(cmd: &mut EntityCommands, sprites: &Sprites, mut pos: Vec3) {
+    pos.z += layers::TELEPORT_EFFECT;
+    cmd.insert_bundle(transform_bundle(Transform {
+        pos,
+        scale: Vec3::splat(1.5),
+        ..Default::default()
+    }))
+    .insert_bundle(sprite_sheet_bundle(sprites.thrust_sheet.clone(), None))
+    .insert_bundle((
+        TeleportEffect,
+        GameEntity,
+        LifeTime(Timer::new(Duration::from_millis(400), false)),
+        UniformAnimation {
+            timer: Timer::new(Duration::from_millis(100), true),
+            n: sprites.thrust_n,
+        },
+    ));
+}
+
+fn setup_player(mut cmd: Commands, assets: Res<Sprites>) {
+    // player
+    spawn_player(
+        cmd.spawn(),
+        assets.player.clone(),
+        Vec3::new(0.0, 0.0, layers::PLAYER),
+        PLAYER_ONE_KEYS,
+    );
+
+    // camera
+    cmd.spawn()
+        .insert(PlayerCamera {
+            follow_speed: 5.0,
+            last_seen: Vec3::ZERO,
+            zoom: 1.0,
+        })
+        .insert(LookAhead {
+            factor: 0.5,
+            ease_speed: 2.0,
+            offset: Vec2::ZERO,
+        })
+        .insert_bundle(renderer::camera_bundle(Camera3d {
+            eye: Vec3::new(0.0, 0.0, CAMERA_DISTANCE),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            aspect: 16.0 / 9.0,
+            fovy: 45.0,
+            znear: 5.0,
+            zfar: 50.0,
+        }))
+        .insert_bundle(transform_bundle(transform::Transform::default()));
+}
+
+fn spawn_player(
+    cmd: &mut EntityCommands,
+    sprite_handle: Handle<SpriteSheet>,
+    pos: Vec3,
+    keys: KeyBindings,
+) {
+    cmd.insert_bundle(transform::transform_bundle(transform::Transform {
+        pos,
+        scale: Vec3::splat(PLAYER_SCALE),
+        ..Default::default()
+    }))
+    .insert_bundle(sprite_renderer::sprite_sheet_bundle(sprite_handle, None))
+    .insert_bundle(aabb_bundle(
+        AABB::around_origin(PLAYER_COLLIDER),
+        PLAYER_TAG,
+        ColliderKind::Trigger,
+    ))
+    .insert_bundle((
+        GameEntity,
+        Player::default(),
+        Velocity::default(),
+        RotationTime(Duration::default()),
+        Bank(0.0),
+        FireBuffered(false),
+        WeaponHeat(0.0),
+        WrapBehavior::Wrap,
+        SpawnClearance(SHIP_SPAWN_CLEARANCE),
+        keys,
+    ));
+}
+
+/// Brings the second ship in next to the first one while co-op is on, and takes it out when
+/// co-op is switched off
+///
+/// A wrecked second ship stays down until the restart.
+fn coop_system(
+    inputs: Res<KeyBoardInputs>,
+    mut coop: ResMut<CoOp>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+    q_first: Query<&GlobalTransform, (With<Player>, WithOut<SecondPlayer>)>,
+    q_second: Query<EntityId, With<SecondPlayer>>,
+    q_game_over: Query<&(), With<GameOver>>,
+    name_entry: Res<leaderboard::NameEntry>,
+) {
+    if !name_entry.is_entering() && inputs.just_released.contains(&VirtualKeyCode::F5) {
+        coop.0 = !coop.0;
+        tracing::info!(coop = coop.0, "Co-op toggled");
+    }
+    if !coop.0 {
+        for id in q_second.iter() {
+            cmd.delete(id);
+        }
+        return;
+    }
+    if !q_second.is_empty() || !q_game_over.is_empty() {
+        return;
+    }
+    let Some(first) = q_first.iter().next() else {
+        return;
+    };
+    spawn_player(
+        cmd.spawn().insert(SecondPlayer),
+        assets.player.clone(),
+        first.0.pos + SECOND_PLAYER_OFFSET,
+        PLAYER_TWO_KEYS,
+    );
+}
+
+fn setup_sprite_sheets(
+    graphics_state: Res<GraphicsState>,
+    mut assets: ResMut<assets::Assets<SpriteSheet>>,
+    mut sprites: ResMut<Sprites>,
+) {
+    // set up first so the other sheets can fall back to it
+    sprites.placeholder = placeholder_sheet(
+        &graphics_state,
+        Vec2::splat(PLACEHOLDER_SIZE),
+        1,
+        "placeholder",
+        &mut assets,
+    );
+    // a sheet that doesn't load is drawn as the placeholder, sheets of several frames get one
+    // laid out like them so every frame lands on the checkerboard
+    let mut load = |bytes: &[u8], box_size: Vec2, num_cols: u32, label: &str| {
+        let loaded = load_sprite_sheet(
+            &graphics_state,
+            bytes,
+            box_size,
+            num_cols,
+            label,
+            &mut assets,
+        );
+        or_placeholder(loaded, label, num_cols, &sprites.placeholder, || {
+            placeholder_sheet(&graphics_state, box_size, num_cols, label, &mut assets)
+        })
+    };
+    *sprites = Sprites {
+        bullet_sheet: load(
+            include_bytes!("../assets/bullet.png"),
+            Vec2::splat(128.0),
+            2,
+            "bullet",
+        ),
+        tracer_sheet: load(
+            include_bytes!("../assets/tracer.png"),
+            Vec2::new(16.0, 64.0),
+            1,
+            "tracer",
+        ),
+        bullet_n: 2,
+        missile_sheet: load(
+            include_bytes!("../assets/missile.png"),
+            Vec2::splat(32.0),
+            1,
+            "missile",
+        ),
+        laser_sheet: load(
+            include_bytes!("../assets/laser.png"),
+            Vec2::splat(4.0),
+            1,
+            "laser",
+        ),
+        ufo_sheet: load(
+            include_bytes!("../assets/ufo.png"),
+            Vec2::splat(32.0),
+            1,
+            "ufo",
+        ),
+        enemy_bullet_sheet: load(
+            include_bytes!("../assets/enemy_bullet.png"),
+            Vec2::splat(16.0),
+            1,
+            "enemy_bullet",
+        ),
+        arrow_sheet: load(
+            include_bytes!("../assets/arrow.png"),
+            Vec2::splat(16.0),
+            1,
+            "arrow",
+        ),
+        weak_point_sheet: load(
+            include_bytes!("../assets/weak_point.png"),
+            Vec2::splat(16.0),
+            1,
+            "weak_point",
+        ),
+        warning_sheet: load(
+            include_bytes!("../assets/warning.png"),
+            Vec2::splat(16.0),
+            2,
+            "warning",
+        ),
+        asteroid_sheet: load(
+            include_bytes!("../assets/asteroids.png"),
+            Vec2::splat(128.0),
+            AsteroidKind::Rocky.stats().variants,
+            "asteroids",
+        ),
+        asteroid_icy_sheet: load(
+            include_bytes!("../assets/asteroids_icy.png"),
+            Vec2::splat(128.0),
+            AsteroidKind::Icy.stats().variants,
+            "asteroids_icy",
+        ),
+        asteroid_metal_sheet: load(
+            include_bytes!("../assets/asteroids_metal.png"),
+            Vec2::splat(128.0),
+            AsteroidKind::Metallic.stats().variants,
+            "asteroids_metal",
+        ),
+        powerup_sheet: load(
+            include_bytes!("../assets/powerups.png"),
+            Vec2::splat(32.0),
+            7,
+            "powerups",
+        ),
+        shield_sheet: load(
+            include_bytes!("../assets/shield.png"),
+            Vec2::splat(64.0),
+            4,
+            "shield",
+        ),
+        shield_n: 4,
+        explosion_sheet: load(
+            include_bytes!("../assets/explosion.png"),
+            Vec2::splat(64.0),
+            4,
+            "explosion",
+        ),
+        explosion_n: 4,
+        fade_sheet: load(
+            include_bytes!("../assets/fade.png"),
+            Vec2::splat(16.0),
+            8,
+            "fade",
+        ),
+        fade_n: 8,
+        collider_outline: load(
+            include_bytes!("../assets/collider_outline.png"),
+            Vec2::splat(32.0),
+            2,
+            "collider_outline",
+        ),
+        line: load(
+            include_bytes!("../assets/line.png"),
+            Vec2::splat(4.0),
+            2,
+            "line",
+        ),
+        gravity_well_sheet: load(
+            include_bytes!("../assets/gravity_well.png"),
+            Vec2::splat(64.0),
+            1,
+            "gravity_well",
+        ),
+        game_over_sheet: load(
+            include_bytes!("../assets/game_over.png"),
+            Vec2::new(128.0, 32.0),
+            1,
+            "game_over",
+        ),
+        player: load(
+            include_bytes!("../assets/ship.png"),
+            Vec2::new(32.0, 45.0),
+            1,
+            "ship",
+        ),
+        thrust_sheet: load(
+            include_bytes!("../assets/flame.png"),
+            Vec2::splat(32.0),
+            4,
+            "flame",
+        ),
+        thrust_n: 4,
+        digits: load(
+            include_bytes!("../assets/digits.png"),
+            Vec2::splat(16.0),
+            10,
+            "digits",
+        ),
+        letters: load(
+            include_bytes!("../assets/letters.png"),
+            Vec2::splat(16.0),
+            27,
+            "letters",
+        ),
+        wave_banner: load(
+            include_bytes!("../assets/wave.png"),
+            Vec2::new(64.0, 16.0),
+            1,
+            "wave",
+        ),
+        placeholder: sprites.placeholder.clone(),
+    };
+}
+
+fn load_sprite_sheet(
+    graphics_state: &GraphicsState,
+    bytes: &[u8],
+    box_size: Vec2,
+    num_cols: u32,
+    label: &str,
     assets: &mut Assets<SpriteSheet>,
-) -> Handle<SpriteSheet> {
+) -> anyhow::Result<Handle<SpriteSheet>> {
     let texture = renderer::texture::Texture::from_bytes(
         graphics_state.device(),
         graphics_state.queue(),
         bytes,
         label,
-    )
-    .unwrap();
+    )?;
     let sprite_sheet = SpriteSheet::from_texture(Vec2::ZERO, box_size, num_cols, texture);
 
-    assets.insert(sprite_sheet)
+    Ok(assets.insert(sprite_sheet))
+}
+
+/// The `loaded` sheet, or a placeholder if it failed: the shared one for single frame sheets, a
+/// `laid_out` one for sheets of `num_cols` frames
+fn or_placeholder<H: Clone>(
+    loaded: anyhow::Result<H>,
+    label: &str,
+    num_cols: u32,
+    placeholder: &H,
+    laid_out: impl FnOnce() -> H,
+) -> H {
+    loaded.unwrap_or_else(|err| {
+        tracing::error!(%err, label, "Failed to load sprite sheet, using the placeholder");
+        if num_cols <= 1 {
+            placeholder.clone()
+        } else {
+            laid_out()
+        }
+    })
+}
+
+/// Checkerboard sheet laid out like the one that failed to load, see
+/// [placeholder::placeholder_png]
+fn placeholder_sheet(
+    graphics_state: &GraphicsState,
+    box_size: Vec2,
+    num_cols: u32,
+    label: &str,
+    assets: &mut Assets<SpriteSheet>,
+) -> Handle<SpriteSheet> {
+    let png = placeholder::placeholder_png(box_size.x as u32 * num_cols.max(1), box_size.y as u32);
+    load_sprite_sheet(graphics_state, &png, box_size, num_cols, label, assets)
+        .expect("the placeholder is a valid PNG")
+}
+
+fn restart_system(
+    q_game_over: Query<&(), (With<GameOver>, WithOut<Cooldown>)>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+    inputs: Res<KeyBoardInputs>,
+    mut score: ResMut<Score>,
+    mut bombs: ResMut<Bombs>,
+    mut missiles: ResMut<Missiles>,
+    mut difficulty: ResMut<Difficulty>,
+    mut wave: ResMut<Wave>,
+    mut wave_countdown: ResMut<WaveCountdown>,
+    mut weapon: ResMut<Weapon>,
+    mut laser: ResMut<Laser>,
+    q_cleanup: Query<EntityId, With<GameEntity>>,
+    q_bullets: Query<EntityId, (With<Pooled>, With<CollisionTag>)>,
+    mut fade: ResMut<ScreenFade>,
+    mut pending: ResMut<PendingRestart>,
+    mut time_scale: ResMut<TimeScale>,
+    name_entry: Res<leaderboard::NameEntry>,
+) {
+    if !pending.0 {
+        if name_entry.is_entering() {
+            // the keys are typing a name
+            return;
+        }
+        // R restarts at any time, Space once the game over screen has been up for a moment
+        pending.0 = inputs.just_released.contains(&VirtualKeyCode::R)
+            || (!q_game_over.is_empty() && inputs.just_released.contains(&VirtualKeyCode::Space));
+        if pending.0 {
+            fade.darken(1.0);
+        }
+        return;
+    }
+    // swap the game out once the screen is black, then fade back in
+    if fade.is_done() {
+        pending.0 = false;
+        fade.fade_to(0.0);
+        reset_game(
+            &mut cmd,
+            &assets,
+            &q_cleanup,
+            &q_bullets,
+            &mut score,
+            &mut bombs,
+            &mut missiles,
+            &mut difficulty,
+            &mut wave,
+            &mut wave_countdown,
+            &mut weapon,
+            &mut laser,
+        );
+        time_scale.0 = 1.0;
+    }
+}
+
+/// Set while the screen fades out for a restart
+struct PendingRestart(pub bool);
+
+/// Opacity of the full screen overlay, 0 is clear and 1 is black
+struct ScreenFade {
+    pub from: f32,
+    pub to: f32,
+    pub elapsed: Duration,
+    pub duration: Duration,
+    /// Length of each fade
+    pub fade_time: Duration,
+}
+
+impl ScreenFade {
+    pub fn new(fade_time: Duration) -> Self {
+        Self {
+            from: 0.0,
+            to: 0.0,
+            elapsed: Duration::ZERO,
+            duration: Duration::ZERO,
+            fade_time,
+        }
+    }
+
+    pub fn alpha(&self) -> f32 {
+        if self.elapsed >= self.duration {
+            return self.to;
+        }
+        let t = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        self.from + (self.to - self.from) * t
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub fn fade_to(&mut self, to: f32) {
+        self.from = self.alpha();
+        self.to = to;
+        self.elapsed = Duration::ZERO;
+        self.duration = self.fade_time;
+    }
+
+    /// Fades to `alpha` unless already heading somewhere darker, so a dim doesn't cut a fade out
+    /// short
+    pub fn darken(&mut self, alpha: f32) {
+        if self.to < alpha {
+            self.fade_to(alpha);
+        }
+    }
+
+    /// Fades to `alpha` unless the screen is being faded out for a restart
+    pub fn lighten(&mut self, alpha: f32) {
+        if self.to < 1.0 && self.to > alpha {
+            self.fade_to(alpha);
+        }
+    }
+}
+
+struct FadeOverlay;
+
+fn screen_fade_system(
+    dt: Res<DeltaTime>,
+    mut fade: ResMut<ScreenFade>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+    mut q_overlay: Query<&mut SpriteInstance, With<FadeOverlay>>,
+) {
+    fade.elapsed = (fade.elapsed + dt.0).min(fade.duration);
+    let index = (fade.alpha().clamp(0.0, 1.0) * (sprites.fade_n - 1) as f32).round() as u32;
+    if q_overlay.is_empty() {
+        let Some(camera_id) = q_camera.single() else {
+            return;
+        };
+        // not a GameEntity, the overlay outlives restarts
+        transform::spawn_child(camera_id, &mut cmd, |cmd| {
+            cmd.insert_bundle(transform_bundle(Transform {
+                pos: Vec3::new(0.0, 0.0, layers::OVERLAY),
+                scale: Vec3::splat(200.0),
+                ..Default::default()
+            }))
+            .insert_bundle(sprite_sheet_bundle(
+                sprites.fade_sheet.clone(),
+                SpriteInstance { index, flip: false },
+            ))
+            .insert(FadeOverlay);
+        });
+    }
+    for sprite in q_overlay.iter_mut() {
+        sprite.index = index;
+    }
+}
+
+/// Clears the current game and spawns a fresh player, new per-game state should be reset here
+fn reset_game(
+    cmd: &mut Commands,
+    assets: &Sprites,
+    q_cleanup: &Query<EntityId, With<GameEntity>>,
+    q_bullets: &Query<EntityId, (With<Pooled>, With<CollisionTag>)>,
+    score: &mut Score,
+    bombs: &mut Bombs,
+    missiles: &mut Missiles,
+    difficulty: &mut Difficulty,
+    wave: &mut Wave,
+    wave_countdown: &mut WaveCountdown,
+    weapon: &mut Weapon,
+    laser: &mut Laser,
+) {
+    for id in q_cleanup.iter() {
+        cmd.delete(id);
+    }
+    for id in q_bullets.iter() {
+        deactivate_bullet(cmd.entity(id));
+    }
+    // the fresh ship comes with cold guns, see spawn_player
+    spawn_player(
+        cmd.spawn(),
+        assets.player.clone(),
+        Vec3::new(0.0, 0.0, layers::PLAYER),
+        PLAYER_ONE_KEYS,
+    );
+    reset_game_state(
+        score,
+        bombs,
+        missiles,
+        difficulty,
+        wave,
+        wave_countdown,
+        weapon,
+        laser,
+    );
+}
+
+/// The resource half of [reset_game], puts the per-game state back to how a game starts
+fn reset_game_state(
+    score: &mut Score,
+    bombs: &mut Bombs,
+    missiles: &mut Missiles,
+    difficulty: &mut Difficulty,
+    wave: &mut Wave,
+    wave_countdown: &mut WaveCountdown,
+    weapon: &mut Weapon,
+    laser: &mut Laser,
+) {
+    score.score.0 = 0;
+    bombs.0 = INITIAL_BOMBS;
+    missiles.0 = 0;
+    difficulty.reset();
+    wave.0 = 0;
+    wave_countdown.0 = None;
+    *weapon = Weapon::default();
+    *laser = Laser::default();
+}
+
+fn render_score(
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+    q_scores: Query<EntityId, With<ScoreDigit>>,
+    mut score: ResMut<Score>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+) {
+    if score.score.0 == score.rendered_score {
+        return;
+    }
+    for id in q_scores.iter() {
+        cmd.delete(id);
+    }
+    score.rendered_score = score.score.0;
+    let Some(camera_id) = q_camera.single() else {
+        return;
+    };
+
+    spawn_digits(
+        &mut cmd,
+        Some(camera_id),
+        &assets,
+        score.score.0,
+        Vec3::new(-45.0, -45.0, layers::UI),
+        1.0,
+        |cmd| {
+            cmd.insert(ScoreDigit);
+        },
+    );
+}
+
+/// Where the arrow for a threat `offset` away from the camera goes, `None` while it's in view
+///
+/// `half_extent` is half the size of the view, [VIEW_HALF_EXTENT] scaled by the camera zoom.
+fn indicator_placement(offset: Vec2, half_extent: Vec2) -> Option<(Vec2, glam::Quat)> {
+    if offset.x.abs() <= half_extent.x && offset.y.abs() <= half_extent.y {
+        return None;
+    }
+    // slide the arrow from the center towards the threat until it meets the edge
+    let bound = half_extent - Vec2::splat(INDICATOR_MARGIN);
+    let t = (bound.x / offset.x.abs()).min(bound.y / offset.y.abs());
+    let angle = offset.y.atan2(offset.x);
+    Some((
+        offset * t,
+        glam::Quat::from_rotation_z(angle - std::f32::consts::FRAC_PI_2),
+    ))
+}
+
+/// Keeps an arrow at the edge of the view for every [Threat] outside of it
+fn offscreen_indicator_system(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_camera: Query<(EntityId, &GlobalTransform, &PlayerCamera)>,
+    q_threats: Query<(EntityId, &GlobalTransform), With<Threat>>,
+    mut q_indicators: Query<(EntityId, &OffscreenIndicator, &mut Transform)>,
+) {
+    let Some((camera_id, camera_tr, camera)) = q_camera.single() else {
+        return;
+    };
+    // the arrows sit on the camera, the further back it is the bigger they have to be
+    let half_extent = VIEW_HALF_EXTENT * camera.zoom;
+    let scale = Vec3::splat(0.8 * camera.zoom);
+    let camera = camera_tr.0.pos.truncate();
+    let mut shown: SmallVec<[EntityId; 4]> = SmallVec::new();
+    for (id, indicator, tr) in q_indicators.iter_mut() {
+        // the threat came into view or is gone
+        let Some((pos, rot)) = q_threats.fetch(indicator.0).and_then(|(_, threat)| {
+            indicator_placement(threat.0.pos.truncate() - camera, half_extent)
+        }) else {
+            cmd.delete(id);
+            continue;
+        };
+        tr.pos = pos.extend(layers::INDICATOR);
+        tr.rot = rot;
+        tr.scale = scale;
+        shown.push(indicator.0);
+    }
+    for (threat_id, threat) in q_threats.iter() {
+        if shown.contains(&threat_id) {
+            continue;
+        }
+        let Some((pos, rot)) = indicator_placement(threat.0.pos.truncate() - camera, half_extent)
+        else {
+            continue;
+        };
+        // not a GameEntity, the arrow goes the frame after its threat does
+        transform::spawn_child(camera_id, &mut cmd, |cmd| {
+            cmd.insert_bundle(transform_bundle(Transform {
+                pos: pos.extend(layers::INDICATOR),
+                rot,
+                scale,
+            }))
+            .insert_bundle(sprite_sheet_bundle(sprites.arrow_sheet.clone(), None))
+            .insert(OffscreenIndicator(threat_id));
+        });
+    }
+}
+
+fn render_bombs(
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+    q_digits: Query<EntityId, With<BombDigit>>,
+    bombs: Res<Bombs>,
+    mut rendered: ResMut<RenderedBombs>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+) {
+    if rendered.0 == Some(bombs.0) {
+        return;
+    }
+    for id in q_digits.iter() {
+        cmd.delete(id);
+    }
+    rendered.0 = Some(bombs.0);
+    let Some(camera_id) = q_camera.single() else {
+        return;
+    };
+    spawn_digits(
+        &mut cmd,
+        Some(camera_id),
+        &assets,
+        bombs.0 as u64,
+        Vec3::new(-45.0, -43.0, layers::UI),
+        1.0,
+        |cmd| {
+            cmd.insert(BombDigit);
+        },
+    );
+}
+
+fn render_missiles(
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+    q_digits: Query<EntityId, With<MissileDigit>>,
+    missiles: Res<Missiles>,
+    mut rendered: ResMut<RenderedMissiles>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+) {
+    if rendered.0 == Some(missiles.0) {
+        return;
+    }
+    for id in q_digits.iter() {
+        cmd.delete(id);
+    }
+    rendered.0 = Some(missiles.0);
+    let Some(camera_id) = q_camera.single() else {
+        return;
+    };
+    spawn_digits(
+        &mut cmd,
+        Some(camera_id),
+        &assets,
+        missiles.0 as u64,
+        Vec3::new(-45.0, -41.5, layers::UI),
+        1.0,
+        |cmd| {
+            cmd.insert(MissileDigit);
+        },
+    );
+}
+
+fn render_asteroid_count(
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+    q_digits: Query<EntityId, With<AsteroidCountDigit>>,
+    q_asteroids: Query<&(), With<Asteroid>>,
+    mut hud: ResMut<AsteroidCountHud>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+) {
+    let count = q_asteroids.count();
+    if hud.rendered_count == Some(count) {
+        return;
+    }
+    for id in q_digits.iter() {
+        cmd.delete(id);
+    }
+    hud.rendered_count = Some(count);
+    let Some(camera_id) = q_camera.single() else {
+        return;
+    };
+    spawn_digits(
+        &mut cmd,
+        Some(camera_id),
+        &assets,
+        count as u64,
+        hud.position,
+        1.0,
+        |cmd| {
+            cmd.insert(AsteroidCountDigit);
+        },
+    );
+}
+
+/// Frame rate counter in the corner of the screen, toggled with F3
+struct ShowFps(pub bool);
+
+struct FpsCounter {
+    /// Exponential moving average of the frame rate
+    pub smoothed: f32,
+    pub refresh: Timer,
+}
+
+struct FpsDigit;
+
+fn fps_counter_system(
+    dt: Res<DeltaTime>,
+    inputs: Res<KeyBoardInputs>,
+    mut show: ResMut<ShowFps>,
+    mut fps: ResMut<FpsCounter>,
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+    q_digits: Query<EntityId, With<FpsDigit>>,
+    mut cmd: Commands,
+    assets: Res<Sprites>,
+) {
+    if inputs.just_released.contains(&VirtualKeyCode::F3) {
+        show.0 = !show.0;
+    }
+    if !show.0 {
+        for id in q_digits.iter() {
+            cmd.delete(id);
+        }
+        return;
+    }
+    let frame_time = dt.0.as_secs_f32();
+    if frame_time > 0.0 {
+        fps.smoothed += (1.0 / frame_time - fps.smoothed) * FPS_SMOOTHING;
+    }
+    fps.refresh.update(dt.0);
+    if !fps.refresh.just_finished() && !q_digits.is_empty() {
+        return;
+    }
+    for id in q_digits.iter() {
+        cmd.delete(id);
+    }
+    let Some(camera_id) = q_camera.single() else {
+        return;
+    };
+    spawn_digits(
+        &mut cmd,
+        Some(camera_id),
+        &assets,
+        fps.smoothed.round() as u64,
+        Vec3::new(-45.0, 45.0, layers::UI),
+        1.0,
+        |cmd| {
+            cmd.insert(FpsDigit);
+        },
+    );
+}
+
+/// Draws the outline of every collider, toggled with F1
+struct DebugDraw(pub bool);
+
+struct ColliderOutline;
+
+/// Outlines are not tied to a collider, each frame the n-th outline is moved onto the n-th
+/// collider and the surplus is deleted
+fn debug_draw_colliders_system(
+    inputs: Res<KeyBoardInputs>,
+    mut debug: ResMut<DebugDraw>,
+    collisions: Res<Collisions>,
+    sprites: Res<Sprites>,
+    mut cmd: Commands,
+    q_colliders: Query<(EntityId, &GlobalAABB), With<CollisionTag>>,
+    mut q_outlines: Query<(EntityId, &mut Transform, &mut SpriteInstance), With<ColliderOutline>>,
+) {
+    if inputs.just_released.contains(&VirtualKeyCode::F1) {
+        debug.0 = !debug.0;
+    }
+    let mut outlines = q_outlines.iter_mut();
+    if debug.0 {
+        let mut colliding: Vec<EntityId> = collisions
+            .iter()
+            .flat_map(|e| [e.entity_1, e.entity_2])
+            .collect();
+        colliding.sort_unstable();
+        for (id, aabb) in q_colliders.iter() {
+            let tr = Transform {
+                pos: aabb.0.center().extend(layers::DEBUG),
+                scale: aabb.0.size().extend(1.0),
+                ..Default::default()
+            };
+            let index = colliding.binary_search(&id).is_ok() as u32;
+            match outlines.next() {
+                Some((_, outline, sprite)) => {
+                    *outline = tr;
+                    sprite.index = index;
+                }
+                None => {
+                    cmd.spawn()
+                        .insert_bundle(transform_bundle(tr))
+                        .insert_bundle(sprite_sheet_bundle(
+                            sprites.collider_outline.clone(),
+                            SpriteInstance { index, flip: false },
+                        ))
+                        .insert(ColliderOutline);
+                }
+            }
+        }
+    }
+    for (id, _, _) in outlines {
+        cmd.delete(id);
+    }
+}
+
+/// Draws the edge of the map and a grid over it, toggled with F2
+struct ShowBounds {
+    pub enabled: bool,
+    /// Distance between grid lines, no grid if zero
+    pub grid_spacing: f32,
+    /// Map radius and grid spacing of the lines currently on screen
+    pub rendered: Option<(f32, f32)>,
+}
+
+struct BoundsLine;
+
+fn show_bounds_system(
+    inputs: Res<KeyBoardInputs>,
+    mut bounds: ResMut<ShowBounds>,
+    sprites: Res<Sprites>,
+    mut cmd: Commands,
+    q_lines: Query<EntityId, With<BoundsLine>>,
+    map: Res<MapConfig>,
+) {
+    if inputs.just_released.contains(&VirtualKeyCode::F2) {
+        bounds.enabled = !bounds.enabled;
+    }
+    let wanted = bounds.enabled.then_some((map.radius, bounds.grid_spacing));
+    if bounds.rendered == wanted {
+        return;
+    }
+    bounds.rendered = wanted;
+    for id in q_lines.iter() {
+        cmd.delete(id);
+    }
+    let Some((radius, spacing)) = wanted else {
+        return;
+    };
+
+    // lines live in world space, not under the camera, so they scroll with the map
+    let mut line = |pos: Vec2, size: Vec2, index: u32| {
+        cmd.spawn()
+            .insert_bundle(transform_bundle(Transform {
+                pos: pos.extend(layers::GRID),
+                scale: size.extend(1.0),
+                ..Default::default()
+            }))
+            .insert_bundle(sprite_sheet_bundle(
+                sprites.line.clone(),
+                SpriteInstance { index, flip: false },
+            ))
+            .insert(BoundsLine);
+    };
+    let length = 2.0 * radius;
+    if spacing > 0.0 {
+        let mut d = -radius + spacing;
+        while d < radius {
+            line(Vec2::new(d, 0.0), Vec2::new(0.05, length), 1);
+            line(Vec2::new(0.0, d), Vec2::new(length, 0.05), 1);
+            d += spacing;
+        }
+    }
+    for edge in [-radius, radius] {
+        line(Vec2::new(edge, 0.0), Vec2::new(0.1, length), 0);
+        line(Vec2::new(0.0, edge), Vec2::new(length, 0.1), 0);
+    }
+}
+
+/// Letters drawn over objects that otherwise only differ in color, cycled with F4
+///
+/// Sprites can't be tinted, so instead of remapping the colors every preset adds markers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ColorblindMode {
+    #[default]
+    Off,
+    /// Mark the kind of each power-up
+    PowerUps,
+    /// Mark the kind of asteroids as well
+    All,
+}
+
+impl ColorblindMode {
+    pub fn next(self) -> Self {
+        match self {
+            ColorblindMode::Off => ColorblindMode::PowerUps,
+            ColorblindMode::PowerUps => ColorblindMode::All,
+            ColorblindMode::All => ColorblindMode::Off,
+        }
+    }
+}
+
+impl PowerUpKind {
+    fn marker(self) -> u8 {
+        match self {
+            PowerUpKind::Shield => b'S',
+            PowerUpKind::RapidFire => b'R',
+            PowerUpKind::SpreadShot => b'W',
+            PowerUpKind::Bomb => b'B',
+            PowerUpKind::Missiles => b'H',
+            PowerUpKind::Clock => b'C',
+            PowerUpKind::Piercing => b'P',
+        }
+    }
+}
+
+impl AsteroidKind {
+    fn marker(self) -> u8 {
+        match self {
+            AsteroidKind::Rocky => b'R',
+            AsteroidKind::Icy => b'I',
+            AsteroidKind::Metallic => b'M',
+        }
+    }
+}
+
+/// The [ColorblindMode] markers were spawned for
+struct RenderedColorblindMode(pub ColorblindMode);
+
+/// Letter over a power-up or an asteroid, child of the entity it marks
+struct ClassMarker(pub EntityId);
+
+/// The entity has its [ClassMarker] attached
+struct HasClassMarker;
+
+/// Size of the markers in world units, whatever the size of the marked object
+const MARKER_SIZE: f32 = 0.6;
+
+fn palette_system(
+    inputs: Res<KeyBoardInputs>,
+    mut mode: ResMut<ColorblindMode>,
+    mut rendered: ResMut<RenderedColorblindMode>,
+    sprites: Res<Sprites>,
+    mut cmd: Commands,
+    q_power_ups: Query<(EntityId, &PowerUp), WithOut<HasClassMarker>>,
+    q_asteroids: Query<(EntityId, &AsteroidKind), WithOut<HasClassMarker>>,
+    q_marked: Query<EntityId, With<HasClassMarker>>,
+    q_parent: Query<&GlobalTransform, With<HasClassMarker>>,
+    mut q_markers: Query<(EntityId, &ClassMarker, &mut Transform)>,
+) {
+    if inputs.just_released.contains(&VirtualKeyCode::F4) {
+        *mode = mode.next();
+    }
+    if rendered.0 != *mode {
+        // start over, the next frame marks what the new mode covers
+        rendered.0 = *mode;
+        for (id, _, _) in q_markers.iter() {
+            cmd.delete(id);
+        }
+        for id in q_marked.iter() {
+            cmd.entity(id).remove::<HasClassMarker>();
+        }
+        return;
+    }
+
+    let mut mark = |id: EntityId, letter: u8| {
+        transform::spawn_child(id, &mut cmd, |cmd| {
+            cmd.insert_bundle(transform_bundle(Transform::from_scale(Vec3::ZERO)))
+                .insert_bundle(sprite_sheet_bundle(
+                    sprites.letters.clone(),
+                    SpriteInstance {
+                        index: glyph_index(letter).unwrap_or_default(),
+                        flip: true,
+                    },
+                ))
+                .insert(ClassMarker(id));
+        });
+        cmd.entity(id).insert(HasClassMarker);
+    };
+    if *mode != ColorblindMode::Off {
+        for (id, power_up) in q_power_ups.iter() {
+            mark(id, power_up.0.marker());
+        }
+    }
+    if *mode == ColorblindMode::All {
+        for (id, kind) in q_asteroids.iter() {
+            mark(id, kind.marker());
+        }
+    }
+
+    for (id, marker, tr) in q_markers.iter_mut() {
+        let Some(parent) = q_parent.fetch(marker.0) else {
+            cmd.delete(id);
+            continue;
+        };
+        let parent = &parent.0;
+        if parent.scale.x <= f32::EPSILON {
+            tr.scale = Vec3::ZERO;
+            continue;
+        }
+        // upright and the same size on every object, undo the rotation and scale of the parent
+        tr.rot = parent.rot.inverse();
+        tr.pos = Vec3::new(0.0, 0.0, layers::MARKER);
+        tr.scale = Vec3::splat(MARKER_SIZE / parent.scale.x);
+    }
+}
+
+/// One point of health over a damaged asteroid, child of the asteroid
+struct HealthPip {
+    asteroid: EntityId,
+    /// Solid while the asteroid has more health than this, faint once it's lost
+    index: u32,
+}
+
+/// The asteroid has its [HealthPip]s attached
+struct HasHealthBar;
+
+/// Size of a pip in world units
+const PIP_SIZE: f32 = 0.2;
+/// Distance between the centers of neighbouring pips
+const PIP_SPACING: f32 = 0.3;
+
+/// Shows the remaining health over asteroids that took a hit but survived
+///
+/// Undamaged asteroids get nothing, the pips are only spawned on the first hit.
+fn health_bar_system(
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_damaged: Query<(EntityId, &Health, &AsteroidKind), WithOut<HasHealthBar>>,
+    q_parent: Query<(&GlobalTransform, &Health, &AsteroidKind), With<HasHealthBar>>,
+    mut q_pips: Query<(EntityId, &HealthPip, &mut Transform, &mut SpriteInstance)>,
+) {
+    for (id, health, kind) in q_damaged.iter() {
+        let max = kind.stats().health;
+        if health.0 >= max {
+            continue;
+        }
+        for index in 0..max {
+            transform::spawn_child(id, &mut cmd, |cmd| {
+                cmd.insert_bundle(transform_bundle(Transform::from_scale(Vec3::ZERO)))
+                    .insert_bundle(sprite_sheet_bundle(
+                        sprites.line.clone(),
+                        SpriteInstance {
+                            index: 0,
+                            flip: false,
+                        },
+                    ))
+                    .insert_bundle((
+                        HealthPip {
+                            asteroid: id,
+                            index,
+                        },
+                        GameEntity,
+                    ));
+            });
+        }
+        cmd.entity(id).insert(HasHealthBar);
+    }
+
+    for (id, pip, tr, sprite) in q_pips.iter_mut() {
+        let Some((parent, health, kind)) = q_parent.fetch(pip.asteroid) else {
+            cmd.delete(id);
+            continue;
+        };
+        let parent = &parent.0;
+        if parent.scale.x <= f32::EPSILON {
+            tr.scale = Vec3::ZERO;
+            continue;
+        }
+        let max = kind.stats().health;
+        sprite.index = if pip.index < health.0 { 0 } else { 1 };
+        // a row over the top edge of the asteroid, in world space whatever the asteroid's spin,
+        // then taken back into the asteroid's space
+        let x = (pip.index as f32 - (max as f32 - 1.0) * 0.5) * PIP_SPACING;
+        let y = kind.stats().size * parent.scale.x * 0.5 + PIP_SPACING;
+        let inv_rot = parent.rot.inverse();
+        tr.rot = inv_rot;
+        tr.pos = inv_rot * Vec3::new(x, y, layers::HEALTH_PIP) / parent.scale.x;
+        tr.scale = Vec3::splat(PIP_SIZE / parent.scale.x);
+    }
+}
+
+/// Heat gauge under a ship, a faint track with the heat of the guns filled in over it
+struct HeatGauge {
+    ship: EntityId,
+    /// The filled part, the track otherwise
+    fill: bool,
 }
 
-fn restart_system(
-    q_game_over: Query<&(), (With<GameOver>, WithOut<Cooldown>)>,
+/// The ship got its [HeatGauge]
+struct HasHeatGauge;
+
+const HEAT_GAUGE_WIDTH: f32 = 1.2;
+const HEAT_GAUGE_HEIGHT: f32 = 0.12;
+/// Distance of the gauge below the center of the ship
+const HEAT_GAUGE_OFFSET: f32 = 1.0;
+
+/// Shows the heat of the guns under each ship, the fill goes faint while they're overheated
+///
+/// Nothing is drawn while the guns are cold.
+fn heat_gauge_system(
     mut cmd: Commands,
-    assets: Res<Sprites>,
-    inputs: Res<KeyBoardInputs>,
-    mut score: ResMut<Score>,
-    q_cleanup: Query<EntityId, With<GameEntity>>,
+    sprites: Res<Sprites>,
+    config: Res<WeaponHeatConfig>,
+    q_new: Query<EntityId, (With<Player>, With<WeaponHeat>, WithOut<HasHeatGauge>)>,
+    q_ship: Query<(&GlobalTransform, &WeaponHeat, Option<&Overheated>), With<Player>>,
+    mut q_gauge: Query<(EntityId, &HeatGauge, &mut Transform, &mut SpriteInstance)>,
 ) {
-    if q_game_over.single().is_some() {
-        for key in inputs.just_released.iter() {
-            if let VirtualKeyCode::Space = key {
-                for id in q_cleanup.iter() {
-                    cmd.delete(id);
-                }
-                spawn_player(cmd.spawn(), assets.player.clone());
-                score.score.0 = 0;
-            }
+    for id in q_new.iter() {
+        for fill in [false, true] {
+            transform::spawn_child(id, &mut cmd, |cmd| {
+                cmd.insert_bundle(transform_bundle(Transform::from_scale(Vec3::ZERO)))
+                    .insert_bundle(sprite_sheet_bundle(
+                        sprites.line.clone(),
+                        SpriteInstance {
+                            index: 1,
+                            flip: false,
+                        },
+                    ))
+                    .insert_bundle((HeatGauge { ship: id, fill }, GameEntity));
+            });
+        }
+        cmd.entity(id).insert(HasHeatGauge);
+    }
+
+    for (id, gauge, tr, sprite) in q_gauge.iter_mut() {
+        // wrecks lose their gauge
+        let Some((ship, heat, overheated)) = q_ship.fetch(gauge.ship) else {
+            cmd.delete(id);
+            continue;
+        };
+        let ship = &ship.0;
+        if heat.0 <= 0.0 || ship.scale.x <= f32::EPSILON {
+            tr.scale = Vec3::ZERO;
+            continue;
         }
+        let (width, z) = if gauge.fill {
+            let t = (heat.0 / config.max).clamp(0.0, 1.0);
+            (HEAT_GAUGE_WIDTH * t, layers::HEAT_GAUGE_FILL)
+        } else {
+            (HEAT_GAUGE_WIDTH, layers::HEAT_GAUGE)
+        };
+        sprite.index = if gauge.fill && overheated.is_none() {
+            0
+        } else {
+            1
+        };
+        // level under the ship whatever its heading, the fill grows from the left end of the
+        // track
+        let x = (width - HEAT_GAUGE_WIDTH) * 0.5;
+        let inv_rot = ship.rot.inverse();
+        tr.rot = inv_rot;
+        tr.pos = inv_rot * Vec3::new(x, -HEAT_GAUGE_OFFSET, z) / ship.scale.x;
+        tr.scale = Vec3::new(width, HEAT_GAUGE_HEIGHT, 1.0) / ship.scale.x;
     }
 }
 
-fn render_score(
-    q_camera: Query<EntityId, With<PlayerCamera>>,
-    q_scores: Query<EntityId, With<ScoreDigit>>,
-    mut score: ResMut<Score>,
+/// Hyperspace recharge bar under a ship's [HeatGauge], shrinking as the jump [Cooldown] runs out
+struct HyperspaceGauge {
+    ship: EntityId,
+}
+
+/// The ship got its [HyperspaceGauge]
+struct HasHyperspaceGauge;
+
+/// Gap between the [HeatGauge] and the [HyperspaceGauge] under it
+const HYPERSPACE_GAUGE_GAP: f32 = 0.08;
+
+/// Shows the time left until the next hyperspace jump under each ship
+///
+/// Nothing is drawn while the jump is ready.
+fn hyperspace_gauge_system(
     mut cmd: Commands,
-    assets: Res<Sprites>,
+    sprites: Res<Sprites>,
+    q_new: Query<EntityId, (With<Player>, WithOut<HasHyperspaceGauge>)>,
+    q_ship: Query<(&GlobalTransform, Option<&Cooldown>), With<Player>>,
+    mut q_gauge: Query<(EntityId, &HyperspaceGauge, &mut Transform)>,
 ) {
-    if score.score.0 == score.rendered_score {
-        return;
-    }
-    for id in q_scores.iter() {
-        cmd.delete(id);
+    for id in q_new.iter() {
+        transform::spawn_child(id, &mut cmd, |cmd| {
+            cmd.insert_bundle(transform_bundle(Transform::from_scale(Vec3::ZERO)))
+                .insert_bundle(sprite_sheet_bundle(
+                    sprites.line.clone(),
+                    SpriteInstance {
+                        index: 1,
+                        flip: false,
+                    },
+                ))
+                .insert_bundle((HyperspaceGauge { ship: id }, GameEntity));
+        });
+        cmd.entity(id).insert(HasHyperspaceGauge);
     }
-    score.rendered_score = score.score.0;
-    let Some(camera_id) = q_camera.single() else {
-        return;
-    };
 
-    // layouting
-    let mut s = score.score.0;
-    let mut digits = Vec::with_capacity(4); // TODO: smallvec
-    digits.push(s % 10);
-    s /= 10;
-    while s > 0 {
-        digits.push(s % 10);
-        s /= 10;
+    for (id, gauge, tr) in q_gauge.iter_mut() {
+        let Some((ship, cooldown)) = q_ship.fetch(gauge.ship) else {
+            cmd.delete(id);
+            continue;
+        };
+        let ship = &ship.0;
+        let t = cooldown.map(Cooldown::remaining_fraction).unwrap_or(0.0);
+        if t <= 0.0 || ship.scale.x <= f32::EPSILON {
+            tr.scale = Vec3::ZERO;
+            continue;
+        }
+        let width = HEAT_GAUGE_WIDTH * t;
+        let y = -HEAT_GAUGE_OFFSET - HEAT_GAUGE_HEIGHT - HYPERSPACE_GAUGE_GAP;
+        let inv_rot = ship.rot.inverse();
+        tr.rot = inv_rot;
+        tr.pos = inv_rot * Vec3::new((width - HEAT_GAUGE_WIDTH) * 0.5, y, layers::HEAT_GAUGE)
+            / ship.scale.x;
+        tr.scale = Vec3::new(width, HEAT_GAUGE_HEIGHT, 1.0) / ship.scale.x;
     }
-    let mut x_offset = -45.0;
-    for digit in digits {
-        transform::spawn_child(camera_id, &mut cmd, |cmd| {
-            cmd.insert_bundle(transform_bundle(Transform::from_position(Vec3::new(
-                x_offset, -45.0, -5.0,
-            ))))
+}
+
+/// Spawn the digits of `value`, as children of `parent` if given, `extra` is called on each digit
+/// entity
+fn spawn_digits(
+    cmd: &mut Commands,
+    parent: Option<EntityId>,
+    assets: &Sprites,
+    value: u64,
+    origin: Vec3,
+    scale: f32,
+    mut extra: impl FnMut(&mut EntityCommands),
+) {
+    let mut pos = origin;
+    for digit in decimal_digits(value.into()) {
+        let mut spawn = |cmd: &mut EntityCommands| {
+            cmd.insert_bundle(transform_bundle(Transform {
+                pos,
+                scale: Vec3::splat(scale),
+                ..Default::default()
+            }))
             .insert_bundle(sprite_sheet_bundle(
                 assets.digits.clone(),
                 SpriteInstance {
                     index: digit as u32,
                     flip: true,
                 },
-            ))
-            .insert_bundle((ScoreDigit,));
-        });
-        x_offset += 1.0;
+            ));
+            extra(cmd);
+        };
+        match parent {
+            Some(parent) => transform::spawn_child(parent, cmd, spawn),
+            None => spawn(cmd.spawn()),
+        }
+        pos.x += scale;
+    }
+}
+
+/// Decimal digits of `value`, lowest first, zero has the single digit 0
+///
+/// Any `u64` fits the inline buffer, longer values spill to the heap.
+fn decimal_digits(mut value: u128) -> SmallVec<[u8; 20]> {
+    let mut digits = SmallVec::new();
+    loop {
+        digits.push((value % 10) as u8);
+        value /= 10;
+        if value == 0 {
+            return digits;
+        }
+    }
+}
+
+/// Spawn `text` in the font of [Sprites::letters], as children of `parent` if given, `extra` is
+/// called on each glyph entity
+///
+/// Covers A to Z and the underscore, anything else is left as a gap.
+fn spawn_text(
+    cmd: &mut Commands,
+    parent: Option<EntityId>,
+    assets: &Sprites,
+    text: &str,
+    origin: Vec3,
+    scale: f32,
+    mut extra: impl FnMut(&mut EntityCommands),
+) {
+    // layouting, like the digits the last glyph goes at the origin
+    let mut pos = origin;
+    for c in text.bytes().rev() {
+        if let Some(index) = glyph_index(c) {
+            let mut spawn = |cmd: &mut EntityCommands| {
+                cmd.insert_bundle(transform_bundle(Transform {
+                    pos,
+                    scale: Vec3::splat(scale),
+                    ..Default::default()
+                }))
+                .insert_bundle(sprite_sheet_bundle(
+                    assets.letters.clone(),
+                    SpriteInstance { index, flip: true },
+                ));
+                extra(cmd);
+            };
+            match parent {
+                Some(parent) => transform::spawn_child(parent, cmd, spawn),
+                None => spawn(cmd.spawn()),
+            }
+        }
+        pos.x += scale;
+    }
+}
+
+/// Frame of `c` in [Sprites::letters]
+fn glyph_index(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a') as u32),
+        b'_' => Some(26),
+        _ => None,
     }
 }
 
-struct GamePlugin;
+struct GamePlugin {
+    seed: u64,
+    config: GameConfig,
+}
 
 impl Plugin for GamePlugin {
     fn build(self, app: &mut App) {
+        app.stage(Stage::PreUpdate)
+            .add_system(sync_broadphase_system)
+            .add_system(clear_consumed_system);
         app.stage(Stage::Update)
             .add_system(rotator)
             .add_system(sprite_animator)
             .add_system(player_rotation_system)
             .add_system(player_thrust_system)
+            .add_system(ship_bank_system.after(player_rotation_system))
+            .add_system(thrust_flame_system.after(player_thrust_system))
+            .add_system(thrust_trail_system.after(player_thrust_system))
             .add_system(camera_controller.after(player_thrust_system))
-            .add_system(fire_system)
-            .add_system(spawn_asteroids_system)
-            .add_system(wraparound_system)
+            .add_system(coop_system)
+            .add_system(dead_drift_system)
+            .add_system(refresh_bullet_pool)
+            .add_system(fire_system.after(refresh_bullet_pool))
+            .add_system(bullet_tracer_system.after(fire_system))
+            .add_system(hyperspace_system)
+            .add_system(spawn_powerups_system)
+            .add_system(shield_visual_system)
+            .add_system(weapon_upgrade_system)
+            .add_system(weapon_heat_system)
+            .add_system(heat_gauge_system)
+            .add_system(hyperspace_gauge_system)
+            .add_system(bomb_system)
+            .add_system(render_bombs)
+            .add_system(fire_missile_system)
+            .add_system(missile_guidance_system)
+            .add_system(render_missiles)
+            .add_system(render_asteroid_count)
+            .add_system(fps_counter_system)
+            .add_system(show_bounds_system)
+            .add_system(palette_system)
+            .add_system(health_bar_system)
+            .add_system(difficulty_system)
+            .add_system(spawn_asteroids_system.after(difficulty_system))
+            .add_system(wave_clear_system.after(difficulty_system))
+            .add_system(wave_system.after(wave_clear_system))
+            .add_system(wraparound_system.after(move_system))
+            .add_system(wrap_ghost_system.after(wraparound_system))
             .add_system(update_lifetime)
-            .add_system(restart_system)
+            .add_system(screen_fade_system)
+            .add_system(restart_system.after(screen_fade_system))
             .add_system(cooldown_system)
             .add_system(render_score)
-            .add_system(move_system);
+            .add_system(offscreen_indicator_system.after(camera_controller))
+            .add_system(time_scale_system)
+            .add_system(gravity_system)
+            .add_system(move_system.after(time_scale_system).after(gravity_system));
 
-        app.stage(Stage::PostUpdate).add_system(handle_collisions);
+        app.stage(Stage::PostUpdate)
+            .add_system(debug_draw_colliders_system)
+            .add_system(handle_collisions)
+            .add_system(game_over_system.after(handle_collisions))
+            .add_system(pickup_powerup_system)
+            .add_system(missile_hit_system)
+            .add_system(laser_system)
+            .add_system(bounce_system);
 
         app.add_startup_system(setup_sprite_sheets)
             .add_startup_system(setup_player.after(setup_sprite_sheets))
+            .add_startup_system(setup_bullet_pool.after(setup_sprite_sheets))
+            .add_startup_system(setup_gravity_well.after(setup_sprite_sheets))
             .add_startup_system(setup_slash);
 
         app.insert_resource(Score {
@@ -718,13 +4721,1015 @@ impl Plugin for GamePlugin {
             rendered_score: u64::MAX,
         });
         app.insert_resource(Sprites::default());
+        app.insert_resource(Rng(fastrand::Rng::with_seed(self.seed)));
+        app.insert_resource(self.config.ship);
+        app.insert_resource(BulletPool::new(BULLET_POOL_SIZE));
+        app.insert_resource(MaxBullets(MAX_BULLETS));
+        app.insert_resource(PierceCount(PIERCE_COUNT));
+        app.insert_resource(BulletRange::default());
+        app.insert_resource(TimeScale(1.0));
+        // replaced by the tilt plugin on pages that report the tilt
+        app.insert_resource(tilt::TiltControls::default());
+        app.insert_resource(CoOp(false));
+        app.insert_resource(self.config.asteroids);
+        app.insert_resource(SpawnDensity::default());
+        app.insert_resource(MapModifiers {
+            gravity_well: self.config.map.gravity_well,
+        });
+        app.insert_resource(self.config.map);
+        app.insert_resource(Difficulty::new(self.config.difficulty));
+        app.insert_resource(self.config.time_attack);
+        app.insert_resource(self.config.telegraph);
+        app.insert_resource(self.config.weapon_heat);
+        app.insert_resource(Wave(0));
+        app.insert_resource(WaveCountdown(None));
+        app.insert_resource(Bombs(INITIAL_BOMBS));
+        app.insert_resource(ScreenFade::new(FADE_TIME));
+        app.insert_resource(PendingRestart(false));
+        app.insert_resource(RenderedBombs(None));
+        app.insert_resource(Missiles(0));
+        app.insert_resource(RenderedMissiles(None));
+        app.insert_resource(Weapon::default());
+        app.insert_resource(Laser::default());
+        app.insert_resource(Consumed::default());
+        app.insert_resource(ShowFps(false));
+        app.insert_resource(DebugDraw(false));
+        app.insert_resource(ColorblindMode::default());
+        app.insert_resource(RenderedColorblindMode(ColorblindMode::default()));
+        app.insert_resource(ShowBounds {
+            enabled: false,
+            grid_spacing: 5.0,
+            rendered: None,
+        });
+        app.insert_resource(FpsCounter {
+            smoothed: 0.0,
+            refresh: Timer::new(FPS_REFRESH, true),
+        });
+        app.insert_resource(AsteroidCountHud {
+            position: Vec3::new(-45.0, -41.0, layers::UI),
+            rendered_count: None,
+        });
+        app.insert_resource(PowerUpSpawner(Timer::new(POWERUP_INTERVAL, true)));
     }
 }
 
 pub async fn game() {
+    game_with_seed(fastrand::u64(..)).await
+}
+
+/// Run the game with a fixed RNG seed, the same seed produces the same asteroid field
+pub async fn game_with_seed(seed: u64) {
+    game_app(seed).run().await;
+}
+
+/// Run a game of `mode`, endless games get a random seed
+pub async fn game_with_mode(mode: GameMode) {
+    mode_app(mode).run().await;
+}
+
+/// Run the game and record the inputs, the replay is written to `path` every time the game ends
+#[cfg(not(target_family = "wasm"))]
+pub async fn record_game(seed: Option<u64>, path: std::path::PathBuf) {
+    let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+    let mut app = game_app(seed);
+    app.add_plugin(replay::ReplayPlugin::Record { seed, path });
+    app.run().await;
+}
+
+/// Play a recorded game back
+#[cfg(not(target_family = "wasm"))]
+pub async fn play_replay(replay: Replay) {
+    let mut app = game_app(replay.seed);
+    app.add_plugin(replay::ReplayPlugin::Play(replay));
+    app.run().await;
+}
+
+/// Handles shared between the game and the web page hosting it
+#[derive(Debug, Clone, Default)]
+pub struct PageLink {
+    /// On-screen controls, pressed on top of the keyboard
+    pub keys: VirtualKeys,
+    /// Score shown by the page
+    pub score: ScoreFeed,
+    /// Device tilt, turns the ship on top of the keyboard
+    pub tilt: Tilt,
+    /// Picked by the page before the game starts
+    pub mode: GameMode,
+}
+
+/// Run the game wired up to the page around it
+pub async fn game_in_page(link: PageLink) {
+    let mut app = mode_app(link.mode);
+    app.add_plugin(touch::TouchPlugin(link.keys));
+    app.add_plugin(tilt::TiltPlugin {
+        tilt: link.tilt,
+        config: TiltConfig::default(),
+    });
+    app.add_plugin(score_feed::ScoreFeedPlugin(link.score));
+    app.run().await;
+}
+
+fn mode_app(mode: GameMode) -> App {
+    match mode {
+        GameMode::Endless => game_app(fastrand::u64(..)),
+        GameMode::Daily => {
+            let date = daily::UtcDate::today();
+            tracing::info!(?date, "Daily challenge");
+            let mut app = game_app(date.seed());
+            app.add_plugin(daily::DailyPlugin(date));
+            app
+        }
+        GameMode::TimeAttack => {
+            let mut app = game_app(fastrand::u64(..));
+            app.add_plugin(time_attack::TimeAttackPlugin);
+            app
+        }
+    }
+}
+
+fn game_app(seed: u64) -> App {
+    tracing::info!(seed, "Starting game");
     let mut app = App::default();
     app.add_plugin(DefaultPlugins);
-    app.add_plugin(GamePlugin);
-    app.add_plugin(CollisionPlugin);
-    app.run().await;
+    let config = GameConfig::load();
+    let wrap_radius = Some(config.map.radius);
+    let broadphase = config.map.broadphase;
+    #[cfg(not(target_family = "wasm"))]
+    app.add_plugin(config::ConfigReloadPlugin(config.clone()));
+    app.add_plugin(GamePlugin { seed, config });
+    app.add_plugin(leaderboard::LeaderboardPlugin);
+    app.add_plugin(ufo::UfoPlugin);
+    app.add_plugin(boss::BossPlugin);
+    app.add_plugin(telegraph::TelegraphPlugin);
+    app.add_plugin(CollisionPlugin {
+        wrap_radius,
+        broadphase,
+    });
+    app
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities(n: usize) -> Vec<EntityId> {
+        let mut world = brengin::cecs::World::new(n as u32);
+        (0..n).map(|_| world.insert_entity()).collect()
+    }
+
+    #[test]
+    fn decimal_digits_lowest_first() {
+        assert_eq!(decimal_digits(0).as_slice(), &[0]);
+        assert_eq!(decimal_digits(1207).as_slice(), &[7, 0, 2, 1]);
+
+        let max = decimal_digits(u64::MAX.into());
+        assert_eq!(max.len(), 20);
+        assert!(!max.spilled());
+    }
+
+    #[test]
+    fn decimal_digits_spill_past_twenty() {
+        let value: u128 = 1_234_567_890_123_456_789_012_345;
+        let digits = decimal_digits(value);
+        assert!(digits.spilled());
+        let text: String = digits.iter().rev().map(|d| char::from(b'0' + d)).collect();
+        assert_eq!(text, value.to_string());
+        assert_eq!(text.len(), 25);
+    }
+
+    /// Asteroids rolled over `frames` frames of `per_frame` spawns each, around a ship at the
+    /// center
+    fn roll_asteroids(
+        rng: &mut fastrand::Rng,
+        frames: usize,
+        per_frame: usize,
+    ) -> Vec<(AsteroidKind, Transform, Velocity)> {
+        let config = AsteroidConfig::default();
+        let density = SpawnDensity::default();
+        let map = MapConfig::default();
+        let mut occupied = vec![(Vec2::ZERO, SHIP_SPAWN_CLEARANCE)];
+        let mut rolled = Vec::new();
+        for _ in 0..frames {
+            for _ in 0..per_frame {
+                rolled.extend(roll_asteroid(
+                    rng,
+                    &config,
+                    &density,
+                    &mut occupied,
+                    Vec3::ZERO,
+                    1.0,
+                    &map,
+                ));
+            }
+        }
+        rolled
+    }
+
+    #[test]
+    fn same_seed_spawns_same_asteroids() {
+        let spawns = |seed| {
+            roll_asteroids(&mut fastrand::Rng::with_seed(seed), 10, 4)
+                .into_iter()
+                .map(|(kind, tr, vel)| (kind, tr.pos, tr.rot, vel.0))
+                .collect::<Vec<_>>()
+        };
+        let first = spawns(42);
+        assert!(!first.is_empty());
+        assert_eq!(first, spawns(42));
+        assert_ne!(first, spawns(43));
+    }
+
+    #[test]
+    fn asteroids_spawn_heading_in_every_direction() {
+        let mut rng = fastrand::Rng::with_seed(11);
+        let config = AsteroidConfig::default();
+        let density = SpawnDensity::default();
+        let map = MapConfig::default();
+        const SAMPLES: usize = 4000;
+        let mut octants = [0usize; 8];
+        let mut sum = Vec2::ZERO;
+        for _ in 0..SAMPLES {
+            let mut occupied = Vec::new();
+            let (_, _, vel) = roll_asteroid(
+                &mut rng,
+                &config,
+                &density,
+                &mut occupied,
+                Vec3::ZERO,
+                1.0,
+                &map,
+            )
+            .unwrap();
+            let dir = vel.0.normalize();
+            sum += dir;
+            let angle = dir.y.atan2(dir.x).rem_euclid(std::f32::consts::TAU);
+            octants[(angle / std::f32::consts::FRAC_PI_4) as usize % 8] += 1;
+        }
+        let expected = SAMPLES / 8;
+        for count in octants {
+            assert!(count.abs_diff(expected) < expected / 5, "{octants:?}");
+        }
+        assert!((sum / SAMPLES as f32).length() < 0.05, "{sum}");
+    }
+
+    /// Spawns around a player at the center and one off to the side, returning how many landed
+    /// within half the falloff of them
+    fn spawns_near_player(density: &SpawnDensity) -> usize {
+        let mut rng = fastrand::Rng::with_seed(3);
+        let config = AsteroidConfig::default();
+        let map = MapConfig::default();
+        let mut near = 0;
+        for i in 0..2000 {
+            let player = if i % 2 == 0 {
+                Vec3::ZERO
+            } else {
+                Vec3::new(10.0, -5.0, 0.0)
+            };
+            let mut occupied = vec![(player.truncate(), SHIP_SPAWN_CLEARANCE)];
+            let Some((kind, tr, _)) =
+                roll_asteroid(&mut rng, &config, density, &mut occupied, player, 1.0, &map)
+            else {
+                continue;
+            };
+            let distance = tr.pos.truncate().distance(player.truncate());
+            assert!(distance >= SHIP_SPAWN_CLEARANCE + kind.stats().size * 0.5);
+            if distance < MAP_RADIUS * 0.5 {
+                near += 1;
+            }
+        }
+        near
+    }
+
+    #[test]
+    fn indicators_point_from_the_view_edge() {
+        let half = VIEW_HALF_EXTENT;
+        assert_eq!(indicator_placement(Vec2::ZERO, half), None);
+        assert_eq!(indicator_placement(half, half), None);
+
+        let (pos, rot) = indicator_placement(Vec2::new(0.0, 50.0), half).unwrap();
+        assert_eq!(pos, Vec2::new(0.0, half.y - INDICATOR_MARGIN));
+        // the arrow sprite points up
+        assert!((rot * Vec3::Y).distance(Vec3::Y) < 1e-5);
+
+        let (pos, rot) = indicator_placement(Vec2::new(-50.0, 0.0), half).unwrap();
+        assert_eq!(pos, Vec2::new(-(half.x - INDICATOR_MARGIN), 0.0));
+        assert!((rot * Vec3::Y).distance(-Vec3::X) < 1e-5);
+
+        // off a corner the arrow stays inside the margin on both axes, on the line to the threat
+        let offset = Vec2::new(60.0, -45.0);
+        let (pos, rot) = indicator_placement(offset, half).unwrap();
+        assert!(pos.x <= half.x - INDICATOR_MARGIN + 1e-4);
+        assert!(pos.y.abs() <= half.y - INDICATOR_MARGIN + 1e-4);
+        assert!(pos.normalize().distance(offset.normalize()) < 1e-5);
+        assert!((rot * Vec3::Y).truncate().distance(offset.normalize()) < 1e-5);
+    }
+
+    #[test]
+    fn screen_fade_eases_between_alphas() {
+        let mut fade = ScreenFade::new(Duration::from_secs(1));
+        assert!(fade.is_done());
+        assert_eq!(fade.alpha(), 0.0);
+
+        fade.fade_to(1.0);
+        assert!(!fade.is_done());
+        fade.elapsed = Duration::from_millis(250);
+        assert_eq!(fade.alpha(), 0.25);
+        // turning back midway starts from where the fade got to
+        fade.fade_to(0.0);
+        assert_eq!(fade.from, 0.25);
+        fade.elapsed = Duration::from_millis(500);
+        assert_eq!(fade.alpha(), 0.125);
+        fade.elapsed = fade.duration;
+        assert!(fade.is_done());
+        assert_eq!(fade.alpha(), 0.0);
+    }
+
+    #[test]
+    fn dimming_doesnt_cut_a_restart_fade_short() {
+        let mut fade = ScreenFade::new(Duration::from_secs(1));
+        fade.darken(0.5);
+        assert_eq!(fade.to, 0.5);
+        fade.lighten(0.2);
+        assert_eq!(fade.to, 0.2);
+
+        fade.fade_to(1.0);
+        fade.darken(0.5);
+        assert_eq!(fade.to, 1.0);
+        fade.lighten(0.0);
+        assert_eq!(fade.to, 1.0);
+    }
+
+    #[test]
+    fn restart_starts_a_fresh_game() {
+        let mut score = Score {
+            score: Wrapping(1200),
+            rendered_score: 1200,
+        };
+        let mut bombs = Bombs(0);
+        let mut missiles = Missiles(3);
+        let mut difficulty = Difficulty::new(DifficultyConfig::default());
+        difficulty.elapsed = Duration::from_secs(90);
+        difficulty.level = 4;
+        let mut wave = Wave(5);
+        let mut wave_countdown = WaveCountdown(Some(Timer::new(Duration::from_secs(3), false)));
+        let mut weapon = Weapon::Laser;
+        let mut laser = Laser {
+            cooldown: Duration::from_millis(80),
+        };
+
+        reset_game_state(
+            &mut score,
+            &mut bombs,
+            &mut missiles,
+            &mut difficulty,
+            &mut wave,
+            &mut wave_countdown,
+            &mut weapon,
+            &mut laser,
+        );
+
+        assert_eq!(score.score.0, 0);
+        assert_eq!(bombs.0, INITIAL_BOMBS);
+        assert_eq!(missiles.0, 0);
+        assert_eq!(difficulty.level, 0);
+        assert_eq!(difficulty.elapsed, Duration::ZERO);
+        assert_eq!(wave.0, 0);
+        assert!(wave_countdown.0.is_none());
+        assert_eq!(weapon, Weapon::Blaster);
+        assert_eq!(laser.cooldown, Duration::ZERO);
+    }
+
+    #[test]
+    fn bullets_expire_at_the_range() {
+        let range = BulletRange::default();
+        assert_eq!(range.lifetime(25.0), Duration::from_secs(1));
+        assert_eq!(range.lifetime(50.0), Duration::from_millis(500));
+        // too slow to reach the range in time
+        assert_eq!(range.lifetime(1.0), range.max_lifetime);
+        assert_eq!(range.lifetime(f32::MIN_POSITIVE), range.max_lifetime);
+        assert_eq!(range.lifetime(0.0), range.max_lifetime);
+        assert_eq!(range.lifetime(-3.0), range.max_lifetime);
+    }
+
+    #[test]
+    fn spawn_acceptance_rises_with_distance() {
+        let density = SpawnDensity::default();
+        assert_eq!(density.acceptance(0.0), density.near);
+        assert_eq!(density.acceptance(density.falloff), 1.0);
+        assert_eq!(density.acceptance(density.falloff * 3.0), 1.0);
+        let half = density.acceptance(density.falloff * 0.5);
+        assert!((half - (density.near + 1.0) * 0.5).abs() < 1e-6, "{half}");
+
+        // weights are relative, the likelier end is always kept
+        let inverted = SpawnDensity {
+            near: 4.0,
+            far: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(inverted.acceptance(0.0), 1.0);
+        assert_eq!(inverted.acceptance(inverted.falloff), 0.5);
+        // no weights at all is no preference rather than no spawns
+        let unweighted = SpawnDensity {
+            near: 0.0,
+            far: 0.0,
+            falloff: 0.0,
+        };
+        assert_eq!(unweighted.acceptance(0.0), 1.0);
+    }
+
+    #[test]
+    fn asteroids_spawn_clear_of_the_player() {
+        let uniform = SpawnDensity {
+            near: 1.0,
+            far: 1.0,
+            ..Default::default()
+        };
+        let near = spawns_near_player(&SpawnDensity::default());
+        let near_uniform = spawns_near_player(&uniform);
+        assert!(near * 4 < near_uniform * 3, "{near} {near_uniform}");
+    }
+
+    #[test]
+    fn asteroids_spawn_clear_of_a_ufo() {
+        let mut rng = fastrand::Rng::with_seed(5);
+        let config = AsteroidConfig::default();
+        let density = SpawnDensity {
+            near: 1.0,
+            far: 1.0,
+            ..Default::default()
+        };
+        let map = MapConfig::default();
+        let ufo = Vec2::new(-8.0, 6.0);
+        let clearance = ufo::UfoConfig::default().spawn_clearance;
+        let mut rolled = 0;
+        for _ in 0..2000 {
+            let mut occupied = vec![(Vec2::ZERO, SHIP_SPAWN_CLEARANCE), (ufo, clearance)];
+            let Some((kind, tr, _)) = roll_asteroid(
+                &mut rng,
+                &config,
+                &density,
+                &mut occupied,
+                Vec3::ZERO,
+                1.0,
+                &map,
+            ) else {
+                continue;
+            };
+            rolled += 1;
+            let distance = tr.pos.truncate().distance(ufo);
+            assert!(
+                distance >= clearance + kind.stats().size * 0.5,
+                "{distance}"
+            );
+        }
+        assert!(rolled > 1900, "{rolled}");
+    }
+
+    #[test]
+    fn spawned_asteroids_dont_overlap() {
+        let rolled = roll_asteroids(&mut fastrand::Rng::with_seed(9), 20, 5);
+        assert!(rolled.len() > 20);
+        for (i, (kind_a, a, _)) in rolled.iter().enumerate() {
+            for (kind_b, b, _) in &rolled[i + 1..] {
+                let clearance = (kind_a.stats().size + kind_b.stats().size) * 0.5;
+                let distance = a.pos.truncate().distance(b.pos.truncate());
+                assert!(distance >= clearance * 0.999, "{distance} {clearance}");
+            }
+        }
+    }
+
+    #[test]
+    fn asteroids_spawn_at_least_min_speed() {
+        let config = AsteroidConfig::default();
+        for (_, _, vel) in roll_asteroids(&mut fastrand::Rng::with_seed(7), 50, 4) {
+            let speed = vel.0.length();
+            assert!(speed >= config.min_speed * 0.999, "{speed}");
+            assert!(speed <= config.max_speed * 1.001, "{speed}");
+        }
+    }
+
+    #[test]
+    fn split_spread_clamps_to_min_speed() {
+        let config = AsteroidConfig::default();
+        assert_eq!(config.split_spread(0.0), config.min_speed);
+        assert_eq!(config.split_spread(config.min_speed), config.min_speed);
+        let fast = config.max_speed * 4.0;
+        assert_eq!(config.split_spread(fast), fast * SPLIT_SPREAD);
+    }
+
+    #[test]
+    fn split_conserves_momentum() {
+        let mut rng = fastrand::Rng::with_seed(5);
+        for split_count in [1, 2, 3, 4] {
+            let config = AsteroidConfig {
+                split_count,
+                ..Default::default()
+            };
+            for (v, scale, hit) in [
+                (Vec2::new(1.0, 0.5), 1.0, Vec2::new(0.0, 0.3)),
+                (Vec2::ZERO, 0.8, Vec2::ZERO),
+                (Vec2::new(-0.2, 0.0), 0.64, Vec2::new(0.5, -0.5)),
+            ] {
+                let pieces = split_velocities(&mut rng, v, scale, &config, hit);
+                assert_eq!(pieces.len(), split_count as usize);
+                let child_mass = (scale * config.split_scale).powi(2);
+                let total: Vec2 = pieces.iter().map(|p| *p * child_mass).sum();
+                let expected = v * scale * scale + hit;
+                assert!(total.distance(expected) < 1e-4, "{total} {expected}");
+            }
+        }
+    }
+
+    #[test]
+    fn asteroids_split_max_splits_generations_deep() {
+        for (split_count, split_scale, max_splits) in [(2, 0.8, 3), (3, 0.5, 1), (2, 0.9, 6)] {
+            let config = AsteroidConfig {
+                split_count,
+                split_scale,
+                max_splits,
+                ..Default::default()
+            };
+            // destroy a full size asteroid and every piece it breaks into
+            let mut generation = vec![1.0f32];
+            let mut depth = 0;
+            let mut destroyed = 0;
+            while !generation.is_empty() {
+                destroyed += generation.len();
+                let pieces: Vec<f32> = generation
+                    .iter()
+                    .filter(|scale| config.can_split(**scale))
+                    .flat_map(|scale| {
+                        std::iter::repeat_n(scale * split_scale, split_count as usize)
+                    })
+                    .collect();
+                if !pieces.is_empty() {
+                    depth += 1;
+                }
+                generation = pieces;
+            }
+            assert_eq!(depth, max_splits);
+            let expected: u32 = (0..=max_splits).map(|g| split_count.pow(g)).sum();
+            assert_eq!(destroyed, expected as usize);
+        }
+    }
+
+    #[test]
+    fn bullets_in_flight_plateau_at_the_cap() {
+        let cap = MaxBullets(MAX_BULLETS);
+        let mut in_flight = 0;
+        // a spread shot every frame, without any bullet expiring
+        for _ in 0..100 {
+            in_flight += cap.allowed(in_flight, 3);
+            assert!(in_flight <= MAX_BULLETS);
+        }
+        assert_eq!(in_flight, MAX_BULLETS);
+        assert_eq!(cap.allowed(in_flight, 1), 0);
+        // one expiring frees a single slot, even for a spread shot
+        assert_eq!(cap.allowed(in_flight - 1, 3), 1);
+    }
+
+    #[test]
+    fn rotation_ramps_up_while_held() {
+        let frame = Duration::from_millis(16);
+        let mut rot_time = RotationTime(Duration::ZERO);
+        let mut last = rot_time.ramp(frame, 1.0);
+        assert!(last < 1.0);
+        let frames = ROTATION_RAMP.as_millis() / frame.as_millis();
+        for _ in 1..frames {
+            let v = rot_time.ramp(frame, 1.0);
+            assert!(v > last, "{v} {last}");
+            last = v;
+        }
+        // capped at the full rate once the ramp is over
+        for _ in 0..10 {
+            assert_eq!(rot_time.ramp(frame, 1.0), 1.0);
+        }
+        // the same hold time reaches the same speed at any frame rate
+        let mut slow = RotationTime(Duration::ZERO);
+        let mut fast = RotationTime(Duration::ZERO);
+        slow.ramp(Duration::from_millis(100), 1.0);
+        let v = (0..10)
+            .map(|_| fast.ramp(Duration::from_millis(10), 1.0))
+            .last();
+        assert_eq!(Some(slow.ramp(Duration::ZERO, 1.0)), v);
+    }
+
+    #[test]
+    fn objects_wrap_at_a_smaller_radius() {
+        let map = MapConfig {
+            radius: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(map.wrap_shift(Vec2::new(10.5, 3.0)), Vec2::new(-20.0, 0.0));
+        assert_eq!(map.wrap_shift(Vec2::new(0.0, -10.5)), Vec2::new(0.0, 20.0));
+        assert_eq!(
+            map.wrap_shift(Vec2::new(-11.0, 12.0)),
+            Vec2::new(20.0, -20.0)
+        );
+        assert_eq!(map.wrap_shift(Vec2::new(9.5, -9.5)), Vec2::ZERO);
+        // past the smaller edge, but well inside the default map
+        let pos = Vec2::new(15.0, 0.0);
+        assert_eq!(MapConfig::default().wrap_shift(pos), Vec2::ZERO);
+        assert_eq!(map.wrap_shift(pos), Vec2::new(-20.0, 0.0));
+        // ghosts follow the same edge
+        assert_eq!(wrap_offsets(Vec3::new(9.5, 0.0, 0.0), map.radius).len(), 1);
+        assert!(wrap_offsets(Vec3::new(9.5, 0.0, 0.0), MAP_RADIUS).is_empty());
+    }
+
+    #[test]
+    fn muzzle_lies_outside_the_ship() {
+        for (scale, collider) in [
+            (0.5, AABB::around_origin(Vec2::new(1.0, 2.0))),
+            (2.0, AABB::around_origin(Vec2::new(1.0, 2.0))),
+            (
+                1.0,
+                AABB::around_point(Vec2::new(0.0, 0.5), Vec2::splat(1.5)),
+            ),
+        ] {
+            for angle in [0.0f32, 0.4, 1.5, 3.0, -2.2] {
+                let rot = glam::Quat::from_rotation_z(angle);
+                let tr = GlobalTransform(Transform {
+                    pos: Vec3::new(3.0, -4.0, 0.0),
+                    rot,
+                    scale: Vec3::splat(scale),
+                });
+                let dir = (rot * Vec3::Y).truncate();
+                let muzzle = muzzle_position(&tr, &collider, dir).truncate();
+                let center = collider.center() * scale + tr.0.pos.truncate();
+                let ship = AABB::around_point(center, collider.size() * scale);
+                assert!(!ship.contains(muzzle), "{scale} {angle} {muzzle}");
+                // right past the edge, not somewhere ahead of the ship
+                let gap = ship.size().length() * 0.5 + MUZZLE_GAP;
+                assert!(muzzle.distance(center) <= gap + 1e-4, "{scale} {angle}");
+            }
+        }
+    }
+
+    #[test]
+    fn tap_during_cooldown_fires_once_it_ends() {
+        let mut buffered = FireBuffered(false);
+        // pressed and released again within the cooldown
+        assert!(!buffered.update(true, false, true, false));
+        assert!(!buffered.update(true, false, false, true));
+        assert!(!buffered.update(true, false, false, false));
+        assert!(buffered.update(false, false, false, false));
+        // a single shot, not one per frame after
+        assert!(!buffered.update(false, false, false, false));
+    }
+
+    #[test]
+    fn overheating_drops_the_buffered_shot() {
+        let mut buffered = FireBuffered(false);
+        assert!(!buffered.update(true, false, false, true));
+        assert!(!buffered.update(false, true, false, false));
+        assert!(!buffered.update(false, false, false, false));
+        // held triggers fire as soon as the weapon is ready
+        assert!(buffered.update(false, false, true, false));
+    }
+
+    #[test]
+    fn nearest_of_two_ships() {
+        let ships = [Vec2::new(-10.0, 0.0), Vec2::new(10.0, 2.0)];
+        let nearest_to = |pos| nearest(ships.iter().copied(), pos);
+        assert_eq!(nearest_to(Vec2::new(-3.0, 5.0)), Some(ships[0]));
+        assert_eq!(nearest_to(Vec2::new(3.0, -5.0)), Some(ships[1]));
+        // right between them
+        assert_eq!(nearest_to(Vec2::new(0.0, 1.0)), Some(ships[0]));
+        assert_eq!(nearest(std::iter::empty(), Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn bullets_leave_the_map_at_the_edge() {
+        let map = MapConfig::default();
+        let r = map.radius;
+        for inside in [Vec2::ZERO, Vec2::new(r, -r), Vec2::new(-r, 3.0)] {
+            assert!(map.contains(inside), "{inside}");
+        }
+        for outside in [
+            Vec2::new(r + 0.01, 0.0),
+            Vec2::new(0.0, -r - 0.01),
+            Vec2::new(-r - 1.0, r + 1.0),
+        ] {
+            // despawning bullets are gone here, everything else comes back in on the other side
+            assert!(!map.contains(outside), "{outside}");
+            assert!(map.contains(outside + map.wrap_shift(outside)), "{outside}");
+        }
+    }
+
+    #[test]
+    fn hyperspace_risk_is_a_probability() {
+        let risk = |hyperspace_risk| {
+            ShipPhysics {
+                hyperspace_risk,
+                ..Default::default()
+            }
+            .hyperspace_risk()
+        };
+        assert_eq!(risk(0.25), 0.25);
+        assert_eq!(risk(-1.0), 0.0);
+        assert_eq!(risk(3.0), 1.0);
+    }
+
+    /// Velocity after thrusting up for a second, then turning right and thrusting for half a second
+    fn thrust_then_turn(thrust_model: ThrustModel) -> Vec2 {
+        let physics = ShipPhysics {
+            thrust_model,
+            ..Default::default()
+        };
+        let mut player = Player {
+            velocity: 0.0,
+            acceleration: 0.0,
+        };
+        let dt = 1.0 / 60.0;
+        let mut vel = Vec2::ZERO;
+        for _ in 0..60 {
+            vel = physics.thrust(&mut player, vel, Vec2::Y, dt);
+        }
+        assert!(vel.y > 0.0 && vel.x.abs() < 1e-4, "{vel}");
+        for _ in 0..30 {
+            vel = physics.thrust(&mut player, vel, Vec2::X, dt);
+        }
+        assert!(vel.length() <= physics.max_vel + 1e-4, "{vel}");
+        vel
+    }
+
+    #[test]
+    fn force_thrust_keeps_sideways_momentum() {
+        let max_vel = ShipPhysics::default().max_vel;
+        let vel = thrust_then_turn(ThrustModel::Force);
+        // still drifting up, only the new heading's component grew
+        assert!(vel.x > 0.0);
+        assert!(vel.y > max_vel * 0.5, "{vel}");
+        // steering swings the velocity round instead
+        let steered = thrust_then_turn(ThrustModel::Steer);
+        assert!(steered.y < vel.y, "{steered} {vel}");
+    }
+
+    #[test]
+    fn asteroid_hit_twice_breaks_once() {
+        let ids = entities(3);
+        let (first, second, asteroid) = (ids[0], ids[1], ids[2]);
+        let mut consumed = HashSet::new();
+        let hit = bullet_hit(&mut consumed, first, asteroid, None, None);
+        assert_eq!(
+            hit,
+            Some(BulletHit {
+                spent: true,
+                destroyed: true
+            })
+        );
+        // the second bullet of the frame finds it already broken, and flies on
+        assert_eq!(
+            bullet_hit(&mut consumed, second, asteroid, None, None),
+            None
+        );
+        assert!(!consumed.contains(&second));
+
+        // armour takes one hit per bullet, even in the same frame
+        let mut consumed = HashSet::new();
+        let mut health = Health(2);
+        let hit = bullet_hit(&mut consumed, first, asteroid, None, Some(&mut health));
+        assert_eq!(hit.map(|hit| hit.destroyed), Some(false));
+        let hit = bullet_hit(&mut consumed, second, asteroid, None, Some(&mut health));
+        assert_eq!(hit.map(|hit| hit.destroyed), Some(true));
+        assert_eq!(health.0, 0);
+    }
+
+    #[test]
+    fn missile_takes_out_one_of_two_overlapping_asteroids() {
+        let ids = entities(4);
+        let (missile, first, second, bullet) = (ids[0], ids[1], ids[2], ids[3]);
+        let mut consumed = HashSet::new();
+        assert_eq!(missile_hit(&mut consumed, missile, first, None), Some(true));
+        // the missile is spent, the asteroid it also touches is left alone
+        assert_eq!(missile_hit(&mut consumed, missile, second, None), None);
+        assert!(!consumed.contains(&second));
+
+        // a bullet hitting the same asteroid in the frame doesn't break it again
+        assert_eq!(bullet_hit(&mut consumed, bullet, first, None, None), None);
+    }
+
+    #[test]
+    fn missile_spent_on_armour() {
+        let ids = entities(3);
+        let (missile, other, asteroid) = (ids[0], ids[1], ids[2]);
+        let mut consumed = HashSet::new();
+        let mut health = Health(MISSILE_DAMAGE + 1);
+        assert_eq!(
+            missile_hit(&mut consumed, missile, asteroid, Some(&mut health)),
+            Some(false)
+        );
+        assert!(consumed.contains(&missile));
+        assert!(!consumed.contains(&asteroid));
+        assert_eq!(
+            missile_hit(&mut consumed, other, asteroid, Some(&mut health)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn piercing_bullet_breaks_three_asteroids() {
+        let ids = entities(5);
+        let bullet = ids[0];
+        let mut consumed = HashSet::new();
+        let mut pierce = Pierce(2);
+        let hits: Vec<_> = ids[1..4]
+            .iter()
+            .map(|asteroid| bullet_hit(&mut consumed, bullet, *asteroid, Some(&mut pierce), None))
+            .collect();
+        let through = BulletHit {
+            spent: false,
+            destroyed: true,
+        };
+        let last = BulletHit {
+            spent: true,
+            destroyed: true,
+        };
+        assert_eq!(hits, [Some(through), Some(through), Some(last)]);
+        assert_eq!(pierce.0, 0);
+        // spent after the third
+        assert_eq!(
+            bullet_hit(&mut consumed, bullet, ids[4], Some(&mut pierce), None),
+            None
+        );
+    }
+
+    #[test]
+    fn armour_stops_piercing_bullets() {
+        let ids = entities(2);
+        let mut consumed = HashSet::new();
+        let mut pierce = Pierce(2);
+        let mut health = Health(3);
+        let hit = bullet_hit(
+            &mut consumed,
+            ids[0],
+            ids[1],
+            Some(&mut pierce),
+            Some(&mut health),
+        );
+        assert_eq!(
+            hit,
+            Some(BulletHit {
+                spent: true,
+                destroyed: false
+            })
+        );
+        assert_eq!(pierce.0, 2);
+    }
+
+    #[test]
+    fn cooldown_remaining_fraction() {
+        let mut cooldown = Cooldown::new(Duration::from_secs(2));
+        assert_eq!(cooldown.remaining_fraction(), 1.0);
+        cooldown.elapsed = Duration::from_millis(500);
+        assert_eq!(cooldown.remaining_fraction(), 0.75);
+        cooldown.elapsed = Duration::from_secs(1);
+        assert_eq!(cooldown.remaining_fraction(), 0.5);
+        cooldown.elapsed = Duration::from_secs(2);
+        assert!(cooldown.is_finished());
+        assert_eq!(cooldown.remaining_fraction(), 0.0);
+    }
+
+    #[test]
+    fn flame_goes_with_the_dead_ship() {
+        let physics = ShipPhysics::default();
+        let mut player = Player {
+            velocity: 0.0,
+            acceleration: 0.0,
+        };
+        assert_eq!(flame(&physics, Some(&player)), Some((1.0, 1.0)));
+        player.acceleration = physics.max_acc;
+        assert_eq!(
+            flame(&physics, Some(&player)),
+            Some((THRUST_MAX_SCALE, THRUST_MAX_ANIMATION_SPEED))
+        );
+        // handle_collisions takes Player off a ship that dies mid-thrust
+        assert_eq!(flame(&physics, None), None);
+    }
+
+    #[test]
+    fn failed_sheets_fall_back_to_the_placeholder() {
+        let garbage = || Err(anyhow::anyhow!("not an image"));
+        let unused = || panic!("single frame sheets share the placeholder");
+        assert_eq!(
+            or_placeholder(garbage(), "wave", 1, &"placeholder", unused),
+            "placeholder"
+        );
+        assert_eq!(
+            or_placeholder(garbage(), "digits", 10, &"placeholder", || {
+                "digits placeholder"
+            }),
+            "digits placeholder"
+        );
+        assert_eq!(
+            or_placeholder(Ok("digits"), "digits", 10, &"placeholder", unused),
+            "digits"
+        );
+    }
+
+    #[test]
+    fn spawns_follow_the_rate_at_any_frame_rate() {
+        let config = DifficultyConfig::default();
+        let rate = config.spawn_rate as usize;
+        for fps in [1, 7, 30, 60, 144, 240] {
+            let mut difficulty = Difficulty::new(config.clone());
+            let mut count = difficulty.take_spawns(Duration::ZERO, 0);
+            assert_eq!(count, config.initial_asteroids);
+            let dt = Duration::from_secs(1) / fps;
+            for second in 1..=3 {
+                for _ in 0..fps {
+                    count += difficulty.take_spawns(dt, count);
+                }
+                let spawned = count - config.initial_asteroids;
+                // rounding in the budget can hold the last one back to the next frame
+                assert!(spawned.abs_diff(second * rate) <= 1, "{fps} {spawned}");
+            }
+        }
+    }
+
+    #[test]
+    fn full_field_doesnt_save_up() {
+        let mut difficulty = Difficulty::new(DifficultyConfig::default());
+        let target = difficulty.target_asteroids();
+        assert_eq!(difficulty.take_spawns(Duration::from_secs(60), target), 0);
+        assert_eq!(
+            difficulty.take_spawns(Duration::from_secs(60), target - 2),
+            2
+        );
+        assert!(difficulty.spawn_budget < 1.0);
+    }
+
+    #[test]
+    fn budget_stays_capped_over_frames_on_a_full_field() {
+        let config = DifficultyConfig::default();
+        let rate = config.spawn_rate as usize;
+        let mut difficulty = Difficulty::new(config);
+        let target = difficulty.target_asteroids();
+        let dt = Duration::from_secs(1) / 60;
+        let mut count = 0;
+        // fill the field in about six seconds, then sit on it for the rest of the twenty
+        for _ in 0..60 * 20 {
+            count += difficulty.take_spawns(dt, count);
+            assert!(count <= target);
+            if count == target {
+                assert!(difficulty.spawn_budget < 1.0, "{}", difficulty.spawn_budget);
+            }
+        }
+        assert_eq!(count, target);
+
+        // a bomb clears half the field, it fills back up at the rate and not in a burst
+        count /= 2;
+        let first = difficulty.take_spawns(dt, count);
+        assert!(first <= 1, "{first}");
+        count += first;
+        for _ in 1..60 {
+            count += difficulty.take_spawns(dt, count);
+        }
+        let refilled = count - target / 2;
+        assert!(refilled.abs_diff(rate) <= 1, "{refilled}");
+    }
+
+    #[test]
+    fn bullet_pool_reuses_deactivated_bullets() {
+        const CAPACITY: usize = 8;
+        let mut pool = BulletPool::new(CAPACITY);
+        let mut bullets: Vec<(EntityId, bool)> = entities(CAPACITY)
+            .into_iter()
+            .map(|id| (id, false))
+            .collect();
+        assert_eq!(pool.refresh(bullets.iter().copied()), 0);
+        let free_capacity = pool.free.capacity();
+
+        for _ in 0..10 {
+            // fire more than the pool holds, the extra shots find no bullet
+            let mut fired = 0;
+            for _ in 0..CAPACITY * 2 {
+                let Some(id) = pool.free.pop() else {
+                    break;
+                };
+                bullets.iter_mut().find(|(b, _)| *b == id).unwrap().1 = true;
+                fired += 1;
+            }
+            assert_eq!(fired, CAPACITY);
+            assert_eq!(pool.refresh(bullets.iter().copied()), 0);
+            assert_eq!(pool.used, CAPACITY);
+            assert!(pool.free.is_empty());
+
+            for bullet in bullets.iter_mut() {
+                bullet.1 = false;
+            }
+            assert_eq!(pool.refresh(bullets.iter().copied()), 0);
+            assert_eq!(pool.used, 0);
+            assert_eq!(pool.free.len(), CAPACITY);
+            assert_eq!(pool.free.capacity(), free_capacity);
+        }
+    }
+
+    #[test]
+    fn bullet_pool_replaces_deleted_bullets() {
+        let mut pool = BulletPool::new(4);
+        let ids = entities(3);
+        assert_eq!(pool.refresh(ids.iter().map(|id| (*id, false))), 1);
+        assert_eq!(pool.free, ids);
+    }
 }
@@ -0,0 +1,179 @@
+//! Stand-in image for sprite sheets that fail to load, so a broken asset shows up on screen
+//! instead of taking the game down
+
+/// Loud enough that nobody mistakes it for real art
+const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+/// Side of a square of the checkerboard, in pixels
+const CELL: u32 = 8;
+
+/// Bytes of a PNG of `width` by `height` pixels, a checkerboard of [MAGENTA] and [BLACK]
+/// squares
+///
+/// Encoded by hand, the image is tiny and the texture loader only needs it to be a valid PNG, so
+/// the pixels go into stored, uncompressed deflate blocks.
+pub fn placeholder_png(width: u32, height: u32) -> Vec<u8> {
+    let (width, height) = (width.max(1), height.max(1));
+    // every row starts with the filter type, 0 is none
+    let mut raw = Vec::with_capacity((1 + width as usize * 4) * height as usize);
+    for y in 0..height {
+        raw.push(0);
+        for x in 0..width {
+            let color = if (x / CELL + y / CELL) & 1 == 0 {
+                MAGENTA
+            } else {
+                BLACK
+            };
+            raw.extend_from_slice(&color);
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, not interlaced
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    push_chunk(&mut png, b"IHDR", &ihdr);
+    push_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn push_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// zlib stream of `data` in stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate, 32K window, no preset dictionary, the check bits make the header a multiple of 31
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(u8::from(last));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65_521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pixels of a PNG made by [placeholder_png], checking the chunk CRCs and the zlib stream on
+    /// the way
+    fn decode(png: &[u8]) -> (u32, u32, Vec<u8>) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut rest = &png[8..];
+        let mut chunks = Vec::new();
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let (body, tail) = rest[4..].split_at(4 + len);
+            let crc = u32::from_be_bytes(tail[..4].try_into().unwrap());
+            assert_eq!(crc, crc32(body));
+            chunks.push((&body[..4], &body[4..]));
+            rest = &tail[4..];
+        }
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+
+        let ihdr = chunks[0].1;
+        let width = u32::from_be_bytes(ihdr[..4].try_into().unwrap());
+        let height = u32::from_be_bytes(ihdr[4..8].try_into().unwrap());
+        assert_eq!(&ihdr[8..], [8, 6, 0, 0, 0]);
+
+        let zlib = chunks[1].1;
+        assert_eq!(u16::from_be_bytes([zlib[0], zlib[1]]) % 31, 0);
+        let (mut blocks, adler) = zlib[2..].split_at(zlib.len() - 6);
+        let mut raw = Vec::new();
+        loop {
+            let last = blocks[0] == 1;
+            let len = u16::from_le_bytes([blocks[1], blocks[2]]);
+            assert_eq!(!len, u16::from_le_bytes([blocks[3], blocks[4]]));
+            raw.extend_from_slice(&blocks[5..5 + len as usize]);
+            blocks = &blocks[5 + len as usize..];
+            if last {
+                break;
+            }
+        }
+        assert!(blocks.is_empty());
+        assert_eq!(adler, adler32(&raw).to_be_bytes());
+
+        let stride = 1 + width as usize * 4;
+        assert_eq!(raw.len(), stride * height as usize);
+        let mut pixels = Vec::new();
+        for row in raw.chunks(stride) {
+            assert_eq!(row[0], 0);
+            pixels.extend_from_slice(&row[1..]);
+        }
+        (width, height, pixels)
+    }
+
+    #[test]
+    fn checksums_match_the_reference_values() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn placeholder_is_a_checkerboard_png() {
+        let (width, height, pixels) = decode(&placeholder_png(20, 12));
+        assert_eq!((width, height), (20, 12));
+        let pixel = |x: usize, y: usize| &pixels[(y * 20 + x) * 4..][..4];
+        assert_eq!(pixel(0, 0), MAGENTA);
+        assert_eq!(pixel(7, 7), MAGENTA);
+        assert_eq!(pixel(8, 0), BLACK);
+        assert_eq!(pixel(0, 8), BLACK);
+        assert_eq!(pixel(8, 8), MAGENTA);
+        assert_eq!(pixel(19, 11), BLACK);
+    }
+
+    #[test]
+    fn large_placeholder_spans_several_blocks() {
+        // a row is 1 + 4 * 256 bytes, 64 of them don't fit a single stored block
+        let png = placeholder_png(256, 80);
+        let (width, height, pixels) = decode(&png);
+        assert_eq!((width, height), (256, 80));
+        assert_eq!(pixels.len(), 256 * 80 * 4);
+    }
+
+    #[test]
+    fn empty_placeholder_is_one_pixel() {
+        let (width, height, pixels) = decode(&placeholder_png(0, 0));
+        assert_eq!((width, height), (1, 1));
+        assert_eq!(pixels, MAGENTA);
+    }
+}
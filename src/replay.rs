@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use brengin::cecs::prelude::*;
+use brengin::winit::event::VirtualKeyCode;
+use brengin::{DeltaTime, KeyBoardInputs};
+
+use crate::{GameOver, Plugin};
+
+const HEADER: &str = "asteroids-replay 1";
+
+/// Keys the game reacts to, frames store them as bit masks indexed into this table
+const KEYS: [VirtualKeyCode; 15] = [
+    VirtualKeyCode::W,
+    VirtualKeyCode::A,
+    VirtualKeyCode::D,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::B,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::RShift,
+    VirtualKeyCode::R,
+    VirtualKeyCode::E,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::F5,
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayFrame {
+    pub dt: Duration,
+    pub pressed: u16,
+    pub just_released: u16,
+}
+
+/// The RNG seed and the inputs and frame time of every frame of a game
+///
+/// Frame time is recorded along with the inputs, played back through the same seed the game
+/// takes the exact same steps and ends with the same score.
+#[derive(Debug, Default, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Writes the replay as text, a header, the seed, then one line per frame
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut out = String::with_capacity(32 * self.frames.len());
+        writeln!(out, "{HEADER}")?;
+        writeln!(out, "{}", self.seed)?;
+        for frame in &self.frames {
+            writeln!(
+                out,
+                "{} {} {}",
+                frame.dt.as_nanos(),
+                frame.pressed,
+                frame.just_released
+            )?;
+        }
+        std::fs::write(path, out)
+            .with_context(|| format!("failed to write replay {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read replay {}", path.display()))?;
+        let mut lines = text.lines();
+        anyhow::ensure!(lines.next() == Some(HEADER), "not a replay file");
+        let seed = lines.next().context("missing seed")?.parse()?;
+        let frames = lines
+            .enumerate()
+            .map(|(i, line)| {
+                let mut fields = line.split_whitespace();
+                let mut next = || fields.next().with_context(|| format!("short frame {i}"));
+                Ok(ReplayFrame {
+                    dt: Duration::from_nanos(next()?.parse()?),
+                    pressed: next()?.parse()?,
+                    just_released: next()?.parse()?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { seed, frames })
+    }
+}
+
+fn to_mask(keys: &HashSet<VirtualKeyCode>) -> u16 {
+    KEYS.iter()
+        .enumerate()
+        .filter(|(_, k)| keys.contains(k))
+        .fold(0, |mask, (i, _)| mask | (1 << i))
+}
+
+fn from_mask(mask: u16, keys: &mut HashSet<VirtualKeyCode>) {
+    keys.clear();
+    keys.extend(
+        KEYS.iter()
+            .enumerate()
+            .filter(|(i, _)| mask & (1 << i) != 0)
+            .map(|(_, k)| *k),
+    );
+}
+
+pub struct ReplayRecorder {
+    pub replay: Replay,
+    /// The recording is written here every time the game ends
+    pub path: PathBuf,
+    saved: bool,
+}
+
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    /// Next frame to play
+    pub frame: usize,
+}
+
+fn record_system(
+    dt: Res<DeltaTime>,
+    inputs: Res<KeyBoardInputs>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    recorder.replay.frames.push(ReplayFrame {
+        dt: dt.0,
+        pressed: to_mask(&inputs.pressed),
+        just_released: to_mask(&inputs.just_released),
+    });
+}
+
+fn save_replay_system(
+    q_game_over: Query<&(), With<GameOver>>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    if q_game_over.is_empty() {
+        // armed again after a restart, the next save includes the earlier games too
+        recorder.saved = false;
+        return;
+    }
+    if recorder.saved {
+        return;
+    }
+    recorder.saved = true;
+    match recorder.replay.save(&recorder.path) {
+        Ok(()) => tracing::info!(path = %recorder.path.display(), "Replay saved"),
+        Err(err) => tracing::error!(?err, "Failed to save replay"),
+    }
+}
+
+/// Overwrites the frame time and the keyboard with the recorded frame, once the recording runs out
+/// the keys are released and the game runs on in real time
+fn play_system(
+    mut dt: ResMut<DeltaTime>,
+    mut inputs: ResMut<KeyBoardInputs>,
+    mut player: ResMut<ReplayPlayer>,
+) {
+    let Some(frame) = player.replay.frames.get(player.frame).copied() else {
+        inputs.pressed.clear();
+        inputs.just_released.clear();
+        return;
+    };
+    player.frame += 1;
+    dt.0 = frame.dt;
+    from_mask(frame.pressed, &mut inputs.pressed);
+    from_mask(frame.just_released, &mut inputs.just_released);
+}
+
+pub enum ReplayPlugin {
+    Record { seed: u64, path: PathBuf },
+    Play(Replay),
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(self, app: &mut crate::App) {
+        match self {
+            ReplayPlugin::Record { seed, path } => {
+                app.stage(crate::Stage::PreUpdate).add_system(record_system);
+                app.stage(crate::Stage::PostUpdate)
+                    .add_system(save_replay_system);
+                app.insert_resource(ReplayRecorder {
+                    replay: Replay::new(seed),
+                    path,
+                    saved: false,
+                });
+            }
+            ReplayPlugin::Play(replay) => {
+                app.stage(crate::Stage::PreUpdate).add_system(play_system);
+                app.insert_resource(ReplayPlayer { replay, frame: 0 });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file under the temp directory, unique to the test
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("asteroids-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn replay_round_trips_through_a_file() {
+        let mut replay = Replay::new(u64::MAX - 7);
+        replay.frames = (0..100)
+            .map(|i| ReplayFrame {
+                dt: Duration::from_nanos(16_666_667 + i),
+                pressed: (i * 37) as u16,
+                just_released: (i * 11) as u16 & 0x7fff,
+            })
+            .collect();
+        let path = temp_path("round-trip.replay");
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.frames, replay.frames);
+    }
+
+    #[test]
+    fn broken_replays_fail_to_load() {
+        let path = temp_path("broken.replay");
+        for text in [
+            "",
+            "some other file\n1\n",
+            &format!("{HEADER}\n"),
+            &format!("{HEADER}\n1\n16666667 3\n"),
+            &format!("{HEADER}\n1\n16666667 3 x\n"),
+        ] {
+            std::fs::write(&path, text).unwrap();
+            assert!(Replay::load(&path).is_err(), "{text:?}");
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(Replay::load(&path).is_err());
+    }
+
+    #[test]
+    fn key_masks_round_trip() {
+        let mut keys = HashSet::new();
+        for mask in [0, 1, 0b101_0101, (1 << KEYS.len()) - 1] {
+            from_mask(mask, &mut keys);
+            assert_eq!(keys.len(), mask.count_ones() as usize);
+            assert_eq!(to_mask(&keys), mask);
+        }
+        // keys the game doesn't react to aren't recorded
+        keys.insert(VirtualKeyCode::Z);
+        assert_eq!(to_mask(&keys), (1 << KEYS.len()) - 1);
+    }
+}
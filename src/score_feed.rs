@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use brengin::cecs::prelude::*;
+
+use crate::{GameOver, Plugin, Score};
+
+#[derive(Debug, Default)]
+struct Shared {
+    score: AtomicU64,
+    game_over: AtomicBool,
+}
+
+/// The current score, readable from outside the game
+///
+/// Cloned handles share the value, the game stores the score into it at the end of every frame.
+#[derive(Debug, Clone, Default)]
+pub struct ScoreFeed(Arc<Shared>);
+
+impl ScoreFeed {
+    pub fn get(&self) -> u64 {
+        self.0.score.load(Ordering::Relaxed)
+    }
+
+    /// The game over screen is up, [get](Self::get) is the final score
+    pub fn is_game_over(&self) -> bool {
+        self.0.game_over.load(Ordering::Relaxed)
+    }
+}
+
+fn publish_score_system(
+    score: Res<Score>,
+    feed: Res<ScoreFeed>,
+    q_game_over: Query<&(), With<GameOver>>,
+) {
+    feed.0.score.store(score.score.0, Ordering::Relaxed);
+    feed.0
+        .game_over
+        .store(!q_game_over.is_empty(), Ordering::Relaxed);
+}
+
+pub struct ScoreFeedPlugin(pub ScoreFeed);
+
+impl Plugin for ScoreFeedPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::PostUpdate)
+            .add_system(publish_score_system);
+        app.insert_resource(self.0);
+    }
+}
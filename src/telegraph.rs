@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use brengin::cecs::prelude::*;
+use brengin::glam::{Vec2, Vec3};
+use brengin::renderer::sprite_renderer::sprite_sheet_bundle;
+use brengin::transform::{transform_bundle, Transform};
+use brengin::{DeltaTime, Timer};
+use serde::Deserialize;
+
+use crate::boss::{self, BossConfig};
+use crate::config::secs;
+use crate::ufo::{self, UfoConfig};
+use crate::{layers, GameEntity, Plugin, Rng, Sprites, Threat, TimeScale, UniformAnimation};
+
+#[cfg(not(target_family = "wasm"))]
+use brengin::assets;
+
+/// How long the warning blinks before a UFO or boss shows up
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct TelegraphConfig {
+    #[serde(deserialize_with = "secs")]
+    pub duration: Duration,
+}
+
+impl Default for TelegraphConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(1),
+        }
+    }
+}
+
+/// What arrives when a [SpawnTelegraph] runs out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TelegraphKind {
+    Ufo,
+    Boss,
+}
+
+/// Warning blinking where something is about to spawn, it spawns when the timer finishes
+pub(crate) struct SpawnTelegraph {
+    pub timer: Timer,
+    pub kind: TelegraphKind,
+}
+
+/// The alert of the telegraph has been played
+#[cfg(not(target_family = "wasm"))]
+struct Alerted;
+
+#[cfg(not(target_family = "wasm"))]
+struct AlertSound;
+
+/// Time the warning spends lit, then as long dimmed
+const BLINK: Duration = Duration::from_millis(125);
+
+/// Queues `kind` to spawn at `pos` once [TelegraphConfig::duration] has passed
+pub(crate) fn spawn_telegraph(
+    cmd: &mut Commands,
+    sprites: &Sprites,
+    config: &TelegraphConfig,
+    kind: TelegraphKind,
+    pos: Vec2,
+) {
+    let scale = match kind {
+        TelegraphKind::Ufo => 1.5,
+        TelegraphKind::Boss => 4.0,
+    };
+    cmd.spawn()
+        .insert_bundle(transform_bundle(Transform {
+            pos: pos.extend(layers::TELEGRAPH),
+            scale: Vec3::splat(scale),
+            ..Default::default()
+        }))
+        .insert_bundle(sprite_sheet_bundle(sprites.warning_sheet.clone(), None))
+        .insert_bundle((
+            SpawnTelegraph {
+                timer: Timer::new(config.duration, false),
+                kind,
+            },
+            UniformAnimation {
+                timer: Timer::new(BLINK, true),
+                n: 2,
+            },
+            GameEntity,
+            // off-screen warnings get an arrow like the threat they announce
+            Threat,
+        ));
+}
+
+/// Counts the telegraphs down and spawns what they announced
+fn telegraph_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    mut rng: ResMut<Rng>,
+    ufo_config: Res<UfoConfig>,
+    boss_config: Res<BossConfig>,
+    mut q_telegraph: Query<(EntityId, &mut SpawnTelegraph, &Transform)>,
+) {
+    let dt = time_scale.scale(&dt);
+    for (id, telegraph, tr) in q_telegraph.iter_mut() {
+        telegraph.timer.update(dt);
+        if !telegraph.timer.just_finished() {
+            continue;
+        }
+        cmd.delete(id);
+        let pos = tr.pos.truncate();
+        match telegraph.kind {
+            TelegraphKind::Ufo => ufo::spawn_ufo(&mut cmd, &sprites, &ufo_config, pos),
+            TelegraphKind::Boss => {
+                boss::spawn_boss(&mut cmd, &mut rng.0, &sprites, &boss_config, pos)
+            }
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn setup_alert_sound(mut cmd: Commands, mut assets: ResMut<assets::Assets<brengin::audio::Audio>>) {
+    let bytes = include_bytes!("../assets/alert.wav");
+    let alert = brengin::audio::Audio::load_audio_bytes(bytes, &mut assets).unwrap();
+    cmd.spawn().insert_bundle((AlertSound, alert));
+}
+
+/// Sounds the alert once for every new telegraph
+///
+/// Not spatial like the other sounds, the point is to hear it while the spawn is out of view.
+#[cfg(not(target_family = "wasm"))]
+fn telegraph_alert_system(
+    mut cmd: Commands,
+    audio: Res<assets::Assets<brengin::audio::Audio>>,
+    am: Res<brengin::audio::AudioManager>,
+    q_sound: Query<&assets::Handle<brengin::audio::Audio>, With<AlertSound>>,
+    q_telegraph: Query<EntityId, (With<SpawnTelegraph>, WithOut<Alerted>)>,
+) {
+    for id in q_telegraph.iter() {
+        cmd.entity(id).insert(Alerted);
+        if let Some(sound) = q_sound.single() {
+            am.play(audio.get(sound));
+        }
+    }
+}
+
+pub(crate) struct TelegraphPlugin;
+
+impl Plugin for TelegraphPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::Update).add_system(telegraph_system);
+        #[cfg(not(target_family = "wasm"))]
+        {
+            app.stage(crate::Stage::Update)
+                .add_system(telegraph_alert_system);
+            app.add_startup_system(setup_alert_sound);
+        }
+    }
+}
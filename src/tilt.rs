@@ -0,0 +1,80 @@
+use std::sync::{Arc, Mutex};
+
+use brengin::cecs::prelude::*;
+
+use crate::Plugin;
+
+/// Left to right tilt of the device, in degrees
+///
+/// Cloned handles share the state, the page sets it from its `deviceorientation` handler and
+/// clears it when tilting is turned off or the device doesn't report an orientation.
+#[derive(Debug, Clone, Default)]
+pub struct Tilt(Arc<Mutex<Option<f32>>>);
+
+impl Tilt {
+    pub fn set(&self, degrees: Option<f32>) {
+        *self.0.lock().unwrap() = degrees;
+    }
+
+    fn get(&self) -> Option<f32> {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TiltConfig {
+    /// Tilt ignored around holding the device level, in degrees
+    pub deadzone: f32,
+    /// Turn gained per degree of tilt past the deadzone, full turn is 1
+    pub sensitivity: f32,
+}
+
+impl Default for TiltConfig {
+    fn default() -> Self {
+        Self {
+            deadzone: 5.0,
+            sensitivity: 1.0 / 25.0,
+        }
+    }
+}
+
+/// Turning the ship by tilting the device
+///
+/// Without a page reporting the tilt [TiltControls::turn] stays 0 and the keys do the turning.
+#[derive(Debug, Default)]
+pub struct TiltControls {
+    pub config: TiltConfig,
+    tilt: Tilt,
+    /// -1 turns left at the full rate, 1 right, same as holding A or D
+    pub turn: f32,
+}
+
+fn tilt_system(mut controls: ResMut<TiltControls>) {
+    let TiltConfig {
+        deadzone,
+        sensitivity,
+    } = controls.config;
+    controls.turn = match controls.tilt.get() {
+        Some(degrees) => {
+            let past_deadzone = (degrees.abs() - deadzone).max(0.0);
+            (past_deadzone * sensitivity).min(1.0) * degrees.signum()
+        }
+        None => 0.0,
+    };
+}
+
+pub struct TiltPlugin {
+    pub tilt: Tilt,
+    pub config: TiltConfig,
+}
+
+impl Plugin for TiltPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::PreUpdate).add_system(tilt_system);
+        app.insert_resource(TiltControls {
+            config: self.config,
+            tilt: self.tilt,
+            turn: 0.0,
+        });
+    }
+}
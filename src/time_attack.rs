@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use brengin::cecs::prelude::*;
+use brengin::glam::Vec3;
+use brengin::DeltaTime;
+use serde::Deserialize;
+
+use crate::config::secs;
+use crate::{
+    layers, spawn_digits, spawn_powerup, wreck_ship, MapConfig, Player, PlayerCamera, Plugin,
+    PowerUpKind, Rng, SecondPlayer, Sprites, TimeScale,
+};
+
+/// How long a time attack lasts and how the clock power-up extends it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct TimeAttackConfig {
+    #[serde(deserialize_with = "secs")]
+    pub duration: Duration,
+    /// Time added by a clock power-up
+    #[serde(deserialize_with = "secs")]
+    pub clock_bonus: Duration,
+    #[serde(deserialize_with = "secs")]
+    pub clock_interval: Duration,
+}
+
+impl Default for TimeAttackConfig {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(60),
+            clock_bonus: Duration::from_secs(5),
+            clock_interval: Duration::from_secs(15),
+        }
+    }
+}
+
+/// Time left on the clock, on the first player's ship, the game ends when it runs out
+///
+/// In co-op both ships play against the same clock.
+pub(crate) struct TimeAttack {
+    pub remaining: Duration,
+}
+
+/// Whole seconds on the clock when the HUD was last drawn
+struct RenderedTime(pub Option<u64>);
+
+struct TimeDigit;
+
+/// Time since the last clock power-up was dropped
+struct ClockSpawner(pub Duration);
+
+/// Starts the clock on every new first ship, so a restart gets the full time again
+fn time_attack_start_system(
+    mut cmd: Commands,
+    config: Res<TimeAttackConfig>,
+    q_player: Query<EntityId, (With<Player>, WithOut<TimeAttack>, WithOut<SecondPlayer>)>,
+) {
+    for id in q_player.iter() {
+        cmd.entity(id).insert(TimeAttack {
+            remaining: config.duration,
+        });
+    }
+}
+
+/// Runs the clock down while a ship is flying, at zero every ship is wrecked whatever the
+/// shields say
+fn time_attack_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut cmd: Commands,
+    mut q_clock: Query<&mut TimeAttack>,
+    q_player: Query<EntityId, With<Player>>,
+) {
+    // the first ship can go down while the second plays on, the clock stays on its wreck
+    if q_player.is_empty() {
+        return;
+    }
+    let dt = time_scale.scale(&dt);
+    let Some(clock) = q_clock.single_mut() else {
+        return;
+    };
+    clock.remaining = clock.remaining.saturating_sub(dt);
+    if clock.remaining.is_zero() {
+        for id in q_player.iter() {
+            wreck_ship(&mut cmd, id);
+        }
+    }
+}
+
+/// Seconds left, rounded up, at the top of the screen
+fn render_time_attack(
+    q_camera: Query<EntityId, With<PlayerCamera>>,
+    q_digits: Query<EntityId, With<TimeDigit>>,
+    q_clock: Query<&TimeAttack>,
+    mut rendered: ResMut<RenderedTime>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+) {
+    // the wreck keeps its clock, so the game over screen shows the 0
+    let Some(clock) = q_clock.iter().next() else {
+        return;
+    };
+    let secs = clock.remaining.as_secs() + u64::from(clock.remaining.subsec_nanos() > 0);
+    if rendered.0 == Some(secs) {
+        return;
+    }
+    for id in q_digits.iter() {
+        cmd.delete(id);
+    }
+    rendered.0 = Some(secs);
+    let Some(camera_id) = q_camera.single() else {
+        return;
+    };
+    spawn_digits(
+        &mut cmd,
+        Some(camera_id),
+        &sprites,
+        secs,
+        Vec3::new(0.0, 44.0, layers::UI),
+        2.0,
+        |cmd| {
+            cmd.insert(TimeDigit);
+        },
+    );
+}
+
+/// Drops a clock power-up somewhere on the map every [TimeAttackConfig::clock_interval]
+fn clock_spawn_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    config: Res<TimeAttackConfig>,
+    mut spawner: ResMut<ClockSpawner>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    q_player: Query<&(), With<Player>>,
+    mut rng: ResMut<Rng>,
+    map: Res<MapConfig>,
+) {
+    spawner.0 += time_scale.scale(&dt);
+    if spawner.0 < config.clock_interval || q_player.is_empty() {
+        return;
+    }
+    spawner.0 = Duration::ZERO;
+    let pos = map.random_point(&mut rng.0).extend(layers::POWERUP);
+    spawn_powerup(cmd.spawn(), &mut rng.0, &sprites, PowerUpKind::Clock, pos);
+}
+
+/// Score as much as possible before the clock runs out, added on top of a regular game
+pub(crate) struct TimeAttackPlugin;
+
+impl Plugin for TimeAttackPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::Update)
+            .add_system(time_attack_start_system)
+            .add_system(time_attack_system)
+            .add_system(render_time_attack)
+            .add_system(clock_spawn_system);
+        app.insert_resource(RenderedTime(None));
+        app.insert_resource(ClockSpawner(Duration::ZERO));
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+
+use brengin::cecs::prelude::*;
+use brengin::winit::event::VirtualKeyCode;
+use brengin::KeyBoardInputs;
+
+use crate::Plugin;
+
+/// Actions of the on-screen controls, each stands in for the key the keyboard player would press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchAction {
+    /// A
+    RotateLeft,
+    /// D
+    RotateRight,
+    /// W
+    Thrust,
+    /// Space
+    Fire,
+}
+
+impl TouchAction {
+    pub const ALL: [Self; 4] = [
+        Self::RotateLeft,
+        Self::RotateRight,
+        Self::Thrust,
+        Self::Fire,
+    ];
+
+    fn key(self) -> VirtualKeyCode {
+        match self {
+            TouchAction::RotateLeft => VirtualKeyCode::A,
+            TouchAction::RotateRight => VirtualKeyCode::D,
+            TouchAction::Thrust => VirtualKeyCode::W,
+            TouchAction::Fire => VirtualKeyCode::Space,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Actions held on the on-screen controls
+///
+/// Cloned handles share the state, the page sets it from its touch handlers and the game reads
+/// it once per frame.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualKeys(Arc<Mutex<u8>>);
+
+impl VirtualKeys {
+    pub fn set(&self, action: TouchAction, held: bool) {
+        let mut state = self.0.lock().unwrap();
+        if held {
+            *state |= action.bit();
+        } else {
+            *state &= !action.bit();
+        }
+    }
+
+    fn get(&self) -> u8 {
+        *self.0.lock().unwrap()
+    }
+}
+
+struct TouchState {
+    keys: VirtualKeys,
+    /// Actions held last frame
+    held: u8,
+    /// Actions whose key the touch controls put down, keys the keyboard already held are left
+    /// to it
+    owned: u8,
+}
+
+impl TouchState {
+    /// Keys to put down and to lift for the actions `held` this frame, given the action keys
+    /// already `pressed`, all as masks of [TouchAction] bits
+    fn update(&mut self, held: u8, pressed: u8) -> (u8, u8) {
+        let press = held & !pressed;
+        self.owned |= press;
+        // letting go of a touch control doesn't lift a key held on the keyboard
+        let release = self.held & !held & self.owned;
+        self.owned &= held;
+        self.held = held;
+        (press, release)
+    }
+}
+
+/// Merges the held actions into the keyboard, so the input systems can't tell them apart
+fn virtual_keys_system(mut state: ResMut<TouchState>, mut inputs: ResMut<KeyBoardInputs>) {
+    let held = state.keys.get();
+    let pressed = TouchAction::ALL
+        .iter()
+        .filter(|action| inputs.pressed.contains(&action.key()))
+        .fold(0, |mask, action| mask | action.bit());
+    let (press, release) = state.update(held, pressed);
+    for action in TouchAction::ALL {
+        let bit = action.bit();
+        if press & bit != 0 {
+            inputs.pressed.insert(action.key());
+        } else if release & bit != 0 {
+            inputs.pressed.remove(&action.key());
+            inputs.just_released.insert(action.key());
+        }
+    }
+}
+
+pub struct TouchPlugin(pub VirtualKeys);
+
+impl Plugin for TouchPlugin {
+    fn build(self, app: &mut crate::App) {
+        app.stage(crate::Stage::PreUpdate)
+            .add_system(virtual_keys_system);
+        app.insert_resource(TouchState {
+            keys: self.0,
+            held: 0,
+            owned: 0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> TouchState {
+        TouchState {
+            keys: VirtualKeys::default(),
+            held: 0,
+            owned: 0,
+        }
+    }
+
+    #[test]
+    fn touch_presses_and_releases_its_keys() {
+        let fire = TouchAction::Fire.bit();
+        let mut state = state();
+        assert_eq!(state.update(fire, 0), (fire, 0));
+        assert_eq!(state.update(fire, fire), (0, 0));
+        assert_eq!(state.update(0, fire), (0, fire));
+        assert_eq!(state.update(0, 0), (0, 0));
+    }
+
+    #[test]
+    fn touch_release_keeps_held_keyboard_keys() {
+        let left = TouchAction::RotateLeft.bit();
+        let thrust = TouchAction::Thrust.bit();
+        let mut state = state();
+        // A is down on the keyboard before the touch starts
+        assert_eq!(state.update(left | thrust, left), (thrust, 0));
+        assert_eq!(state.update(left | thrust, left | thrust), (0, 0));
+        assert_eq!(state.update(0, left | thrust), (0, thrust));
+
+        // the keyboard lets go first, the touch puts the key back down and owns it from then on
+        assert_eq!(state.update(left, left), (0, 0));
+        assert_eq!(state.update(left, 0), (left, 0));
+        assert_eq!(state.update(0, left), (0, left));
+    }
+}
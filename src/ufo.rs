@@ -0,0 +1,346 @@
+use std::time::Duration;
+
+use brengin::cecs::prelude::*;
+use brengin::glam::{Vec2, Vec3};
+use brengin::renderer::sprite_renderer::sprite_sheet_bundle;
+use brengin::transform::{transform_bundle, GlobalTransform, Transform};
+use brengin::{DeltaTime, Timer};
+use smallvec::SmallVec;
+
+use crate::collision::{
+    aabb_bundle, raycast, AABBBuffer, ColliderKind, CollisionEvent, Collisions,
+};
+use crate::telegraph::{spawn_telegraph, SpawnTelegraph, TelegraphConfig, TelegraphKind};
+use crate::{
+    deactivate_bullet, hit_player, layers, nearest_ship, spawn_explosion, GameEntity, LifeTime,
+    MapConfig, Player, Plugin, Pooled, Rng, Score, Shield, SpawnClearance, Sprites, Threat,
+    TimeScale, Velocity, WrapBehavior, AABB, ASTEROID_TAG, BULLET_TAG, ENEMY_BULLET_TAG,
+    MISSILE_TAG, PLAYER_TAG, UFO_TAG,
+};
+
+/// How UFOs show up, move and shoot
+pub struct UfoConfig {
+    pub spawn_interval: Duration,
+    /// Time until a UFO that hasn't been shot down leaves
+    pub lifetime: Duration,
+    pub speed: f32,
+    /// How quickly the UFO turns onto the heading it picked, per second
+    pub steering: f32,
+    pub fire_interval: Duration,
+    pub bullet_speed: f32,
+    pub bullet_lifetime: Duration,
+    /// Weight of heading towards the player when picking a heading
+    pub aggression: f32,
+    /// Weight of keeping clear of asteroids when picking a heading
+    pub dodge: f32,
+    /// Distance the UFO looks ahead for asteroids
+    pub lookahead: f32,
+    /// Headings tried each frame, evenly spread around the UFO
+    pub samples: u32,
+    pub score: u64,
+    /// New asteroids keep at least this far from the UFO
+    pub spawn_clearance: f32,
+}
+
+impl Default for UfoConfig {
+    fn default() -> Self {
+        Self {
+            spawn_interval: Duration::from_secs(25),
+            lifetime: Duration::from_secs(20),
+            speed: 2.5,
+            steering: 2.0,
+            fire_interval: Duration::from_millis(1500),
+            bullet_speed: 6.0,
+            bullet_lifetime: Duration::from_secs(2),
+            aggression: 1.0,
+            dodge: 1.5,
+            lookahead: 4.0,
+            samples: 12,
+            score: 10,
+            spawn_clearance: 3.0,
+        }
+    }
+}
+
+pub struct Ufo {
+    /// Time until the UFO can fire again
+    pub cooldown: Duration,
+}
+
+pub struct EnemyBullet;
+
+struct UfoSpawner(pub Timer);
+
+/// One UFO at a time, entering from a random point on the edge of the map once its warning has
+/// blinked there for a while
+fn ufo_spawn_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    mut spawner: ResMut<UfoSpawner>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    mut rng: ResMut<Rng>,
+    map: Res<MapConfig>,
+    telegraph: Res<TelegraphConfig>,
+    q_ufo: Query<&(), With<Ufo>>,
+    q_telegraph: Query<&SpawnTelegraph>,
+    q_player: Query<&(), With<Player>>,
+) {
+    spawner.0.update(time_scale.scale(&dt));
+    if !spawner.0.just_finished() || !q_ufo.is_empty() || q_player.is_empty() {
+        return;
+    }
+    if q_telegraph.iter().any(|t| t.kind == TelegraphKind::Ufo) {
+        return;
+    }
+    let rng = &mut rng.0;
+    let along = (rng.f32() * 2.0 - 1.0) * map.radius;
+    let edge = if rng.bool() { map.radius } else { -map.radius };
+    let pos = if rng.bool() {
+        Vec2::new(edge, along)
+    } else {
+        Vec2::new(along, edge)
+    };
+    spawn_telegraph(&mut cmd, &sprites, &telegraph, TelegraphKind::Ufo, pos);
+}
+
+pub fn spawn_ufo(cmd: &mut Commands, sprites: &Sprites, config: &UfoConfig, pos: Vec2) {
+    cmd.spawn()
+        .insert_bundle(transform_bundle(Transform {
+            pos: pos.extend(layers::UFO),
+            scale: Vec3::splat(1.2),
+            ..Default::default()
+        }))
+        .insert_bundle(sprite_sheet_bundle(sprites.ufo_sheet.clone(), None))
+        .insert_bundle(aabb_bundle(
+            AABB::around_origin(Vec2::new(1.0, 0.4)),
+            UFO_TAG,
+            ColliderKind::Trigger,
+        ))
+        .insert_bundle((
+            Ufo {
+                cooldown: config.fire_interval,
+            },
+            GameEntity,
+            Threat,
+            Velocity::default(),
+            LifeTime(Timer::new(config.lifetime, false)),
+            SpawnClearance(config.spawn_clearance),
+        ));
+}
+
+/// Distance to the closest asteroid along `dir`, up to `lookahead`
+fn clearance(colliders: &AABBBuffer, pos: Vec2, dir: Vec2, lookahead: f32) -> f32 {
+    raycast(colliders, pos, dir, ASTEROID_TAG.src).map_or(lookahead, |(_, t)| t.min(lookahead))
+}
+
+/// No asteroid between `pos` and the target `to_target` away
+fn has_clear_shot(colliders: &AABBBuffer, pos: Vec2, to_target: Vec2) -> bool {
+    let distance = to_target.length();
+    raycast(colliders, pos, to_target / distance, ASTEROID_TAG.src)
+        .filter(|(_, t)| *t <= distance)
+        .is_none()
+}
+
+/// Picks among the sampled headings by how much it closes in on the player and how far it is
+/// from the next asteroid, weighted by [UfoConfig::aggression] and [UfoConfig::dodge]
+fn pick_heading(colliders: &AABBBuffer, config: &UfoConfig, pos: Vec2, chase: Vec2) -> Vec2 {
+    let score = |dir: Vec2| {
+        let clear = clearance(colliders, pos, dir, config.lookahead) / config.lookahead;
+        config.aggression * dir.dot(chase) + config.dodge * clear
+    };
+    (0..config.samples)
+        .map(|i| {
+            let angle = i as f32 / config.samples as f32 * std::f32::consts::TAU;
+            Vec2::new(angle.cos(), angle.sin())
+        })
+        .map(|dir| (dir, score(dir)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(chase, |(dir, _)| dir)
+}
+
+/// Steers the UFOs around asteroids towards the nearest ship, and fires when nothing is in the way
+///
+/// Runs after the collision plugin, so the broadphase holds this frame's colliders.
+fn ufo_ai_system(
+    dt: Res<DeltaTime>,
+    time_scale: Res<TimeScale>,
+    config: Res<UfoConfig>,
+    colliders: Res<AABBBuffer>,
+    sprites: Res<Sprites>,
+    mut cmd: Commands,
+    mut q_ufo: Query<(&Transform, &mut Velocity, &mut Ufo)>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+) {
+    let dt = time_scale.scale(&dt);
+    for (tr, vel, ufo) in q_ufo.iter_mut() {
+        let pos = tr.pos.truncate();
+        let to_player = nearest_ship(&q_player, pos)
+            .map(|player| player - pos)
+            .filter(|d| d.length_squared() > f32::EPSILON);
+        let chase = to_player.map_or(Vec2::ZERO, Vec2::normalize);
+        let heading = pick_heading(&colliders, &config, pos, chase);
+        let t = (config.steering * dt.as_secs_f32()).min(1.0);
+        vel.0 = vel.0.lerp(heading * config.speed, t);
+
+        ufo.cooldown = ufo.cooldown.saturating_sub(dt);
+        let Some(to_player) = to_player else {
+            continue;
+        };
+        // hold fire until the asteroids are out of the way
+        if !ufo.cooldown.is_zero() || !has_clear_shot(&colliders, pos, to_player) {
+            continue;
+        }
+        ufo.cooldown = config.fire_interval;
+        let dir = to_player.normalize();
+        cmd.spawn()
+            .insert_bundle(transform_bundle(Transform {
+                pos: (pos + dir * 0.8).extend(tr.pos.z),
+                scale: Vec3::splat(0.3),
+                ..Default::default()
+            }))
+            .insert_bundle(sprite_sheet_bundle(
+                sprites.enemy_bullet_sheet.clone(),
+                None,
+            ))
+            .insert_bundle(aabb_bundle(
+                AABB::around_origin(Vec2::splat(0.6)),
+                ENEMY_BULLET_TAG,
+                ColliderKind::Trigger,
+            ))
+            .insert_bundle((
+                EnemyBullet,
+                GameEntity,
+                WrapBehavior::Despawn,
+                Velocity(dir * config.bullet_speed),
+                LifeTime(Timer::new(config.bullet_lifetime, false)),
+            ));
+    }
+}
+
+fn ufo_collision_system(
+    collisions: Res<Collisions>,
+    mut cmd: Commands,
+    sprites: Res<Sprites>,
+    config: Res<UfoConfig>,
+    mut score: ResMut<Score>,
+    q_ufo: Query<&GlobalTransform, With<Ufo>>,
+    q_pooled: Query<&(), With<Pooled>>,
+    mut q_shield: Query<&mut Shield>,
+) {
+    // a UFO can be hit by more than one thing in a frame, it only goes down once, same for the
+    // ships
+    let mut destroyed: SmallVec<[EntityId; 2]> = SmallVec::new();
+    let mut downed: SmallVec<[EntityId; 2]> = SmallVec::new();
+    for event in collisions.iter() {
+        let CollisionEvent {
+            mut entity_1,
+            mut tag1,
+            mut entity_2,
+            mut tag2,
+            ..
+        } = *event;
+        if tag2 == UFO_TAG || tag2 == ENEMY_BULLET_TAG {
+            std::mem::swap(&mut entity_1, &mut entity_2);
+            std::mem::swap(&mut tag1, &mut tag2);
+        }
+        if tag1 == ENEMY_BULLET_TAG && tag2 == PLAYER_TAG {
+            cmd.delete(entity_1);
+            if !downed.contains(&entity_2) && !hit_player(&mut cmd, entity_2, &mut q_shield) {
+                downed.push(entity_2);
+            }
+            continue;
+        }
+        if tag1 != UFO_TAG || destroyed.contains(&entity_1) {
+            continue;
+        }
+        if tag2 == BULLET_TAG || tag2 == MISSILE_TAG {
+            if q_pooled.fetch(entity_2).is_some() {
+                deactivate_bullet(cmd.entity(entity_2));
+            } else {
+                cmd.delete(entity_2);
+            }
+            score.score += config.score;
+        } else if tag2 == PLAYER_TAG {
+            if !downed.contains(&entity_2) && !hit_player(&mut cmd, entity_2, &mut q_shield) {
+                downed.push(entity_2);
+            }
+        } else if tag2 != ASTEROID_TAG {
+            continue;
+        }
+        destroyed.push(entity_1);
+        if let Some(tr) = q_ufo.fetch(entity_1) {
+            spawn_explosion(cmd.spawn(), &sprites, tr.0.pos, tr.0.scale.x);
+        }
+        cmd.delete(entity_1);
+    }
+}
+
+pub struct UfoPlugin;
+
+impl Plugin for UfoPlugin {
+    fn build(self, app: &mut crate::App) {
+        let config = UfoConfig::default();
+        app.stage(crate::Stage::Update).add_system(ufo_spawn_system);
+        app.stage(crate::Stage::PostUpdate)
+            .add_system(ufo_ai_system)
+            .add_system(ufo_collision_system);
+        app.insert_resource(UfoSpawner(Timer::new(config.spawn_interval, true)));
+        app.insert_resource(config);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Broadphase buffer with an asteroid at each of `asteroids` and the player at `player`
+    fn scene(asteroids: &[Vec2], player: Vec2) -> AABBBuffer {
+        let mut world = brengin::cecs::World::new(asteroids.len() as u32 + 1);
+        let mut buffer: Vec<_> = asteroids
+            .iter()
+            .map(|pos| {
+                let aabb = AABB::around_point(*pos, Vec2::splat(2.0));
+                (world.insert_entity(), aabb, ASTEROID_TAG, false)
+            })
+            .collect();
+        let aabb = AABB::around_point(player, Vec2::ONE);
+        buffer.push((world.insert_entity(), aabb, PLAYER_TAG, false));
+        AABBBuffer(buffer)
+    }
+
+    #[test]
+    fn holds_fire_with_an_asteroid_in_between() {
+        let ufo = Vec2::new(-5.0, 0.0);
+        let player = Vec2::new(5.0, 0.0);
+        let to_player = player - ufo;
+        assert!(!has_clear_shot(
+            &scene(&[Vec2::ZERO], player),
+            ufo,
+            to_player
+        ));
+        // grazing the line of fire still blocks it
+        assert!(!has_clear_shot(
+            &scene(&[Vec2::new(1.0, 0.9)], player),
+            ufo,
+            to_player
+        ));
+    }
+
+    #[test]
+    fn fires_past_asteroids_off_the_line() {
+        let ufo = Vec2::new(-5.0, 0.0);
+        let player = Vec2::new(5.0, 0.0);
+        let to_player = player - ufo;
+        assert!(has_clear_shot(&scene(&[], player), ufo, to_player));
+        // beside the line, behind the player and behind the UFO
+        for asteroid in [
+            Vec2::new(0.0, 3.0),
+            Vec2::new(9.0, 0.0),
+            Vec2::new(-9.0, 0.0),
+        ] {
+            let colliders = scene(&[asteroid], player);
+            assert!(has_clear_shot(&colliders, ufo, to_player), "{asteroid}");
+        }
+    }
+}